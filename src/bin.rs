@@ -20,6 +20,9 @@ struct Cli {
     #[clap(short, long)]
     progress: bool, 
 
+    #[clap(long)]
+    parallel: bool, 
+
     #[clap(short, long)]
     debug: bool 
 }
@@ -38,6 +41,10 @@ enum Commands {
         #[clap(value_parser)]
         range: String 
     }, 
+    Verify {
+        #[clap(long, default_value_t = 1e-9)]
+        tolerance: f64 
+    }, 
 }
 
 // 16355
@@ -46,10 +53,13 @@ enum Commands {
 
 fn main() -> Result<(), Error> {
     let cli = Cli::parse();
-    let mut book: Book = Book::from(cli.path); 
-    book.load(cli.progress)?; 
+    let mut book: Book = Book::open(&cli.path)?; 
+    match cli.parallel {
+        true => book.load_parallel(cli.progress)?, 
+        false => book.load(cli.progress)?
+    }
     match &cli.command {
-        Some(Commands::Load) => { book.load(cli.progress)?}, 
+        Some(Commands::Load) => {}, 
         Some(Commands::Deps) => { 
             println!("{}", book.dependencies); 
         }, 
@@ -72,6 +82,13 @@ fn main() -> Result<(), Error> {
         Some(Commands::Calculate {range}) => {
             book.calculate(cli.debug, cli.progress)?; 
             println!("{:?}", book.resolve_str_ref(range)); 
+        }, 
+        Some(Commands::Verify {tolerance}) => {
+            let verification = book.verify(*tolerance, cli.progress)?; 
+            print!("{}", verification); 
+            if !verification.is_ok() {
+                std::process::exit(1); 
+            }
         }
         _ => {}
     }
@@ -0,0 +1,108 @@
+use std::path::Path; 
+use chrono::{NaiveDate, NaiveDateTime}; 
+
+use crate::evaluate::value::Value; 
+
+// Tab for .tsv and .tab files, comma otherwise
+pub fn delimiter_for(path: &str) -> char {
+    match Path::new(path).extension().and_then(|x| x.to_str()).map(|x| x.to_ascii_lowercase()).as_deref() {
+        Some("tsv") | Some("tab") => '\t', 
+        _ => ','
+    }
+}
+
+// Splits RFC 4180 text into records, quoted fields may hold delimiters, "" and line breaks
+pub fn read_records(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records: Vec<Vec<String>> = vec![]; 
+    let mut record: Vec<String> = vec![]; 
+    let mut field = String::new(); 
+    let mut quoted = false; 
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable(); 
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"'); 
+                chars.next(); 
+            }, 
+            '"' if quoted => quoted = false, 
+            '"' if field.is_empty() => quoted = true, 
+            c if quoted => field.push(c), 
+            c if c == delimiter => record.push(std::mem::take(&mut field)), 
+            '\r' if chars.peek() == Some(&'\n') => {}, 
+            '\n' | '\r' => {
+                record.push(std::mem::take(&mut field)); 
+                records.push(std::mem::take(&mut record)); 
+            }, 
+            c => field.push(c)
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field); 
+        records.push(record); 
+    }
+    records
+}
+
+// Numbers, percentages, booleans and ISO dates become values, everything else is text
+pub fn infer_value(field: &str) -> Value {
+    let trimmed = field.trim(); 
+    if trimmed.is_empty() {
+        return Value::Empty; 
+    }
+    match trimmed.to_ascii_uppercase().as_str() {
+        "TRUE" => return Value::Bool(true), 
+        "FALSE" => return Value::Bool(false), 
+        _ => {}
+    }
+    if let Some(n) = parse_number(trimmed) {
+        return Value::Num(n); 
+    }
+    if let Some(n) = trimmed.strip_suffix('%').and_then(parse_number) {
+        return Value::Num(n / 100.0); 
+    }
+    if let Ok(d) = NaiveDate::parse_from_str(trimmed, "%Y-%m-%d") {
+        return Value::from(d); 
+    }
+    for format in ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(trimmed, format) {
+            return Value::from(dt); 
+        }
+    }
+    Value::Text(field.to_string())
+}
+
+// Plain decimal or scientific notation, f64::from_str alone would also accept inf and NaN
+fn parse_number(text: &str) -> Option<f64> {
+    if !text.chars().all(|c| c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | 'e' | 'E')) {
+        return None; 
+    }
+    text.parse::<f64>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::csv::{delimiter_for, infer_value, read_records}; 
+    use crate::evaluate::value::Value; 
+    use chrono::NaiveDate; 
+
+    #[test]
+    fn test_read_records() {
+        assert_eq!(read_records("a,b\r\n1,\"x, \"\"y\"\"\"\n", ','), vec![vec!["a", "b"], vec!["1", "x, \"y\""]]); 
+        assert_eq!(read_records("\u{feff}a\tb\n\"line\nbreak\"\t", '\t'), vec![vec!["a", "b"], vec!["line\nbreak", ""]]); 
+        assert_eq!(read_records("a,,c", ','), vec![vec!["a", "", "c"]]); 
+        assert_eq!(delimiter_for("data/export.TSV"), '\t'); 
+        assert_eq!(delimiter_for("data/export.csv"), ','); 
+    }
+
+    #[test]
+    fn test_infer_value() {
+        assert_eq!(infer_value("1.5e3"), Value::from(1500.0)); 
+        assert_eq!(infer_value(" -2 "), Value::from(-2.0)); 
+        assert_eq!(infer_value("25%"), Value::from(0.25)); 
+        assert_eq!(infer_value("true"), Value::from(true)); 
+        assert_eq!(infer_value("2024-02-29"), Value::from(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap())); 
+        assert_eq!(infer_value("2024-02-29 12:30:00"), Value::from(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap().and_hms_opt(12, 30, 0).unwrap())); 
+        assert_eq!(infer_value("inf"), Value::from("inf")); 
+        assert_eq!(infer_value(""), Value::Empty); 
+    }
+}
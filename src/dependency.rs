@@ -6,10 +6,10 @@ use petgraph::{
 }; 
 use std::{fmt, cmp::Ordering}; 
 use crate::{
-    workbook::Sheet,
+    workbook::{Book, Sheet, ExternalLink},
     parser::{
         parse_str, 
-        ast::Expr
+        ast::{Expr, Infix}
     }, 
     reference::Reference, 
     errors::Error,
@@ -79,70 +79,122 @@ impl DependencyTree {
         DependencyTree { tree: DiGraphMap::new(), offsets: vec![] }
     }
 
-    pub fn add_formula(&mut self, cell: CellId, formula_text: &str, sheets: &Vec<Sheet>) -> Result<(), Error> {
+    pub fn add_formula(&mut self, cell: CellId, formula_text: &str, book: &Book) -> Result<(), Error> {
         let mut chars = formula_text.chars();
         chars.next(); // FIXME: Parse can't handle the = in the front of a formula
         let expression: Expr = parse_str(chars.as_str())?;
-        self.add_expression(cell, expression, sheets)?; 
-        Ok(())
+        self.add_parsed_formula(cell, expression, book)
+    }
+
+    // Formulas are expected in A1, R1C1 references are converted when the book is loaded 
+    pub fn add_parsed_formula(&mut self, cell: CellId, expression: Expr, book: &Book) -> Result<(), Error> {
+        self.add_cell(cell); // Formulas without precedents still need calculating
+        self.add_expression(cell, expression, book)
     }
 
-    pub fn add_expression(&mut self, cell: CellId, expression: Expr, sheets: &Vec<Sheet>) -> Result<(), Error> {
+    pub fn add_expression(&mut self, cell: CellId, expression: Expr, book: &Book) -> Result<(), Error> {
         match expression {
+            // Cells of other workbooks are not part of this tree 
+            Expr::Reference { sheet: Some(ref s), .. } | Expr::Name { sheet: Some(ref s), .. } 
+                | Expr::MultiSheetReference { sheets: (ref s, _), .. } if ExternalLink::split(s).is_some() => {}, 
+            Expr::MultiSheetReference { sheets: (first, last), reference } => {
+                for sheet in book.get_sheet_span(&first, &last).unwrap_or_default() {
+                    self.add_expression(cell, Expr::Reference { sheet: Some(sheet), reference: reference.clone() }, book)?; 
+                }
+            }, 
             Expr::Reference { sheet, reference } => {
                 let sheet_id = match sheet {
-                    Some(s) => {
-                        sheets.iter().position(|x|  {
-                            x.name == s
-                        }).unwrap()
+                    Some(s) => match book.sheets.iter().position(|x| x.name == s) {
+                        Some(idx) => idx, 
+                        None => return Ok(()) // #REF! 
                     }, 
                     None => cell.sheet
                 }; 
-                let sheet: &Sheet = sheets.get(sheet_id).unwrap(); 
+                let sheet: &Sheet = book.sheets.get(sheet_id).unwrap(); 
                 let reference = Reference::from(reference); 
                 let (mut start_row, mut start_col, mut num_rows, mut num_cols) = reference.get_dimensions(); 
                 start_row = start_row.max(1); 
                 start_col = start_col.max(1); 
-                num_rows = num_rows.min(sheet.max_rows);
-                num_cols = num_cols.min(sheet.max_columns); 
+                // Ranges stop at the last stored cell so that whole rows and columns only cover the used cells 
+                let (stored_rows, stored_columns) = sheet.values.dim(); 
+                num_rows = num_rows.min((stored_rows + 1).saturating_sub(start_row)); 
+                num_cols = num_cols.min((stored_columns + 1).saturating_sub(start_col)); 
+                // Cells inside an array formula block or spill range depend on the formula that fills them 
+                let cell_node = |row: usize, column: usize| {
+                    book.get_array_formula(sheet_id, row, column)
+                        .or_else(|| book.get_spill(sheet_id, row, column).map(|x| x.anchor))
+                        .unwrap_or(CellId::from((sheet_id, row, column, 1, 1, true)))
+                }; 
                 let pre_cell: CellId; 
                 if reference.is_multi_cell() {
                     pre_cell = CellId::from((sheet_id, start_row, start_col, num_rows, num_cols, false)); 
                     if ! self.cell_exists(&pre_cell) {
                         for c in Reference::get_cells_from_dim(start_row, start_col, num_rows, num_cols) {
-                            let sub_cell = CellId::from((sheet_id, c.0, c.1, 1, 1, true)); 
+                            let sub_cell = cell_node(c.0, c.1); 
                             if sub_cell != pre_cell {
                                 self.add_precedent(&sub_cell, &pre_cell); 
                             }
                         }
                     }
                 } else {
-                    pre_cell = CellId::from((sheet_id, start_row, start_col, num_rows, num_cols, true)); 
+                    pre_cell = cell_node(start_row, start_col); 
                 }
                 if pre_cell != cell {
                     self.add_precedent(&pre_cell, &cell); 
                 }
             },
+            Expr::Name { sheet, name } => {
+                let sheet_id = match sheet {
+                    Some(s) => match book.sheets.iter().position(|x| x.name == s) {
+                        Some(idx) => idx, 
+                        None => return Ok(()) // #REF! 
+                    }, 
+                    None => cell.sheet
+                }; 
+                if let Some(defined_name) = book.get_defined_name(&name, sheet_id) 
+                    && !book.is_circular_name(&name, Some(sheet_id)) {
+                    self.add_expression(cell, defined_name.expression()?, book)?; 
+                }
+            }, 
+            Expr::StructuredReference { .. } => {
+                let reference = book.resolve_structured_reference(&expression, cell.sheet, cell.row, cell.column); 
+                self.add_expression(cell, reference, book)?; 
+            }, 
+            Expr::Infix(Infix::Range | Infix::Intersect | Infix::Union, ref a, ref b) => {
+                // Plain references depend on the cells they combine into, INDEX and OFFSET operands 
+                // add the rest once they are evaluated 
+                match Book::reference_areas(&expression) {
+                    Some(areas) => {
+                        for (sheet, reference) in areas {
+                            self.add_expression(cell, Expr::Reference { sheet, reference: reference.to_string() }, book)?; 
+                        }
+                    }, 
+                    None => {
+                        self.add_expression(cell, *a.clone(), book)?; 
+                        self.add_expression(cell, *b.clone(), book)?; 
+                    }
+                }
+            }, 
             Expr::Infix(_, a, b) => {
-                self.add_expression(cell, *a, sheets)?; 
-                self.add_expression(cell, *b, sheets)?; 
+                self.add_expression(cell, *a, book)?; 
+                self.add_expression(cell, *b, book)?; 
             }, 
-            Expr::Prefix(_, a) => {
-                self.add_expression(cell, *a, sheets)?; 
+            Expr::Prefix(_, a) | Expr::Postfix(_, a) => {
+                self.add_expression(cell, *a, book)?; 
             }, 
             Expr::Func { name, args } => {
                 if name.as_str() == "OFFSET" {
                     let mut offset_args = args.clone(); 
                     offset_args.remove(0); 
-                    self.add_expression(cell, Expr::Array(offset_args), sheets)?; 
+                    self.add_expression(cell, Expr::Array(offset_args), book)?; 
                 }
                 for arg in args.into_iter() {
-                    self.add_expression(cell, arg, sheets)?; 
+                    self.add_expression(cell, arg, book)?; 
                 }
             }, 
             Expr::Array(arr) => {
                 for a in arr.into_iter() {
-                    self.add_expression(cell, a, sheets)?; 
+                    self.add_expression(cell, a, book)?; 
                 }
             }, 
             _ => {}
@@ -1,4 +1,6 @@
+use std::fmt; 
 use thiserror::Error; 
+use quick_xml::events::attributes::AttrError; 
 use crate::dependency::CellId; 
 use crate::parser::ast::Expr; 
 
@@ -13,9 +15,87 @@ pub enum Error {
     #[error("Unable to parse str {0}")]
     UnableToParse(String), 
 
-    #[error("Unable to lex str {0}")]
-    UnableToLex(String), 
+    #[error("Unable to lex formula: {0}")]
+    UnableToLex(Diagnostic), 
+
+    #[error("Unable to parse formula: {0}")]
+    InvalidFormula(Diagnostic), 
+
+    #[error("Cell {0} has an invalid value {1}")]
+    InvalidValue(String, String), 
+
+    #[error("Attribute {0} has an invalid value {1}")]
+    InvalidAttribute(String, String), 
+
+    #[error("Unable to read workbook: {0}")]
+    Io(#[from] std::io::Error), 
+
+    #[error("Unable to read workbook package: {0}")]
+    Zip(#[from] zip::result::ZipError), 
+
+    #[error("Unable to parse workbook XML: {0}")]
+    Xml(#[from] quick_xml::Error), 
+
+    #[error("Binary record {0} is truncated")]
+    InvalidRecord(u16), 
+
+    #[error("Workbook was not loaded from a package")]
+    MissingPackage, 
+
+    #[error("Package is missing part {0}")]
+    MissingPart(String), 
+
+    #[error("Saving {0} packages is not supported")]
+    UnsupportedSave(String), 
+
+    #[error("Spill range of {0} changed.")]
+    Spill(CellId), 
 
     #[error("Dependency tree changed.")]
     Volatile(Box<Expr>)
 }
+
+impl From<AttrError> for Error {
+    fn from(e: AttrError) -> Error {
+        Error::Xml(quick_xml::Error::from(e))
+    }
+}
+
+// Where a formula stopped lexing or parsing and the tokens that could have come next 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub formula: String, 
+    pub offset: usize, // Byte offset into the formula 
+    pub expected: Vec<String>, 
+    pub found: String 
+}
+
+impl Diagnostic {
+    // The formula around the offset with a caret underneath, long formulas are cut to the part that matters 
+    pub fn snippet(&self) -> String {
+        const CONTEXT: usize = 40; 
+        let mut start: usize = self.offset.saturating_sub(CONTEXT); 
+        while !self.formula.is_char_boundary(start) {
+            start -= 1; 
+        }
+        let mut end: usize = (self.offset + CONTEXT).min(self.formula.len()); 
+        while !self.formula.is_char_boundary(end) {
+            end += 1; 
+        }
+        let prefix: &str = if start > 0 { "..." } else { "" }; 
+        let suffix: &str = if end < self.formula.len() { "..." } else { "" }; 
+        let caret: usize = prefix.len() + self.formula[start..self.offset].chars().count(); 
+        format!("{}{}{}\n{}^", prefix, &self.formula[start..end], suffix, " ".repeat(caret))
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.expected.as_slice() {
+            [] => write!(f, "unexpected {} at offset {}", self.found, self.offset)?, 
+            [expected] => write!(f, "expected {} but found {} at offset {}", expected, self.found, self.offset)?, 
+            [rest @ .., last] => write!(f, "expected {} or {} but found {} at offset {}", rest.join(", "), last, self.found, self.offset)?
+        }
+        write!(f, "\n{}", self.snippet())
+    }
+}
@@ -1,13 +1,15 @@
+use ndarray::Array2; 
 use crate::{
     parser::{
-        ast::{Literal, Prefix, Infix, Expr}, 
+        ast::{Literal, Prefix, Postfix, Infix, Expr, Error as ExcelError}, 
         parse_str
     }, 
     function::*, 
-    workbook::Book,  
+    workbook::{Book, ExternalLink},  
     evaluate::value::Value, 
     reference::Reference, 
     errors::Error, 
+    utils::DateSystem, 
 }; 
 
 pub mod value; 
@@ -33,15 +35,19 @@ pub fn evaluate_expr(expr: Expr) -> Result<Value, Error> {
                     }
                 }
 				Literal::Boolean(b) => Value::from(b), 
-				Literal::Text(s) => Value::from(s)
+				Literal::Text(s) => Value::from(s), 
+				Literal::Empty => Value::Empty
 			}
 		},
 		Expr::Prefix(p, box_expr) => { 
 			match p {
 				Prefix::Plus => Value::from(evaluate_expr(*box_expr)?.as_num().abs()),
-				Prefix::Minus => evaluate_expr(*box_expr)? * Value::from(-1.0)
+				Prefix::Minus => evaluate_expr(*box_expr)? * Value::from(-1.0), 
+				Prefix::ImplicitIntersection => evaluate_expr(*box_expr)?.ensure_single()
 			}
 		}, 
+        Expr::Postfix(Postfix::Percent, box_expr) => percent(evaluate_expr(*box_expr)?), 
+        Expr::Postfix(Postfix::Spill, _) => Value::Error(ExcelError::Ref), // Spill ranges need a workbook
 		Expr::Infix(i, a, b) => {
             let a = evaluate_expr(*a)?; 
             let b = evaluate_expr(*b)?; 
@@ -62,6 +68,7 @@ pub fn evaluate_expr(expr: Expr) -> Result<Value, Error> {
                     Infix::LessThanEqual => Value::from(a <= b), 
                     Infix::GreaterThan => Value::from(a > b), 
                     Infix::GreaterThanEqual => Value::from(a >= b), 
+                    Infix::Range | Infix::Intersect | Infix::Union => Value::Error(ExcelError::Value), // Only references combine 
                     Infix::Ampersand => {
                         let value = if a.is_array() {
                             Value::from(a.as_array().into_iter().map(|x| Value::from(format!("{}{}", x.as_text(), b.as_text()))).collect::<Vec<Value>>())
@@ -77,6 +84,8 @@ pub fn evaluate_expr(expr: Expr) -> Result<Value, Error> {
         }, 
         Expr::Array(x) => Value::Array(x.into_iter().map(|x| evaluate_expr(x).unwrap()).collect::<Vec<Value>>()), 
         Expr::Error(err) => Value::Error(err), 
+        Expr::Name { sheet: _, name: _ } => Value::Error(ExcelError::Name), // Names need a workbook
+        Expr::StructuredReference { .. } => Value::Error(ExcelError::Ref), // Tables need a workbook
         _ => panic!("Expression {:?} does not convert to a value.", expr)  
 	}; 
     Ok(value)
@@ -101,7 +110,29 @@ pub fn offset_expr(args: Vec<Expr>, book: &Book, debug: bool) -> Result<Expr, Er
     }
 }
 
+// Booleans and empty cells count as numbers, text has to read as one 
+fn percent(value: Value) -> Value {
+    match value.ensure_single() {
+        Value::Num(_) | Value::Bool(_) | Value::Empty => Value::from(value.as_num() / 100.0), 
+        Value::Text(t) => match t.trim().parse::<f64>() {
+            Ok(x) => Value::from(x / 100.0), 
+            Err(_) => Value::Error(ExcelError::Value)
+        }, 
+        Value::Error(e) => Value::Error(e), 
+        _ => Value::Error(ExcelError::Value)
+    }
+}
+
+// Functions that take any number of values 
+fn takes_areas(name: &str) -> bool {
+    matches!(name, "SUM" | "AVERAGE" | "COUNT" | "MAX" | "MIN" | "CONCAT" | "AND" | "OR")
+}
+
+// Unions outside of an aggregate read their areas one after another 
 pub fn ensure_non_range(value: Value) -> Value {
+    if let Value::Areas(areas) = value {
+        return Value::from(areas.into_iter().flat_map(|x| ensure_non_range(x).as_array()).collect::<Vec<Value>>()); 
+    }
     if let Value::Range { sheet: _, reference: _, value } = value {
         if let Some(value) = value {
             return *value; 
@@ -113,8 +144,134 @@ pub fn ensure_non_range(value: Value) -> Value {
     }
 }
 
+// Dates multiply and divide as serials in the book's own date system 
+fn as_serials(value: Value, date_system: DateSystem) -> Value {
+    match value {
+        Value::Date(_) | Value::DateTime(_) => Value::from(value.as_serial(date_system)), 
+        Value::Array2(arr2) => Value::from(arr2.mapv(|x| as_serials(x, date_system))), 
+        value => value
+    }
+}
+
+fn evaluate_infix(i: Infix, a: Value, b: Value) -> Value {
+    if a.is_err() {
+        a
+    } else if b.is_err() {
+        b
+    } else {
+        match i {
+            Infix::Plus => a.ensure_single() + b.ensure_single(), 
+            Infix::Minus => a.ensure_single() - b.ensure_single(), 
+            Infix::Multiply => a.ensure_single() * b.ensure_single(), 
+            Infix::Divide => a.ensure_single() / b.ensure_single(), 
+            Infix::Exponent => Exponent {a, b}.evaluate(), 
+            Infix::NotEqual => Value::from(a.ensure_single() != b.ensure_single()), 
+            Infix::Equal => Value::from(a.ensure_single() == b.ensure_single()), 
+            Infix::LessThan => Value::from(a.ensure_single() < b.ensure_single()), 
+            Infix::LessThanEqual => Value::from(a.ensure_single() <= b.ensure_single()), 
+            Infix::GreaterThan => Value::from(a.ensure_single() > b.ensure_single()), 
+            Infix::GreaterThanEqual => Value::from(a.ensure_single() >= b.ensure_single()), 
+            Infix::Range | Infix::Intersect | Infix::Union => Value::Error(ExcelError::Value), 
+            Infix::Ampersand => {
+                if a.is_array() {
+                    Value::from(a.as_array().into_iter().map(|x| Value::from(format!("{}{}", x.as_text(), b.as_text()))).collect::<Vec<Value>>())
+                } else if b.is_array() {
+                    Value::from(b.as_array().into_iter().map(|x| Value::from(format!("{}{}", a.as_text(), x.as_text()))).collect::<Vec<Value>>())
+                } else {
+                    Value::from(format!("{}{}", a.as_text(), b.as_text()))
+                }
+            },
+        }
+    }
+}
+
+// Arrays combine element by element, single rows and columns are repeated to fill the larger shape 
+fn evaluate_array_infix(i: Infix, a: Value, b: Value) -> Value {
+    let (a_rows, a_cols) = a.dimensions(); 
+    let (b_rows, b_cols) = b.dimensions(); 
+    let shape = (a_rows.max(b_rows), a_cols.max(b_cols)); 
+    Value::from(Array2::from_shape_fn(shape, |(row, column)| {
+        match (a.broadcast_get(row, column), b.broadcast_get(row, column)) {
+            (Some(x), Some(y)) => evaluate_infix(i.clone(), x, y), 
+            _ => Value::Error(ExcelError::NA)
+        }
+    }))
+}
+
+// A1:INDEX(B:B, 5), B:B 3:3 and (A1:A3,C1:C3) combine the references on either side. 
+// A union has no single area, it keeps the ranges it was made of 
+fn evaluate_reference_infix(i: Infix, a: Expr, b: Expr, book: &Book, debug: bool) -> Result<Value, Error> {
+    // OFFSET keeps its reference here instead of giving back the values 
+    let operand = |expr: Expr| match expr {
+        Expr::Func { name, args } if name == "OFFSET" => offset(args, book, debug), 
+        expr => evaluate_expr_with_context(expr, book, debug)
+    }; 
+    let a = operand(a)?; 
+    let b = operand(b)?; 
+    if a.is_err() {
+        return Ok(a); 
+    } else if b.is_err() {
+        return Ok(b); 
+    }
+    if i == Infix::Union {
+        let areas: Vec<Value> = [a, b].into_iter().flat_map(|x| match x {
+            Value::Areas(areas) => areas, 
+            x => vec![x]
+        }).collect(); 
+        return Ok(match areas.iter().all(|x| x.is_range()) {
+            true => Value::Areas(areas), 
+            false => Value::Error(ExcelError::Value)
+        }); 
+    }
+    match (a, b) {
+        (Value::Range { sheet: sheet_a, reference: reference_a, .. }, Value::Range { sheet: sheet_b, reference: reference_b, .. }) => {
+            if sheet_a.is_some() && sheet_b.is_some() && sheet_a != sheet_b {
+                return Ok(Value::Error(ExcelError::Value)); 
+            }
+            let reference: Option<Reference> = match i {
+                Infix::Range => Some(reference_a.span(&reference_b)), 
+                _ => reference_a.intersect(&reference_b)
+            }; 
+            match reference {
+                Some(r) => {
+                    let expr = Expr::Reference { sheet: sheet_a.or(sheet_b), reference: r.to_string() }; 
+                    // Cells only reached through INDEX or OFFSET become dependencies once they are known 
+                    if book.is_calculated(expr.clone()) {
+                        evaluate_expr_with_context(expr, book, debug)
+                    } else {
+                        Err(Error::Volatile(Box::new(expr)))
+                    }
+                }, 
+                None => Ok(Value::Error(ExcelError::Null))
+            }
+        }, 
+        _ => Ok(Value::Error(ExcelError::Value))
+    }
+}
+
+// @ keeps the value in the same row or column as the formula cell 
+fn implicit_intersection(value: Value, (row, column): (usize, usize)) -> Value {
+    if let Value::Range { sheet: _, reference, value: Some(range_value) } = value {
+        let arr = range_value.as_array2(); 
+        let (start_row, start_col, _, _) = reference.get_dimensions(); 
+        let (num_rows, num_cols) = arr.dim(); 
+        let row_idx = if num_rows == 1 { Some(0) } else { row.checked_sub(start_row.max(1)).filter(|x| *x < num_rows) }; 
+        let col_idx = if num_cols == 1 { Some(0) } else { column.checked_sub(start_col.max(1)).filter(|x| *x < num_cols) }; 
+        match (row_idx, col_idx) {
+            (Some(r), Some(c)) => arr[[r, c]].clone(), 
+            _ => Value::Error(ExcelError::Value)
+        }
+    } else {
+        ensure_non_range(value).ensure_single()
+    }
+}
+
 pub fn evaluate_expr_with_context(expr: Expr, book: &Book, debug: bool) -> Result<Value, Error> {
     let value = match expr.clone() {
+        // Sheets that are not in the workbook, such as in A1:A3 Deleted!A2 
+        Expr::Reference { sheet: Some(ref s), .. } if ExternalLink::split(s).is_none() && !book.sheets.iter().any(|x| &x.name == s) => {
+            Value::Error(ExcelError::Ref)
+        }, 
         Expr::Reference { ref sheet, ref reference } => {
             let range_value: Option<Box<Value>> = match book.resolve_ref(expr.clone()) {
                 Ok(arr2) => Some(Box::new(Value::from(arr2))), 
@@ -122,6 +279,38 @@ pub fn evaluate_expr_with_context(expr: Expr, book: &Book, debug: bool) -> Resul
             }; 
             Value::Range { sheet: sheet.clone(), reference: Reference::from(reference.clone()), value: range_value }
 		}, 
+        // 3D references only feed aggregates, the cells of every sheet are read as one list like a union 
+        Expr::MultiSheetReference { sheets: (ref first, ref last), .. } => {
+            match book.get_sheet_span(first, last) {
+                Some(_) => Value::from(book.resolve_ref(expr.clone())?.into_iter().collect::<Vec<Value>>()), 
+                None => Value::Error(ExcelError::Ref)
+            }
+        }, 
+        Expr::Name { sheet: Some(ref s), ref name } if ExternalLink::split(s).is_some() => {
+            match book.get_external_name(s, name) {
+                Some(expr) => evaluate_expr_with_context(expr, book, debug)?, 
+                None => Value::Error(ExcelError::Ref)
+            }
+        }, 
+        Expr::Name { ref sheet, ref name } => {
+            let sheet_idx: usize = match sheet {
+                Some(s) => match book.sheets.iter().position(|x| &x.name == s) {
+                    Some(idx) => idx, 
+                    None => return Ok(Value::Error(ExcelError::Ref))
+                }, 
+                None => book.current_sheet
+            }; 
+            match book.get_defined_name(name, sheet_idx) {
+                Some(_) if book.is_circular_name(name, Some(sheet_idx)) => Value::Error(ExcelError::Ref), 
+                Some(defined_name) => evaluate_expr_with_context(defined_name.expression()?, book, debug)?, 
+                None => Value::Error(ExcelError::Name)
+            }
+        }, 
+        Expr::StructuredReference { .. } => {
+            let (row, column) = book.current_cell; 
+            let reference = book.resolve_structured_reference(&expr, book.current_sheet, row, column); 
+            evaluate_expr_with_context(reference, book, debug)?
+        }, 
         Expr::Func {name, args} => {
             match name.as_str() {
                 "OFFSET" => {
@@ -139,8 +328,23 @@ pub fn evaluate_expr_with_context(expr: Expr, book: &Book, debug: bool) -> Resul
                 "INDEX" => {
                     index(args, book, debug)?
                 }, 
+                "AREAS" if args.len() == 1 => {
+                    match evaluate_expr_with_context(args[0].clone(), book, debug)? {
+                        Value::Areas(areas) => Value::from(areas.len()), 
+                        Value::Range { .. } => Value::from(1.0), 
+                        Value::Error(e) => Value::Error(e), 
+                        _ => Value::Error(ExcelError::Value)
+                    }
+                }, 
                 c => {
-                    let arg_values: Vec<Value> = args.into_iter().map(|x| ensure_non_range(evaluate_expr_with_context(x, book, debug).unwrap())).collect::<Vec<Value>>(); 
+                    let mut arg_values: Vec<Value> = vec![]; 
+                    for x in args.into_iter() {
+                        match evaluate_expr_with_context(x, book, debug).unwrap() {
+                            // Aggregates read each area of a union as its own argument, SUM((A1:A2,C1:C2)) is SUM(A1:A2, C1:C2) 
+                            Value::Areas(areas) if takes_areas(c) => arg_values.extend(areas.into_iter().map(ensure_non_range)), 
+                            value => arg_values.push(ensure_non_range(value))
+                        }
+                    }
                     get_function_value(c, arg_values)?
                 }
             }
@@ -149,51 +353,44 @@ pub fn evaluate_expr_with_context(expr: Expr, book: &Book, debug: bool) -> Resul
 			match lit {
 				Literal::Number(f) => Value::from(f), 
 				Literal::Boolean(b) => Value::from(b), 
-				Literal::Text(s) => Value::from(s)
+				Literal::Text(s) => Value::from(s), 
+				Literal::Empty => Value::Empty
 			}
 		},
 		Expr::Prefix(p, box_expr) => { 
-            let a: Value = ensure_non_range(evaluate_expr_with_context(*box_expr, book, debug)?);
+            let a: Value = evaluate_expr_with_context(*box_expr, book, debug)?;
 			match p {
-				Prefix::Plus => Value::from(a.as_num().abs()),
-				Prefix::Minus => a * Value::from(-1.0)
+				Prefix::Plus => Value::from(ensure_non_range(a).as_num().abs()),
+				Prefix::Minus => ensure_non_range(a) * Value::from(-1.0), 
+				Prefix::ImplicitIntersection => implicit_intersection(a, book.current_cell)
 			}
 		}, 
+        Expr::Postfix(Postfix::Percent, box_expr) => {
+            percent(ensure_non_range(evaluate_expr_with_context(*box_expr, book, debug)?))
+        }, 
+        Expr::Postfix(Postfix::Spill, box_expr) => {
+            let reference = book.resolve_spill(&box_expr); 
+            evaluate_expr_with_context(reference, book, debug)?
+        }, 
+        Expr::Infix(i @ (Infix::Range | Infix::Intersect | Infix::Union), a, b) => {
+            evaluate_reference_infix(i, *a, *b, book, debug)?
+        }, 
 		Expr::Infix(i, a, b) => {
             let a = ensure_non_range(evaluate_expr_with_context(*a, book, debug)?); 
             let b = ensure_non_range(evaluate_expr_with_context(*b, book, debug)?); 
-            if a.is_err() {
-                return Ok(a); 
-            } else if b.is_err() {
-                return Ok(b); 
+            let (a, b) = match i {
+                Infix::Multiply | Infix::Divide | Infix::Exponent => (as_serials(a, book.date_system), as_serials(b, book.date_system)), 
+                _ => (a, b)
+            }; 
+            let is_single = a.dimensions() == (1, 1) && b.dimensions() == (1, 1); 
+            if (a.is_array2() || b.is_array2()) && !is_single {
+                evaluate_array_infix(i, a, b)
             } else {
-                match i {
-                    Infix::Plus => a.ensure_single() + b.ensure_single(), 
-                    Infix::Minus => a.ensure_single() - b.ensure_single(), 
-                    Infix::Multiply => a.ensure_single() * b.ensure_single(), 
-                    Infix::Divide => a.ensure_single() / b.ensure_single(), 
-                    Infix::Exponent => Exponent {a, b}.evaluate(), 
-                    Infix::NotEqual => Value::from(a.ensure_single() != b.ensure_single()), 
-                    Infix::Equal => Value::from(a.ensure_single() == b.ensure_single()), 
-                    Infix::LessThan => Value::from(a.ensure_single() < b.ensure_single()), 
-                    Infix::LessThanEqual => Value::from(a.ensure_single() <= b.ensure_single()), 
-                    Infix::GreaterThan => Value::from(a.ensure_single() > b.ensure_single()), 
-                    Infix::GreaterThanEqual => Value::from(a.ensure_single() >= b.ensure_single()), 
-                    Infix::Ampersand => {
-                        let value = if a.is_array() {
-                            Value::from(a.as_array().into_iter().map(|x| Value::from(format!("{}{}", x.as_text(), b.as_text()))).collect::<Vec<Value>>())
-                        } else if b.is_array() {
-                            Value::from(b.as_array().into_iter().map(|x| Value::from(format!("{}{}", a.as_text(), x.as_text()))).collect::<Vec<Value>>())
-                        } else {
-                            Value::from(format!("{}{}", a.as_text(), b.as_text()))
-                        }; 
-                        value
-                    },
-                }
-            } 
+                evaluate_infix(i, a, b)
+            }
         }, 
 		Expr::Array(x) => Value::Array(x.into_iter().map(|e| ensure_non_range(evaluate_expr_with_context(e, book, debug).unwrap())).collect::<Vec<Value>>()), 
-        _ => panic!("Expression {:?} does not convert to a value.", expr)  
+        Expr::Error(err) => Value::Error(err), 
 	}; 
     if debug {
         match expr.clone() {
@@ -210,6 +407,7 @@ pub fn evaluate_expr_with_context(expr: Expr, book: &Book, debug: bool) -> Resul
 mod tests {
 	use crate::evaluate::evaluate_str;
     use crate::evaluate::value::Value; 
+    use crate::parser::ast::Error as ExcelError; 
     use crate::errors::Error; 
 
     #[test]
@@ -220,6 +418,14 @@ mod tests {
         assert_eq!(evaluate_str(" (2 + 1) * 2 ")?, Value::from(6.0)); 
         assert_eq!(evaluate_str(" 8 / 4 ")?, Value::from(2.0)); 
         assert_eq!(evaluate_str(" 8^2 ")?, Value::from(64.0)); 
+        assert_eq!(evaluate_str(" 25% ")?, Value::from(0.25)); 
+        assert_eq!(evaluate_str(" 8*50% ")?, Value::from(4.0)); 
+        assert_eq!(evaluate_str(" 4^50% ")?, Value::from(2.0)); 
+        assert_eq!(evaluate_str(" -50%% ")?, Value::from(-0.005)); 
+        assert_eq!(evaluate_str(" TRUE% ")?, Value::from(0.01)); 
+        assert_eq!(evaluate_str(" \"50\"% ")?, Value::from(0.5)); 
+        assert_eq!(evaluate_str(" \"abc\"% ")?, Value::Error(ExcelError::Value)); 
+        assert_eq!(evaluate_str(" #N/A% ")?, Value::Error(ExcelError::NA)); 
         Ok(())
     }
 
@@ -1,4 +1,4 @@
-use chrono::{Duration, NaiveDate}; 
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime}; 
 use std::fmt; 
 use std::cmp::{Eq, PartialEq, PartialOrd, Ordering};
 use std::ops::{Add, Sub, Mul, Div, Neg, AddAssign};  
@@ -6,7 +6,7 @@ use ndarray::Array2;
 
 use crate::reference::Reference;
 use crate::parser::ast::Error;
-use crate::utils::excel_to_date; 
+use crate::utils::{excel_to_date, serial_to_datetime, datetime_to_serial, days_to_duration, duration_to_days, DateSystem}; 
 
 type NumType = f64;
 type BoolType = bool;
@@ -14,6 +14,7 @@ type TextType = String;
 type ArrayType = Vec<Value>;
 type Array2Type = Array2<Value>;
 type DateType = NaiveDate; 
+type DateTimeType = NaiveDateTime; 
 type ErrorType = Error; 
 
 #[derive(Clone, PartialEq, Debug)]
@@ -22,11 +23,13 @@ pub enum Value {
     Bool(BoolType), 
     Text(TextType), 
     Date(DateType), 
+    DateTime(DateTimeType), 
     Array(ArrayType), 
     Array2(Array2Type), 
     Formula(TextType), 
     Error(ErrorType), 
     Range { sheet: Option<String>, reference: Reference, value: Option<Box<Value>> }, 
+    Areas(Vec<Value>), // Ranges of a union, (A1:B2,D1) 
     Empty
 }
 
@@ -39,19 +42,31 @@ impl From<&str> for Value { fn from(s: &str) -> Value { Value::Text(s.to_string(
 impl From<Vec<Value>> for Value { fn from(v: ArrayType) -> Value { Value::Array(v) }}
 impl From<Array2<Value>> for Value { fn from(v: Array2Type) -> Value { Value::Array2(v) }}
 impl From<NaiveDate> for Value { fn from(d: DateType) -> Value { Value::Date(d) }}
+impl From<NaiveDateTime> for Value { fn from(d: DateTimeType) -> Value { Value::DateTime(d) }}
 
 impl Value {
     pub fn is_num(&self) -> bool { matches!(self, Value::Num(_)) }
     pub fn is_bool(&self) -> bool { matches!(self, Value::Bool(_)) }
     pub fn is_text(&self) -> bool { matches!(self, Value::Text(_)) }
     pub fn is_date(&self) -> bool { matches!(self, Value::Date(_)) }
+    pub fn is_datetime(&self) -> bool { matches!(self, Value::DateTime(_)) }
     pub fn is_array(&self) -> bool { matches!(self, Value::Array(_)) }
     pub fn is_array2(&self) -> bool { matches!(self, Value::Array2(_)) }
     pub fn is_empty(&self) -> bool { matches!(self, Value::Empty) }
     pub fn is_formula(&self) -> bool { matches!(self, Value::Formula(_)) }
     pub fn is_range(&self) -> bool { matches!(self, Value::Range {sheet: _, reference: _, value: _}) }
+    pub fn is_areas(&self) -> bool { matches!(self, Value::Areas(_)) }
     pub fn is_err(&self) -> bool { matches!(self, Value::Error(_)) }
 
+    // Whole serials load as dates, anything with a time of day keeps it. Serials no date can hold stay numbers 
+    pub fn from_serial(serial: NumType, date_system: DateSystem) -> Value {
+        match serial_to_datetime(serial, date_system) {
+            Some(datetime) if datetime.time() == NaiveTime::MIN => Value::Date(datetime.date()), 
+            Some(datetime) => Value::DateTime(datetime), 
+            None => Value::Num(serial)
+        }
+    }
+
     pub fn ensure_single(&self) -> Value {
         match self {
             Value::Array2(arr2) => arr2[[0,0]].ensure_single().clone(), // assume single
@@ -60,6 +75,27 @@ impl Value {
         }
     }
 
+    pub fn dimensions(&self) -> (usize, usize) {
+        match self {
+            Value::Array2(arr2) => arr2.dim(), 
+            Value::Array(arr) => (1, arr.len()), 
+            _ => (1, 1)
+        }
+    }
+
+    // Element at a position, repeating single rows, columns and values across the other dimension 
+    pub fn broadcast_get(&self, row: usize, column: usize) -> Option<Value> {
+        let (num_rows, num_cols) = self.dimensions(); 
+        let row = if num_rows == 1 { 0 } else { row }; 
+        let column = if num_cols == 1 { 0 } else { column }; 
+        match self {
+            Value::Array2(arr2) => arr2.get([row, column]).cloned(), 
+            Value::Array(arr) if row == 0 => arr.get(column).cloned(), 
+            Value::Array(_) => None, 
+            _ => Some(self.clone())
+        }
+    }
+
     pub fn as_num(&self) -> NumType {
         match self {
             Value::Num(x) => {
@@ -80,6 +116,8 @@ impl Value {
                 arr2[[0,0]].as_num()
             }, 
             Value::Empty => 0.0, 
+            // Without a book dates count in the 1900 date system, use as_serial for the book's own 
+            Value::Date(_) | Value::DateTime(_) => self.as_serial(DateSystem::Excel1900), 
             _ => panic!("{} cannot be converted to a number.", self)
         }
     }
@@ -117,11 +155,36 @@ impl Value {
             Value::Array2(arr2) => {
                 arr2[[0,0]].as_date()
             }, 
-            Value::Num(n) => excel_to_date(*n), 
+            Value::DateTime(x) => x.date(), 
+            Value::Num(n) => excel_to_date(*n).unwrap_or_else(|| panic!("{} cannot be converted to a date.", self)), 
             _ => panic!("{} cannot be converted to a date.", self)
         }
     }
 
+    // Numbers are serials counted in date_system, None when the serial is out of range 
+    pub fn as_datetime(&self, date_system: DateSystem) -> Option<DateTimeType> {
+        match self { 
+            Value::DateTime(x) => Some(*x),
+            Value::Date(x) => Some(x.and_time(NaiveTime::MIN)), 
+            Value::Array2(arr2) => {
+                arr2[[0,0]].as_datetime(date_system)
+            }, 
+            Value::Num(n) => serial_to_datetime(*n, date_system), 
+            _ => panic!("{} cannot be converted to a date.", self)
+        }
+    }
+
+    pub fn as_serial(&self, date_system: DateSystem) -> NumType {
+        match self {
+            Value::Date(x) => datetime_to_serial(x.and_time(NaiveTime::MIN), date_system), 
+            Value::DateTime(x) => datetime_to_serial(*x, date_system), 
+            Value::Array2(arr2) => {
+                arr2[[0,0]].as_serial(date_system)
+            }, 
+            c => c.as_num()
+        }
+    }
+
     pub fn as_array(&self) -> ArrayType {
         match self {
             Value::Array(arr) => arr.to_vec(),
@@ -163,6 +226,7 @@ impl fmt::Display for Value {
             Value::Text(x) => { write!(f, "\"{}\"", x) },
             Value::Formula(x) => { write!(f, "{}", x) }, 
             Value::Date(x) => { write!(f, "{}", x) }, 
+            Value::DateTime(x) => { write!(f, "{}", x) }, 
             Value::Array(x) => {
                 x.iter().fold(Ok(()), |result, output| {
                     result.and_then(|_| writeln!(f, "{}", output)) 
@@ -175,6 +239,7 @@ impl fmt::Display for Value {
                     None => write!(f, "{}", reference)
                 }
             }, 
+            Value::Areas(areas) => write!(f, "({})", areas.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(",")), 
             Value::Array2(arr2) => write!(f, "{}", arr2), 
             Value::Error(err) => write!(f, "{}", err)
         }
@@ -188,7 +253,7 @@ fn variant_ord(v : &Value) -> usize {
         v.is_bool(),
         v.is_text(),
         v.is_num(),
-        v.is_date()
+        v.is_date() || v.is_datetime()
     ];
     let variant_len : usize = variants.len();
     match variants.into_iter().position(|x| x) {
@@ -227,8 +292,10 @@ impl PartialOrd for Value {
                     } else {
                         Some(Ordering::Equal)
                     }
-                } else if self.is_date() {
+                } else if self.is_date() && other.is_date() {
                     Some(self.as_date().cmp(&other.as_date()))
+                } else if self.is_date() || self.is_datetime() {
+                    Some(self.as_datetime(DateSystem::default())?.cmp(&other.as_datetime(DateSystem::default())?))
                 } else {
                     None
                 }
@@ -247,19 +314,29 @@ impl Add for Value {
     type Output = Self; 
     fn add(self, other: Self) -> Self {
         match self.ensure_single() {
+            // 1+date moves the date like date+1 does 
+            Value::Num(x) if other.ensure_single().is_date() || other.ensure_single().is_datetime() => add_days(&other.ensure_single(), x), 
             Value::Num(x) => Value::from(x + other.ensure_single().as_num()), 
             Value::Text(ref x) => Value::from(format!("{}{}", x, other.ensure_single().as_text())),
             Value::Bool(_) => Value::from(self.as_num() + other.ensure_single().as_num()), 
             Value::Empty => Value::from(0.0 + other.ensure_single().as_num()), 
-            Value::Date(dt) => {
-                Value::from(dt.checked_add_signed(Duration::days(other.ensure_single().as_num() as i64)).unwrap())
-            }, 
+            Value::Date(_) | Value::DateTime(_) => add_days(&self.ensure_single(), other.ensure_single().as_num()), 
             Value::Error(_) => self, 
             _ => panic!("{} cannot be added to {}.", other, self)
         }
     }
 }
 
+// Dates stay dates when moved by whole days, #NUM! past the last date 
+fn add_days(date: &Value, days: NumType) -> Value {
+    let datetime = date.as_datetime(DateSystem::default()).zip(days_to_duration(days)).and_then(|(x, duration)| x.checked_add_signed(duration)); 
+    match datetime {
+        Some(datetime) if date.is_date() && datetime.time() == NaiveTime::MIN => Value::from(datetime.date()), 
+        Some(datetime) => Value::from(datetime), 
+        None => Value::Error(Error::Num)
+    }
+}
+
 impl AddAssign for Value {
     fn add_assign(&mut self, other: Self) {
         if self.ensure_single().is_num() {
@@ -277,12 +354,15 @@ impl Sub for Value {
             Value::Num(x) => Value::from(x - other.ensure_single().as_num()), 
             Value::Bool(_) => Value::from(self.as_num() - other.ensure_single().as_num()), 
             Value::Empty => Value::from(0.0 - other.ensure_single().as_num()), 
-            Value::Date(dt) => {
+            Value::Date(_) | Value::DateTime(_) => {
                 let other_single = other.ensure_single(); 
-                if other_single.is_date() {
-                        Value::from(NaiveDate::signed_duration_since(dt, other_single.as_date()).num_days() as f64)
+                if other_single.is_date() || other_single.is_datetime() {
+                    match (self.as_datetime(DateSystem::default()), other_single.as_datetime(DateSystem::default())) {
+                        (Some(a), Some(b)) => Value::from(duration_to_days(a.signed_duration_since(b))), 
+                        _ => Value::Error(Error::Num)
+                    }
                 } else {
-                    Value::from(dt.checked_sub_signed(Duration::days(other_single.as_num() as i64)).unwrap())
+                    add_days(&self.ensure_single(), -other_single.as_num())
                 }
             }, 
             Value::Error(_) => self, 
@@ -298,9 +378,9 @@ impl Mul for Value {
             Value::Num(x) => Value::from(x * other.ensure_single().as_num()), 
             Value::Bool(_) => Value::from(self.as_num() * other.ensure_single().as_num()), 
             Value::Empty => Value::from(0.0 * other.ensure_single().as_num()), 
+            Value::Date(_) | Value::DateTime(_) => Value::from(self.as_num() * other.ensure_single().as_num()), 
             Value::Error(_) => self, 
-            // TODO
-            _ => panic!("{} cannot be multiplied by {}.", self, other)
+            _ => Value::Error(Error::Value)
         }
     }
 }
@@ -310,9 +390,9 @@ impl Div for Value {
     fn div(self, other: Self) -> Self {
         match self.ensure_single() {
             Value::Num(x) => Value::from(x / other.ensure_single().as_num()), 
+            Value::Bool(_) | Value::Empty | Value::Date(_) | Value::DateTime(_) => Value::from(self.as_num() / other.ensure_single().as_num()), 
             Value::Error(_) => self, 
-            // TODO
-            _ => panic!("{} cannot be divided by {}.", self, other)
+            _ => Value::Error(Error::Value)
         }
     }
 }
@@ -0,0 +1,640 @@
+use chrono::{Datelike, Timelike}; 
+
+use crate::evaluate::value::Value; 
+use crate::utils::{serial_to_datetime, DateSystem}; 
+use crate::parser::ast::Error; 
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatKind {
+    General, 
+    Number, 
+    Percent, 
+    Currency, 
+    Date, 
+    Time, 
+    DateTime, 
+    Text
+}
+
+impl FormatKind {
+    pub fn is_date(&self) -> bool {
+        matches!(self, FormatKind::Date | FormatKind::Time | FormatKind::DateTime)
+    }
+}
+
+// Codes for the built-in numFmtIds, which styles.xml references without declaring
+pub fn builtin_format_code(id: usize) -> Option<&'static str> {
+    let code = match id {
+        0 => "General", 
+        1 => "0", 
+        2 => "0.00", 
+        3 => "#,##0", 
+        4 => "#,##0.00", 
+        5 => "$#,##0_);($#,##0)", 
+        6 => "$#,##0_);[Red]($#,##0)", 
+        7 => "$#,##0.00_);($#,##0.00)", 
+        8 => "$#,##0.00_);[Red]($#,##0.00)", 
+        9 => "0%", 
+        10 => "0.00%", 
+        11 => "0.00E+00", 
+        12 => "# ?/?", 
+        13 => "# ??/??", 
+        14 => "mm-dd-yy", 
+        15 => "d-mmm-yy", 
+        16 => "d-mmm", 
+        17 => "mmm-yy", 
+        18 => "h:mm AM/PM", 
+        19 => "h:mm:ss AM/PM", 
+        20 => "h:mm", 
+        21 => "h:mm:ss", 
+        22 => "m/d/yy h:mm", 
+        37 => "#,##0 ;(#,##0)", 
+        38 => "#,##0 ;[Red](#,##0)", 
+        39 => "#,##0.00;(#,##0.00)", 
+        40 => "#,##0.00;[Red](#,##0.00)", 
+        45 => "mm:ss", 
+        46 => "[h]:mm:ss", 
+        47 => "mmss.0", 
+        48 => "##0.0E+0", 
+        49 => "@", 
+        _ => return None
+    }; 
+    Some(code)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(String), 
+    Placeholder(char), // 0, # or ?
+    DecimalPoint, 
+    Thousands, 
+    Percent, 
+    Exponent(bool), // E+ shows the sign of positive exponents
+    Slash, 
+    Text, 
+    General, 
+    Year(usize), 
+    Month(usize), 
+    Day(usize), 
+    Hour(usize), 
+    Minute(usize), 
+    Second(usize), 
+    SubSecond(usize), 
+    Elapsed(char, usize), // [h], [mm], [ss]
+    AmPm(String, String), 
+}
+
+impl Token {
+    fn is_date(&self) -> bool {
+        matches!(self, Token::Year(_) | Token::Month(_) | Token::Day(_))
+    }
+
+    fn is_time(&self) -> bool {
+        matches!(self, Token::Hour(_) | Token::Minute(_) | Token::Second(_) | Token::SubSecond(_) | Token::Elapsed(_, _) | Token::AmPm(_, _))
+    }
+}
+
+// Sections are separated by ; outside of quotes, escapes and brackets
+fn split_sections(code: &str) -> Vec<&str> {
+    let mut sections = vec![]; 
+    let mut start = 0; 
+    let mut in_quotes = false; 
+    let mut in_brackets = false; 
+    let mut escaped = false; 
+    for (idx, c) in code.char_indices() {
+        if escaped {
+            escaped = false; 
+            continue; 
+        }
+        match c {
+            '\\' if !in_quotes => { escaped = true; }, 
+            '"' => { in_quotes = !in_quotes; }, 
+            '[' if !in_quotes => { in_brackets = true; }, 
+            ']' if !in_quotes => { in_brackets = false; }, 
+            ';' if !in_quotes && !in_brackets => {
+                sections.push(&code[start..idx]); 
+                start = idx + 1; 
+            }, 
+            _ => {}
+        }
+    }
+    sections.push(&code[start..]); 
+    sections
+}
+
+fn run_length(chars: &[char], idx: usize) -> usize {
+    let c = chars[idx].to_ascii_lowercase(); 
+    chars[idx..].iter().take_while(|x| x.to_ascii_lowercase() == c).count()
+}
+
+fn starts_with_ignore_case(chars: &[char], idx: usize, pattern: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect(); 
+    chars.len() >= idx + pattern.len() && chars[idx..idx + pattern.len()].iter().zip(pattern.iter()).all(|(a, b)| a.eq_ignore_ascii_case(b))
+}
+
+fn tokenize(section: &str) -> Vec<Token> {
+    let chars: Vec<char> = section.chars().collect(); 
+    let mut tokens: Vec<Token> = vec![]; 
+    let mut idx = 0; 
+    while idx < chars.len() {
+        let c = chars[idx]; 
+        let mut len = 1; 
+        match c {
+            '"' => {
+                let text: String = chars[idx+1..].iter().take_while(|x| **x != '"').collect(); 
+                len = text.chars().count() + 2; 
+                tokens.push(Token::Literal(text)); 
+            }, 
+            '\\' => {
+                if let Some(escaped) = chars.get(idx+1) {
+                    tokens.push(Token::Literal(escaped.to_string())); 
+                }
+                len = 2; 
+            }, 
+            // Padding the width of the next character
+            '_' => {
+                tokens.push(Token::Literal(String::from(" "))); 
+                len = 2; 
+            }, 
+            // Repeating the next character to fill the cell
+            '*' => { len = 2; }, 
+            '[' => {
+                let content: String = chars[idx+1..].iter().take_while(|x| **x != ']').collect(); 
+                len = content.chars().count() + 2; 
+                let lower = content.to_lowercase(); 
+                if let Some(currency) = content.strip_prefix('$') {
+                    let symbol: String = currency.chars().take_while(|x| *x != '-').collect(); 
+                    tokens.push(Token::Literal(symbol)); 
+                } else if !lower.is_empty() && lower.chars().all(|x| x == 'h') {
+                    tokens.push(Token::Elapsed('h', lower.len())); 
+                } else if !lower.is_empty() && lower.chars().all(|x| x == 'm') {
+                    tokens.push(Token::Elapsed('m', lower.len())); 
+                } else if !lower.is_empty() && lower.chars().all(|x| x == 's') {
+                    tokens.push(Token::Elapsed('s', lower.len())); 
+                }
+                // Colours and conditions don't change the text
+            }, 
+            _ if starts_with_ignore_case(&chars, idx, "General") => {
+                tokens.push(Token::General); 
+                len = 7; 
+            }, 
+            _ if starts_with_ignore_case(&chars, idx, "AM/PM") => {
+                tokens.push(Token::AmPm(chars[idx..idx+2].iter().collect(), chars[idx+3..idx+5].iter().collect())); 
+                len = 5; 
+            }, 
+            _ if starts_with_ignore_case(&chars, idx, "A/P") => {
+                tokens.push(Token::AmPm(chars[idx].to_string(), chars[idx+2].to_string())); 
+                len = 3; 
+            }, 
+            'E' | 'e' if matches!(chars.get(idx+1), Some('+') | Some('-')) => {
+                tokens.push(Token::Exponent(chars[idx+1] == '+')); 
+                len = 2; 
+            }, 
+            'y' | 'Y' | 'e' => {
+                len = run_length(&chars, idx); 
+                tokens.push(Token::Year(len)); 
+            }, 
+            'm' | 'M' => {
+                len = run_length(&chars, idx); 
+                tokens.push(Token::Month(len)); 
+            }, 
+            'd' | 'D' => {
+                len = run_length(&chars, idx); 
+                tokens.push(Token::Day(len)); 
+            }, 
+            'h' | 'H' => {
+                len = run_length(&chars, idx); 
+                tokens.push(Token::Hour(len)); 
+            }, 
+            's' | 'S' => {
+                len = run_length(&chars, idx); 
+                tokens.push(Token::Second(len)); 
+            }, 
+            '.' if matches!(tokens.last(), Some(Token::Second(_)) | Some(Token::Elapsed('s', _))) && chars.get(idx+1) == Some(&'0') => {
+                len = 1 + chars[idx+1..].iter().take_while(|x| **x == '0').count(); 
+                tokens.push(Token::SubSecond(len - 1)); 
+            }, 
+            '0' | '#' | '?' => { tokens.push(Token::Placeholder(c)); }, 
+            '.' => { tokens.push(Token::DecimalPoint); }, 
+            ',' => { tokens.push(Token::Thousands); }, 
+            '%' => { tokens.push(Token::Percent); }, 
+            '@' => { tokens.push(Token::Text); }, 
+            '/' if matches!(tokens.last(), Some(Token::Placeholder(_))) => { tokens.push(Token::Slash); }, 
+            _ => { tokens.push(Token::Literal(c.to_string())); }
+        }
+        idx += len; 
+    }
+    // m means minutes right after hours or right before seconds
+    for idx in 0..tokens.len() {
+        if let Token::Month(n) = tokens[idx] {
+            let previous = tokens[..idx].iter().rev().find(|x| x.is_date() || x.is_time()); 
+            let next = tokens[idx+1..].iter().find(|x| x.is_date() || x.is_time()); 
+            let after_hours = matches!(previous, Some(Token::Hour(_)) | Some(Token::Elapsed('h', _))); 
+            let before_seconds = matches!(next, Some(Token::Second(_)) | Some(Token::Elapsed('s', _))); 
+            if n <= 2 && (after_hours || before_seconds) {
+                tokens[idx] = Token::Minute(n); 
+            }
+        }
+    }
+    tokens
+}
+
+pub fn classify(code: &str) -> FormatKind {
+    let section = split_sections(code)[0]; 
+    let tokens = tokenize(section); 
+    let has_date = tokens.iter().any(|x| x.is_date()); 
+    let has_time = tokens.iter().any(|x| x.is_time()); 
+    let is_currency = tokens.iter().any(|x| matches!(x, Token::Literal(s) if s.contains(['$', '€', '£', '¥']))); 
+    let has_placeholders = tokens.iter().any(|x| matches!(x, Token::Placeholder(_))); 
+    if has_date && has_time {
+        FormatKind::DateTime
+    } else if has_date {
+        FormatKind::Date
+    } else if has_time {
+        FormatKind::Time
+    } else if tokens.contains(&Token::Percent) {
+        FormatKind::Percent
+    } else if is_currency {
+        FormatKind::Currency
+    } else if tokens.contains(&Token::Text) && !has_placeholders {
+        FormatKind::Text
+    } else if has_placeholders {
+        FormatKind::Number
+    } else {
+        FormatKind::General
+    }
+}
+
+// Renders a value the way Excel displays it in a cell with the given format code
+pub fn format_value(value: &Value, code: &str, date_system: DateSystem) -> String {
+    match value {
+        Value::Num(_) | Value::Date(_) | Value::DateTime(_) => format_number(value.as_serial(date_system), code, date_system), 
+        Value::Text(text) => format_text(text, code), 
+        Value::Bool(x) => String::from(if *x { "TRUE" } else { "FALSE" }), 
+        Value::Array(_) | Value::Array2(_) => format_value(&value.ensure_single(), code, date_system), 
+        Value::Formula(x) => x.clone(), 
+        Value::Empty => String::new(), 
+        Value::Error(err) => err.to_string(), 
+        Value::Range { .. } | Value::Areas(_) => value.to_string()
+    }
+}
+
+fn format_text(text: &str, code: &str) -> String {
+    let sections = split_sections(code); 
+    let section = match sections.get(3) {
+        Some(s) => s, 
+        None if tokenize(sections[0]).contains(&Token::Text) => sections[0], 
+        None => return text.to_string()
+    }; 
+    tokenize(section).into_iter().map(|x| {
+        match x {
+            Token::Text => text.to_string(), 
+            Token::Literal(s) => s, 
+            _ => String::new()
+        }
+    }).collect()
+}
+
+fn format_number(n: f64, code: &str, date_system: DateSystem) -> String {
+    // Cells can't hold infinities, show what Excel shows for a number it can't represent
+    if !n.is_finite() {
+        return Error::Num.to_string(); 
+    }
+    let sections = split_sections(code); 
+    // Negative and zero sections show the magnitude, with any sign written in the code
+    let (section, n, signed) = if n < 0.0 && sections.len() >= 2 {
+        (sections[1], -n, false)
+    } else if n == 0.0 && sections.len() >= 3 {
+        (sections[2], n, false)
+    } else {
+        (sections[0], n, true)
+    }; 
+    let tokens = tokenize(section); 
+    if tokens.iter().any(|x| x.is_date() || x.is_time()) {
+        format_datetime(n, &tokens, date_system)
+    } else if tokens.contains(&Token::Slash) {
+        format_fraction(n, &tokens, signed)
+    } else if tokens.iter().any(|x| matches!(x, Token::Placeholder(_))) {
+        format_decimal(n, &tokens, signed)
+    } else {
+        tokens.into_iter().map(|x| {
+            match x {
+                Token::General => format_general(if signed { n } else { n.abs() }), 
+                Token::Literal(s) => s, 
+                _ => String::new()
+            }
+        }).collect::<String>()
+    }
+}
+
+fn trim_decimal_zeros(s: &str) -> String {
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+// General fits numbers in eleven characters, switching to scientific notation for very large or small numbers
+pub fn format_general(n: f64) -> String {
+    if n == 0.0 || !n.is_finite() {
+        return String::from("0"); 
+    }
+    let exponent = n.abs().log10().floor() as i32; 
+    if !(-9..11).contains(&exponent) {
+        let formatted = format!("{:.5E}", n); 
+        let (mantissa, exponent) = formatted.split_once('E').unwrap(); 
+        let exponent: i32 = exponent.parse().unwrap(); 
+        format!("{}E{}{:02}", trim_decimal_zeros(mantissa), if exponent < 0 { '-' } else { '+' }, exponent.abs())
+    } else {
+        let decimals = (9 - exponent).clamp(0, 9) as usize; 
+        let formatted = trim_decimal_zeros(&format!("{:.*}", decimals, n)); 
+        if formatted == "-0" { String::from("0") } else { formatted }
+    }
+}
+
+fn group_thousands(digits: &str) -> String {
+    let mut output = String::new(); 
+    for (idx, c) in digits.chars().enumerate() {
+        if idx > 0 && (digits.len() - idx).is_multiple_of(3) {
+            output.push(','); 
+        }
+        output.push(c); 
+    }
+    output
+}
+
+fn format_decimal(n: f64, tokens: &[Token], signed: bool) -> String {
+    let exponent_idx = tokens.iter().position(|x| matches!(x, Token::Exponent(_))); 
+    let mantissa_tokens = &tokens[..exponent_idx.unwrap_or(tokens.len())]; 
+    let decimal_idx = mantissa_tokens.iter().position(|x| *x == Token::DecimalPoint).unwrap_or(mantissa_tokens.len()); 
+    let integer_placeholders: Vec<char> = mantissa_tokens[..decimal_idx].iter().filter_map(|x| if let Token::Placeholder(c) = x { Some(*c) } else { None }).collect(); 
+    let decimal_placeholders: Vec<char> = mantissa_tokens[decimal_idx..].iter().filter_map(|x| if let Token::Placeholder(c) = x { Some(*c) } else { None }).collect(); 
+    let last_placeholder = mantissa_tokens.iter().rposition(|x| matches!(x, Token::Placeholder(_))); 
+    let grouped = mantissa_tokens[..decimal_idx].windows(2).any(|x| x[0] == Token::Thousands && matches!(x[1], Token::Placeholder(_))); 
+    // Commas after the last digit scale by a thousand each
+    let scaling = match last_placeholder {
+        Some(idx) => mantissa_tokens[idx+1..].iter().take_while(|x| **x == Token::Thousands).count(), 
+        None => 0
+    }; 
+    let percents = tokens.iter().filter(|x| **x == Token::Percent).count(); 
+    let mut value = n.abs() * 100f64.powi(percents as i32) / 1000f64.powi(scaling as i32); 
+    let mut exponent: i32 = 0; 
+    if exponent_idx.is_some() && value != 0.0 {
+        let integer_digits = integer_placeholders.len().max(1) as i32; 
+        exponent = value.log10().floor() as i32 - (integer_digits - 1); 
+        value /= 10f64.powi(exponent); 
+        // Rounding can carry into another digit
+        if format!("{:.*}", decimal_placeholders.len(), value).len() > format!("{:.*}", decimal_placeholders.len(), 10f64.powi(integer_digits) - 1.0).len() {
+            value /= 10.0; 
+            exponent += 1; 
+        }
+    }
+    // Excel rounds halves away from zero
+    let factor = 10f64.powi(decimal_placeholders.len() as i32); 
+    if !(value * factor).is_finite() {
+        return Error::Num.to_string(); 
+    }
+    let rounded = format!("{:.*}", decimal_placeholders.len(), (value * factor).round() / factor); 
+    let (integer, decimals) = rounded.split_once('.').unwrap_or((rounded.as_str(), "")); 
+    let mut integer = integer.to_string(); 
+    if integer == "0" && !integer_placeholders.contains(&'0') {
+        integer = String::new(); 
+    }
+    let min_digits = integer_placeholders.iter().skip_while(|x| **x != '0').count(); 
+    while integer.len() < min_digits {
+        integer.insert(0, '0'); 
+    }
+    if grouped {
+        integer = group_thousands(&integer); 
+    }
+    // Optional decimal places drop trailing zeros; ? keeps their width
+    let mut decimals: Vec<char> = decimals.chars().collect(); 
+    for (idx, placeholder) in decimal_placeholders.iter().enumerate().rev() {
+        if decimals.get(idx) != Some(&'0') || *placeholder == '0' {
+            break; 
+        }
+        if *placeholder == '?' {
+            decimals[idx] = ' '; 
+        } else {
+            decimals.pop(); 
+        }
+    }
+    let decimals: String = decimals.into_iter().collect(); 
+    let is_zero = rounded.chars().all(|x| x == '0' || x == '.'); 
+    let mut output = String::new(); 
+    if signed && n < 0.0 && !is_zero {
+        output.push('-'); 
+    }
+    let mut integer_written = false; 
+    let mut decimals_written = false; 
+    for (idx, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Placeholder(_) if exponent_idx.is_some_and(|x| idx > x) => {}, 
+            Token::Placeholder(_) if idx < decimal_idx && !integer_written => {
+                output.push_str(&integer); 
+                integer_written = true; 
+            }, 
+            Token::Placeholder(_) if idx > decimal_idx && !decimals_written => {
+                output.push_str(&decimals); 
+                decimals_written = true; 
+            }, 
+            Token::DecimalPoint => { output.push('.'); }, 
+            Token::Percent => { output.push('%'); }, 
+            Token::Exponent(show_plus) => {
+                let exponent_digits = tokens[idx+1..].iter().filter(|x| matches!(x, Token::Placeholder(_))).count(); 
+                output.push('E'); 
+                if exponent < 0 {
+                    output.push('-'); 
+                } else if *show_plus {
+                    output.push('+'); 
+                }
+                output.push_str(&format!("{:0width$}", exponent.abs(), width = exponent_digits)); 
+            }, 
+            Token::Literal(s) => { output.push_str(s); }, 
+            Token::General => { output.push_str(&format_general(value)); }, 
+            _ => {}
+        }
+    }
+    output
+}
+
+// Closest fraction with a denominator of at most max_denominator
+fn closest_fraction(x: f64, max_denominator: usize) -> (usize, usize) {
+    (1..=max_denominator.max(1))
+        .map(|d| ((x * d as f64).round() as usize, d))
+        .min_by(|a, b| {
+            let error_a = (x - a.0 as f64 / a.1 as f64).abs(); 
+            let error_b = (x - b.0 as f64 / b.1 as f64).abs(); 
+            error_a.total_cmp(&error_b)
+        })
+        .unwrap()
+}
+
+fn format_fraction(n: f64, tokens: &[Token], signed: bool) -> String {
+    let slash_idx = tokens.iter().position(|x| *x == Token::Slash).unwrap(); 
+    // A space between placeholders separates the whole number from the numerator
+    let has_whole = tokens[..slash_idx].iter()
+        .skip_while(|x| !matches!(x, Token::Placeholder(_)))
+        .any(|x| !matches!(x, Token::Placeholder(_))); 
+    let denominator_text: String = tokens[slash_idx+1..].iter().map_while(|x| {
+        match x {
+            Token::Placeholder(c) => Some(*c), 
+            Token::Literal(s) if s.chars().all(|c| c.is_ascii_digit()) => s.chars().next(), 
+            _ => None
+        }
+    }).collect(); 
+    let value = n.abs(); 
+    let mut whole = if has_whole { value.trunc() as usize } else { 0 }; 
+    let fraction = value - whole as f64; 
+    let (mut numerator, denominator) = match denominator_text.parse::<usize>() {
+        Ok(d) if d > 0 && !denominator_text.starts_with('0') => ((fraction * d as f64).round() as usize, d), 
+        // Searching denominators gets slow past five digits
+        _ => closest_fraction(fraction, 10usize.pow(denominator_text.len().min(5) as u32) - 1)
+    }; 
+    if has_whole && numerator == denominator {
+        whole += 1; 
+        numerator = 0; 
+    }
+    let mut output = String::new(); 
+    if signed && n < 0.0 && (whole > 0 || numerator > 0) {
+        output.push('-'); 
+    }
+    if has_whole && (whole > 0 || numerator == 0) {
+        output.push_str(&whole.to_string()); 
+    }
+    if numerator > 0 || !has_whole {
+        if has_whole && whole > 0 {
+            output.push(' '); 
+        }
+        output.push_str(&format!("{}/{}", numerator, denominator)); 
+    }
+    output
+}
+
+fn format_datetime(serial: f64, tokens: &[Token], date_system: DateSystem) -> String {
+    // Times round to the shown precision so 0:59.9 doesn't show as 0:59
+    let precision = tokens.iter().find_map(|x| if let Token::SubSecond(n) = x { Some(*n as i32) } else { None }).unwrap_or(0); 
+    let units = 86400.0 * 10f64.powi(precision); 
+    let serial = (serial * units).round() / units; 
+    // Excel fills the cell with # for dates before the epoch or past 9999
+    let Some(datetime) = serial_to_datetime(serial, date_system).filter(|x| serial >= 0.0 && x.year() <= 9999) else {
+        return String::from("####"); 
+    }; 
+    let total_seconds = (serial * 86400.0).floor() as i64; 
+    let is_twelve_hour = tokens.iter().any(|x| matches!(x, Token::AmPm(_, _))); 
+    let pad = |x: u32, n: usize| if n >= 2 { format!("{:02}", x) } else { x.to_string() }; 
+    tokens.iter().map(|x| {
+        match x {
+            Token::Year(n) if *n <= 2 => format!("{:02}", datetime.year() % 100), 
+            Token::Year(_) => format!("{:04}", datetime.year()), 
+            Token::Month(n) if *n <= 2 => pad(datetime.month(), *n), 
+            Token::Month(3) => datetime.format("%b").to_string(), 
+            Token::Month(4) => datetime.format("%B").to_string(), 
+            Token::Month(_) => datetime.format("%B").to_string()[..1].to_string(), 
+            Token::Day(n) if *n <= 2 => pad(datetime.day(), *n), 
+            Token::Day(3) => datetime.format("%a").to_string(), 
+            Token::Day(_) => datetime.format("%A").to_string(), 
+            Token::Hour(n) if is_twelve_hour => pad((datetime.hour() + 11) % 12 + 1, *n), 
+            Token::Hour(n) => pad(datetime.hour(), *n), 
+            Token::Minute(n) => pad(datetime.minute(), *n), 
+            Token::Second(n) => pad(datetime.second(), *n), 
+            Token::SubSecond(n) => format!(".{:03}", datetime.nanosecond() / 1_000_000)[..n.min(&3)+1].to_string(), 
+            Token::Elapsed(unit, n) => {
+                let elapsed = match unit {
+                    'h' => total_seconds / 3600, 
+                    'm' => total_seconds / 60, 
+                    _ => total_seconds
+                }; 
+                format!("{:0width$}", elapsed, width = *n)
+            }, 
+            Token::AmPm(am, pm) => if datetime.hour() < 12 { am.clone() } else { pm.clone() }, 
+            Token::Thousands => String::from(","), 
+            Token::DecimalPoint => String::from("."), 
+            Token::Literal(s) => s.clone(), 
+            _ => String::new()
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::{classify, format_value, builtin_format_code, FormatKind}; 
+    use crate::evaluate::value::Value; 
+    use crate::utils::DateSystem; 
+    use chrono::NaiveDate; 
+
+    fn format(value: Value, code: &str) -> String {
+        format_value(&value, code, DateSystem::Excel1900)
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify("General"), FormatKind::General); 
+        assert_eq!(classify("#,##0.00"), FormatKind::Number); 
+        assert_eq!(classify("0.0%"), FormatKind::Percent); 
+        assert_eq!(classify("[$€-407]#,##0.00"), FormatKind::Currency); 
+        assert_eq!(classify("\"$\"#,##0_);(\"$\"#,##0)"), FormatKind::Currency); 
+        assert_eq!(classify("yyyy-mm-dd"), FormatKind::Date); 
+        assert_eq!(classify("mmm-yy"), FormatKind::Date); 
+        assert_eq!(classify("[h]:mm:ss"), FormatKind::Time); 
+        assert_eq!(classify("h:mm AM/PM"), FormatKind::Time); 
+        assert_eq!(classify("m/d/yy h:mm"), FormatKind::DateTime); 
+        assert_eq!(classify("@"), FormatKind::Text); 
+        assert_eq!(classify("\"Total: \"0"), FormatKind::Number); 
+        assert_eq!(classify(builtin_format_code(14).unwrap()), FormatKind::Date); 
+        assert_eq!(classify(builtin_format_code(10).unwrap()), FormatKind::Percent); 
+    }
+
+    #[test]
+    fn test_format_numbers() {
+        assert_eq!(format(Value::from(1234.5678), "General"), "1234.5678"); 
+        assert_eq!(format(Value::from(1.0 / 3.0), "General"), "0.333333333"); 
+        assert_eq!(format(Value::from(123456789012.0), "General"), "1.23457E+11"); 
+        assert_eq!(format(Value::from(1234.5678), "0.00"), "1234.57"); 
+        assert_eq!(format(Value::from(1234567.891), "#,##0.00"), "1,234,567.89"); 
+        assert_eq!(format(Value::from(-1234.5), "#,##0"), "-1,235"); 
+        assert_eq!(format(Value::from(-1234.5), "#,##0;(#,##0)"), "(1,235)"); 
+        assert_eq!(format(Value::from(0.0), "0;-0;\"zero\""), "zero"); 
+        assert_eq!(format(Value::from(0.125), "0.0%"), "12.5%"); 
+        assert_eq!(format(Value::from(5.0), "000"), "005"); 
+        assert_eq!(format(Value::from(0.5), "#.##"), ".5"); 
+        assert_eq!(format(Value::from(1500000.0), "#,##0.0,,\"M\""), "1.5M"); 
+        assert_eq!(format(Value::from(12345.0), "0.00E+00"), "1.23E+04"); 
+        assert_eq!(format(Value::from(0.00012), "0.0E+00"), "1.2E-04"); 
+        assert_eq!(format(Value::from(1234.5), "[$€-407]#,##0.00"), "€1,234.50"); 
+        assert_eq!(format(Value::from(1234.5), "$#,##0.00_);($#,##0.00)"), "$1,234.50 "); 
+        assert_eq!(format(Value::from(1.75), "# ?/?"), "1 3/4"); 
+        assert_eq!(format(Value::from(0.3), "?/8"), "2/8"); 
+        assert_eq!(format(Value::from(f64::INFINITY), "0.00"), "#NUM!"); 
+        assert_eq!(format(Value::from(1e308), "0.00%"), "#NUM!"); 
+        assert_eq!(format(Value::from(0.5), "# ?????????????????????/??????????????????????"), "1/2"); 
+    }
+
+    #[test]
+    fn test_format_dates() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap(); 
+        let datetime = date.and_hms_opt(18, 7, 9).unwrap(); 
+        assert_eq!(format(Value::from(date), "yyyy-mm-dd"), "2024-03-05"); 
+        assert_eq!(format(Value::from(date), "mmm-yy"), "Mar-24"); 
+        assert_eq!(format(Value::from(date), "dddd, mmmm d"), "Tuesday, March 5"); 
+        assert_eq!(format(Value::from(date), "mm-dd-yy"), "03-05-24"); 
+        assert_eq!(format(Value::from(datetime), "h:mm AM/PM"), "6:07 PM"); 
+        assert_eq!(format(Value::from(datetime), "hh:mm:ss"), "18:07:09"); 
+        assert_eq!(format(Value::from(datetime), "m/d/yy h:mm"), "3/5/24 18:07"); 
+        assert_eq!(format(Value::from(1.5), "[h]:mm"), "36:00"); 
+        assert_eq!(format(Value::from(45356.0), "yyyy-mm-dd"), "2024-03-05"); 
+        assert_eq!(format(Value::from(1e10), "yyyy-mm-dd"), "####"); 
+        assert_eq!(format(Value::from(-1.0), "yyyy-mm-dd"), "####"); 
+        assert_eq!(format(Value::from(1e300), "[h]:mm"), "####"); 
+        assert_eq!(format_value(&Value::from(date), "yyyy-mm-dd", DateSystem::Excel1904), "2024-03-05"); 
+    }
+
+    #[test]
+    fn test_format_text() {
+        assert_eq!(format(Value::from("abc"), "@"), "abc"); 
+        assert_eq!(format(Value::from("abc"), "0;-0;0;\"Name: \"@"), "Name: abc"); 
+        assert_eq!(format(Value::from("abc"), "0.00"), "abc"); 
+        assert_eq!(format(Value::Bool(true), "0.00"), "TRUE"); 
+    }
+}
@@ -186,48 +186,58 @@ fn orfunc(a: Value, b: Value) -> Value {
 
 #[function]
 fn max(args: Vec<Value>) -> Value {
-    let mut output = args[0].clone(); 
+    // Start from the first value, an array argument itself is not comparable 
+    let mut output: Option<Value> = None; 
+    let mut compare = |x: Value| output = Some(match output.take() {
+        Some(o) => o.max(x), 
+        None => x
+    }); 
     for v in args.into_iter() {
         if let Value::Array(arr) = v {
             for x in arr {
                 if x.is_num() {
-                    output = output.max(x); 
+                    compare(x); 
                 }
             }
         } else if let Value::Array2(arr2) = v {
             for x in arr2 {
                 if x.is_num() {
-                    output = output.max(x); 
+                    compare(x); 
                 }
             }
         } else {
-            output = output.max(v); 
+            compare(v); 
         }
     }
-    output
+    output.unwrap_or(Value::from(0.0))
 }
 
 #[function]
 fn min(args: Vec<Value>) -> Value {
-    let mut output = args[0].clone(); 
+    // Start from the first value, an array argument itself is not comparable 
+    let mut output: Option<Value> = None; 
+    let mut compare = |x: Value| output = Some(match output.take() {
+        Some(o) => o.min(x), 
+        None => x
+    }); 
     for v in args.into_iter() {
         if let Value::Array(arr) = v {
             for x in arr {
                 if x.is_num() {
-                    output = output.min(x); 
+                    compare(x); 
                 }
             }
         } else if let Value::Array2(arr2) = v {
             for x in arr2 {
                 if x.is_num() {
-                    output = output.min(x); 
+                    compare(x); 
                 }
             }
         } else {
-            output = output.min(v); 
+            compare(v); 
         }
     }
-    output
+    output.unwrap_or(Value::from(0.0))
 }
 
 #[function]
@@ -285,6 +295,10 @@ pub fn index(args: Vec<Expr>, book: &Book, debug: bool) -> Result<Value, Error>
 		Some(expr) => evaluate_expr_with_context(expr, book, debug)?,
 		None => Value::from(1.0)
 	}; 
+	let area_num = match arg_values.next() {
+		Some(expr) => ensure_non_range(evaluate_expr_with_context(expr, book, debug)?),
+		None => Value::from(1.0)
+	}; 
     // Pass up Err
     if array.is_err() {
         return Ok(array); 
@@ -292,12 +306,25 @@ pub fn index(args: Vec<Expr>, book: &Book, debug: bool) -> Result<Value, Error>
         return Ok(row_num); 
     } else if col_num.is_err() {
         return Ok(col_num); 
-    }
+    } else if area_num.is_err() {
+        return Ok(area_num); 
+    }
+    // The area of a union to index into, counted from 1 
+    let array = match array {
+        Value::Areas(mut areas) => match (area_num.as_num() as usize).checked_sub(1).filter(|x| *x < areas.len()) {
+            Some(area_idx) => areas.swap_remove(area_idx), 
+            None => return Ok(Value::Error(ExcelError::Ref))
+        }, 
+        _ if area_num.as_num() != 1.0 => return Ok(Value::Error(ExcelError::Ref)), 
+        array => array
+    }; 
     let row_idx = row_num.as_num() as usize - 1;
     let col_idx = col_num.as_num() as usize - 1; 
     if let Value::Range { sheet, reference, value } = array {
 		let reference = Reference::from(reference); 
 		let (start_row, start_col, _, _) = reference.get_dimensions(); 
+        // Whole columns and rows start at the first cell 
+        let (start_row, start_col) = (start_row.max(1), start_col.max(1)); 
 
         // If row value is zero, reference entire column.
         // Start cell row index is zero. 
@@ -1,7 +1,7 @@
 use nom::branch::*;
 use nom::bytes::complete::{tag, take, take_while, take_while1};
-use nom::character::complete::{alpha1, alphanumeric1, digit1, multispace0};
-use nom::combinator::{map, map_res, recognize, opt};
+use nom::character::complete::{alpha1, digit1, multispace0};
+use nom::combinator::{map, map_res, recognize, opt, not, peek, verify, rest_len};
 use nom::multi::many0;
 use nom::sequence::{terminated, delimited, separated_pair, pair};
 use nom::*;
@@ -12,11 +12,11 @@ use std::str::Utf8Error;
 
 pub mod token; 
 use crate::lexer::token::*; 
-use crate::errors::Error; 
+use crate::errors::{Error, Diagnostic}; 
 
 macro_rules! syntax {
     ($func_name: ident, $tag_string: literal, $output_token: expr) => {
-        fn $func_name<'syntax>(s: &'syntax [u8]) -> IResult<&'syntax [u8], Token> {
+        fn $func_name(s: &[u8]) -> IResult<&[u8], Token> {
             map(tag($tag_string), |_| $output_token).parse(s)
         }
     };
@@ -27,16 +27,27 @@ syntax! {null_err, "#NULL!", Token::Null}
 syntax! {div_err, "#DIV/0!", Token::Div}
 syntax! {value_err, "#VALUE!", Token::Value}
 syntax! {ref_err, "#REF!", Token::Ref}
-syntax! {name_err, "#NAME!", Token::Name}
+syntax! {name_err, "#NAME?", Token::Name}
+syntax! {name_err_displayed, "#NAME!", Token::Name}
 syntax! {num_err, "#NUM!", Token::Num}
 syntax! {na_err, "#N/A", Token::NA}
 syntax! {getting_data_err, "#GETTING_DATA", Token::GettingData}
+syntax! {spill_err, "#SPILL!", Token::Spill}
+syntax! {calc_err, "#CALC!", Token::Calc}
+syntax! {field_err, "#FIELD!", Token::Field}
+syntax! {blocked_err, "#BLOCKED!", Token::Blocked}
+syntax! {connect_err, "#CONNECT!", Token::Connect}
+syntax! {busy_err, "#BUSY!", Token::Busy}
+syntax! {unknown_err, "#UNKNOWN!", Token::Unknown}
 syntax! {plus, "+", Token::Plus}
 syntax! {minus, "-", Token::Minus}
 syntax! {divide, "/", Token::Divide}
 syntax! {multiply, "*", Token::Multiply}
 syntax! {exponent, "^", Token::Exponent}
 syntax! {ampersand, "&", Token::Ampersand}
+syntax! {hash, "#", Token::Hash}
+syntax! {at, "@", Token::At}
+syntax! {percent, "%", Token::Percent}
 syntax! {equal, "=", Token::Equal}
 syntax! {comma, ",", Token::Comma}
 syntax! {period, ".", Token::Period}
@@ -61,9 +72,17 @@ pub fn lex_syntax(input: &[u8]) -> IResult<&[u8], Token> {
             value_err, 
             ref_err, 
             name_err, 
+            name_err_displayed, 
             num_err, 
             na_err, 
-            getting_data_err
+            getting_data_err, 
+            spill_err, 
+            calc_err, 
+            field_err, 
+            blocked_err, 
+            connect_err, 
+            busy_err, 
+            unknown_err
         )), 
         alt((
             plus,
@@ -74,6 +93,9 @@ pub fn lex_syntax(input: &[u8]) -> IResult<&[u8], Token> {
         )), 
         alt((
             ampersand, 
+            hash, 
+            at, 
+            percent, 
             equal, 
             comma, 
             colon, 
@@ -95,12 +117,13 @@ pub fn lex_syntax(input: &[u8]) -> IResult<&[u8], Token> {
     )).parse(input)
 }
 
-// String
+// String, a doubled quote is a quote inside the text 
 fn pis(input: &[u8]) -> IResult<&[u8], Vec<u8>> {
     use std::result::Result::*;
 
     let (i1, c1) = take(1usize).parse(input)?;
     match c1.as_bytes() {
+        b"\"" if i1.starts_with(b"\"") => pis(&i1[1..]).map(|(slice, done)| (slice, concat_slice_vec(c1, done))),
         b"\"" => Ok((input, vec![])),
         b"\\" => {
             let (i2, c2) = take(1usize)(i1)?;
@@ -186,13 +209,41 @@ fn is_digit_or_alpha(chr: u8) -> bool {
     is_digit(chr) || is_alpha(chr)
 }
 
+fn in_ident(chr: u8) -> bool {
+    is_digit_or_alpha(chr) || b"_.\\".contains(&chr)
+}
+
+// A cell must not run into an identifier or function call (LOG10(, Rate2024) 
+fn is_cell_end(input: &[u8]) -> IResult<&[u8], ()> {
+    not(peek(alt((take_while1(in_ident), tag("("))))).parse(input)
+}
+
+// Columns stop at XFD 
+fn is_valid_column(c: &[u8]) -> bool {
+    let letters: Vec<u8> = c.iter().filter(|x| is_alpha(**x)).map(|x| x.to_ascii_uppercase()).collect(); 
+    letters.len() <= 3 && letters.iter().fold(0, |col, x| col * 26 + (x - b'A' + 1) as usize) <= 16384
+}
+
+// Quotes inside a quoted sheet name are doubled, 'O''Brien' 
+fn lex_quoted_name(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    recognize(many0(alt((take_while1(in_quote_sheet_name), tag("''"))))).parse(input)
+}
+
 fn lex_sheet_name(input: &[u8]) -> IResult<&[u8], &[u8]> {
     alt((
         take_while1(in_sheet_name),
-        recognize(delimited(tag("'"), take_while(in_quote_sheet_name), tag("'")))
+        recognize(delimited(tag("'"), lex_quoted_name, tag("'")))
     )).parse(input)
 }
 
+// 'O''Brien' -> O'Brien, names without quotes are kept as they are 
+fn unquote_sheet_name(name: &str) -> String {
+    match name.strip_prefix('\'').and_then(|x| x.strip_suffix('\'')) {
+        Some(inner) => inner.replace("''", "'"), 
+        None => name.to_string()
+    }
+}
+
 fn lex_sheet(input: &[u8]) -> IResult<&[u8], Token> {
     map_res(
         alt((
@@ -201,24 +252,64 @@ fn lex_sheet(input: &[u8]) -> IResult<&[u8], Token> {
         )), 
         |s| {
             let c = complete_byte_slice_str_from_utf8(s);
-            c.map(|syntax| Token::Sheet(syntax.replace('\'', "")))
+            c.map(|syntax| Token::Sheet(unquote_sheet_name(syntax)))
+        }
+    ).parse(input)
+}
+
+// Sheets of another workbook: [1]Rates!, '[Assumptions.xlsx]My Rates'!, 'C:\Models\[Book.xlsx]Rates'! 
+// and [1]! for names scoped to the whole linked workbook 
+fn lex_external_book(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    delimited(tag("["), take_while1(|c| c != b']' && c != b'['), tag("]")).parse(input)
+}
+
+fn lex_external_sheet(input: &[u8]) -> IResult<&[u8], Token> {
+    map_res(
+        terminated(
+            alt((
+                recognize(pair(lex_external_book, take_while(in_sheet_name))), 
+                recognize(delimited(
+                    tag("'"), 
+                    pair(take_while(|c| c != b'\'' && c != b'['), pair(lex_external_book, lex_quoted_name)), 
+                    tag("'")
+                ))
+            )), 
+            tag("!")
+        ), 
+        |s| {
+            let c = complete_byte_slice_str_from_utf8(s);
+            c.map(|syntax| Token::Sheet(unquote_sheet_name(syntax)))
         }
     ).parse(input)
 }
 
+// Jan:Dec! and 'Jan 2024:Dec 2024'! 
 fn lex_multisheet(input: &[u8]) -> IResult<&[u8], Token> {
     map(
-        terminated(recognize(separated_pair(lex_sheet_name, tag(":"), lex_sheet_name)), tag("!")), 
+        terminated(
+            alt((
+                recognize(separated_pair(lex_sheet_name, tag(":"), lex_sheet_name)), 
+                recognize(delimited(
+                    tag("'"), 
+                    separated_pair(verify(lex_quoted_name, |x: &[u8]| !x.is_empty()), tag(":"), verify(lex_quoted_name, |x: &[u8]| !x.is_empty())), 
+                    tag("'")
+                ))
+            )), 
+            tag("!")
+        ), 
         |a| {
             let x = complete_byte_slice_str_from_utf8(a).unwrap();
-            Token::MultiSheet(x.to_string())
+            Token::MultiSheet(unquote_sheet_name(x))
         }
     ).parse(input)
 }
 
 fn lex_cell(input: &[u8]) -> IResult<&[u8], Token> {
     map(
-        recognize(pair(pair(opt(tag("$")), alpha1), pair(opt(tag("$")), digit1))), 
+        terminated(
+            verify(recognize(pair(pair(opt(tag("$")), alpha1), pair(opt(tag("$")), digit1))), |c: &[u8]| is_valid_column(c)), 
+            is_cell_end
+        ), 
         |c| {
             let s = complete_byte_slice_str_from_utf8(c).unwrap(); 
             Token::Cell(s.to_string())
@@ -235,6 +326,33 @@ fn lex_range(input: &[u8]) -> IResult<&[u8], Token> {
     ).parse(input)
 }
 
+// R1C1 cells have a row and a column part, each absolute (R2), relative (R[-1]) or the current one (R): R[-1]C2, RC[3], R1C1:R[2]C 
+fn lex_r1c1_part(prefix: &'static str) -> impl FnMut(&[u8]) -> IResult<&[u8], &[u8]> {
+    move |input| recognize(pair(tag(prefix), opt(alt((digit1, recognize(delimited(tag("["), pair(opt(alt((tag("-"), tag("+")))), digit1), tag("]")))))))).parse(input)
+}
+
+// RC5 is column RC of row 5 
+fn lex_r1c1_cell(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    terminated(
+        verify(recognize(pair(lex_r1c1_part("R"), lex_r1c1_part("C"))), |c: &[u8]| !is_a1_cell(c)), 
+        is_cell_end
+    ).parse(input)
+}
+
+fn is_a1_cell(c: &[u8]) -> bool {
+    c.len() > 2 && c.starts_with(b"RC") && c[2..].iter().all(|x| is_digit(*x))
+}
+
+fn lex_r1c1(input: &[u8]) -> IResult<&[u8], Token> {
+    map(
+        recognize(pair(lex_r1c1_cell, opt(pair(tag(":"), lex_r1c1_cell)))), 
+        |s| {
+            let c = complete_byte_slice_str_from_utf8(s).unwrap(); 
+            Token::R1C1(c.to_string())
+        }
+    ).parse(input)
+}
+
 fn lex_references(input: &[u8]) -> IResult<&[u8], Token> {
     alt((
         lex_multisheet,
@@ -246,6 +364,42 @@ fn lex_references(input: &[u8]) -> IResult<&[u8], Token> {
     )).parse(input)
 }
 
+// Structured references: Sales[Amount], Sales[[#This Row],[Qty]], [@Qty] 
+fn lex_bracket_group(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    let mut depth: usize = 0; 
+    let mut i: usize = 0; 
+    while i < input.len() {
+        match input[i] {
+            b'[' => depth += 1, 
+            b']' => {
+                if depth == 0 { break } 
+                depth -= 1; 
+                if depth == 0 {
+                    return Ok((&input[i+1..], &input[..i+1])); 
+                }
+            }, 
+            b'\'' if depth > 0 => i += 1, // Escaped special character 
+            _ if depth == 0 => break, 
+            _ => {}
+        }
+        i += 1; 
+    }
+    Err(Err::Error(error::Error::new(input, error::ErrorKind::TakeUntil)))
+}
+
+fn lex_structured_reference(input: &[u8]) -> IResult<&[u8], Token> {
+    map(
+        recognize(pair(
+            opt(pair(alt((alpha1, tag("_"), tag("\\"))), take_while(in_ident))), 
+            lex_bracket_group
+        )), 
+        |s| {
+            let c = complete_byte_slice_str_from_utf8(s).unwrap(); 
+            Token::StructuredReference(c.to_string())
+        }
+    ).parse(input)
+}
+
 // Integer
 fn complete_str_from_str<F: FromStr>(c: &str) -> Result<F, F::Err> {
     FromStr::from_str(c)
@@ -277,7 +431,7 @@ fn lex_float(input: &[u8]) -> IResult<&[u8], Token> {
 fn lex_ident(input: &[u8]) -> IResult<&[u8], Token> {
     map(
         map_res(
-            map_res(alphanumeric1, complete_byte_slice_str_from_utf8),
+            map_res(recognize(pair(alt((alpha1, tag("_"), tag("\\"))), take_while(in_ident))), complete_byte_slice_str_from_utf8),
             complete_str_from_str,
         ),
         Token::Ident,
@@ -287,6 +441,9 @@ fn lex_ident(input: &[u8]) -> IResult<&[u8], Token> {
 // Tokens
 fn lex_token(input: &[u8]) -> IResult<&[u8], Token> {
     alt((
+        lex_external_sheet, 
+        lex_r1c1, 
+        lex_structured_reference, 
         lex_syntax,
         lex_string,
         lex_references,
@@ -296,22 +453,57 @@ fn lex_token(input: &[u8]) -> IResult<&[u8], Token> {
     )).parse(input)
 }
 
-fn lex_tokens(input: &[u8]) -> IResult<&[u8], Vec<Token>> {
-    many0(delimited(multispace0, lex_token, multispace0)).parse(input)
+// Whitespace between two references is the intersection operator: B:B 3:3, Revenue Q1 
+fn lex_tokens(input: &[u8]) -> IResult<&[u8], Vec<(Token, Span)>> {
+    let offset = |rest_len: usize| input.len() - rest_len; 
+    map(
+        terminated(many0((multispace0, rest_len, lex_token, rest_len)), multispace0), 
+        |tokens| {
+            let mut output: Vec<(Token, Span)> = vec![]; 
+            for (space, start, token, end) in tokens {
+                let span = Span { start: offset(start), end: offset(end) }; 
+                if !space.is_empty() && output.last().is_some_and(|(last, _)| is_intersection(last, &token)) {
+                    output.push((Token::Space, Span { start: span.start - space.len(), end: span.start })); 
+                }
+                output.push((token, span)); 
+            }
+            output
+        }
+    ).parse(input)
+}
+
+fn is_intersection(left: &Token, right: &Token) -> bool {
+    let ends_reference = matches!(left, Token::Cell(_) | Token::Range(_) | Token::VRange(_) | Token::HRange(_) | Token::R1C1(_) 
+        | Token::StructuredReference(_) | Token::Ident(_) | Token::RParen | Token::Hash); 
+    let starts_reference = matches!(right, Token::Cell(_) | Token::Range(_) | Token::VRange(_) | Token::HRange(_) | Token::R1C1(_) 
+        | Token::StructuredReference(_) | Token::Ident(_) | Token::Sheet(_) | Token::MultiSheet(_) | Token::LParen); 
+    // SUM (A1) is still a function call 
+    ends_reference && starts_reference && !matches!((left, right), (Token::Ident(_), Token::LParen))
 }
 
 pub struct Lexer; 
 impl Lexer {
     pub fn lex_tokens(bytes: &[u8]) -> Result<Vec<Token>, Error> {
-		match lex_tokens(bytes)
-			.map(|(slice, result)| (slice, [&result[..]].concat())) {
-                Ok((_, mut tokens)) => {
-                    tokens.push(Token::EOF); 
-                    Ok(tokens)
-                }, 
-                _ => Err(Error::UnableToLex(String::from_utf8(bytes.to_vec()).unwrap()))
-            }
-	}
+        Lexer::lex_spanned_tokens(bytes).map(|(tokens, _)| tokens)
+    }
+
+    // Tokens with the byte range each came from, anything left that is not a token is an error 
+    pub fn lex_spanned_tokens(bytes: &[u8]) -> Result<(Vec<Token>, Vec<Span>), Error> {
+        let rest: &[u8] = match lex_tokens(bytes) {
+            Ok(([], tokens)) => {
+                let (mut tokens, mut spans): (Vec<Token>, Vec<Span>) = tokens.into_iter().unzip(); 
+                tokens.push(Token::EOF); 
+                spans.push(Span { start: bytes.len(), end: bytes.len() }); 
+                return Ok((tokens, spans)); 
+            }, 
+            Ok((rest, _)) => rest, 
+            _ => bytes
+        }; 
+        let formula: String = String::from_utf8_lossy(bytes).to_string(); 
+        let offset: usize = bytes.len() - rest.len(); 
+        let found: String = formula.get(offset..).and_then(|x| x.chars().next()).map(|c| format!("'{}'", c)).unwrap_or_default(); 
+        Err(Error::UnableToLex(Diagnostic { formula, offset, expected: vec![], found }))
+    }
 }
 
 #[cfg(test)]
@@ -325,7 +517,7 @@ mod tests {
 
 	#[test]
 	fn test_symbols() -> Result<(), Error> {
-        assert_eq!(lex(b"=+(){},;")?, vec![
+        assert_eq!(lex(b"=+(){},;%")?, vec![
 			Token::Equal, 
             Token::Plus,
             Token::LParen,
@@ -334,11 +526,37 @@ mod tests {
             Token::RBrace,
             Token::Comma,
             Token::SemiColon,
+            Token::Percent, 
             Token::EOF, 
         ]);
         Ok(())
 	}
 
+    #[test]
+    fn test_intersection() -> Result<(), Error> {
+        assert_eq!(lex(b"B:B 3:3")?, vec![Token::VRange("B:B".to_string()), Token::Space, Token::HRange("3:3".to_string()), Token::EOF]); 
+        assert_eq!(lex(b"(A1) Sheet1!B2")?, vec![
+            Token::LParen, Token::Cell("A1".to_string()), Token::RParen, Token::Space, Token::Sheet("Sheet1".to_string()), Token::Cell("B2".to_string()), Token::EOF
+        ]); 
+        assert_eq!(lex(b" A1 + SUM (B1) ")?, vec![
+            Token::Cell("A1".to_string()), Token::Plus, Token::Ident("SUM".to_string()), Token::LParen, Token::Cell("B1".to_string()), Token::RParen, Token::EOF
+        ]); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_spans() -> Result<(), Error> {
+        let (tokens, spans) = Lexer::lex_spanned_tokens(b"SUM( B:B 3:3 )")?; 
+        assert_eq!(tokens[3], Token::Space); 
+        let ranges: Vec<(usize, usize)> = spans.iter().map(|x| (x.start, x.end)).collect(); 
+        assert_eq!(ranges, vec![(0, 3), (3, 4), (5, 8), (8, 9), (9, 12), (13, 14), (14, 14)]); 
+        match lex(b"A1 ~ B1") {
+            Err(Error::UnableToLex(d)) => assert_eq!((d.offset, d.found.as_str()), (3, "'~'")), 
+            other => panic!("{:?}", other)
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_strings() -> Result<(), Error> {
         assert_eq!(lex(b"\"this is a test\"")?, vec![
@@ -376,8 +594,12 @@ mod tests {
         assert_eq!(lex(b"#VALUE!")?, vec![Token::Value, Token::EOF]); 
         assert_eq!(lex(b"#REF!")?, vec![Token::Ref, Token::EOF]); 
         assert_eq!(lex(b"#NAME!")?, vec![Token::Name, Token::EOF]); 
+        assert_eq!(lex(b"#NAME?")?, vec![Token::Name, Token::EOF]); 
         assert_eq!(lex(b"#N/A")?, vec![Token::NA, Token::EOF]); 
         assert_eq!(lex(b"#GETTING_DATA")?, vec![Token::GettingData, Token::EOF]); 
+        assert_eq!(lex(b"#SPILL!")?, vec![Token::Spill, Token::EOF]); 
+        assert_eq!(lex(b"#CALC!")?, vec![Token::Calc, Token::EOF]); 
+        assert_eq!(lex(b"#UNKNOWN!")?, vec![Token::Unknown, Token::EOF]); 
         Ok(())
     }
 
@@ -391,12 +613,39 @@ mod tests {
     #[test]
     fn test_multisheet() -> Result<(), Error> {
         assert_eq!(lex(b"test:test!")?, vec![Token::MultiSheet(String::from("test:test")), Token::EOF]); 
+        assert_eq!(lex(b"'Jan 2024:Dec 2024'!B2")?, vec![Token::MultiSheet(String::from("Jan 2024:Dec 2024")), Token::Cell(String::from("B2")), Token::EOF]); 
+        assert_eq!(lex(b"'Feb:O''Brien'!B2")?, vec![Token::MultiSheet(String::from("Feb:O'Brien")), Token::Cell(String::from("B2")), Token::EOF]); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_structured_reference() -> Result<(), Error> {
+        assert_eq!(lex(b"SUM(Sales[Amount])")?, vec![
+            Token::Ident(String::from("SUM")), 
+            Token::LParen, 
+            Token::StructuredReference(String::from("Sales[Amount]")), 
+            Token::RParen, 
+            Token::EOF
+        ]); 
+        assert_eq!(lex(b"[@Qty]*Sales[[#This Row],[Unit Price]]")?, vec![
+            Token::StructuredReference(String::from("[@Qty]")), 
+            Token::Multiply, 
+            Token::StructuredReference(String::from("Sales[[#This Row],[Unit Price]]")), 
+            Token::EOF
+        ]); 
+        assert_eq!(lex(b"Sales[['#Code]]")?, vec![Token::StructuredReference(String::from("Sales[['#Code]]")), Token::EOF]); 
         Ok(())
     }
 
     #[test]
     fn test_sheet() -> Result<(), Error> {
         assert_eq!(lex(b"'Test'!")?, vec![Token::Sheet(String::from("Test")), Token::EOF]); 
+        assert_eq!(lex(b"'O''Brien'!A1")?, vec![Token::Sheet(String::from("O'Brien")), Token::Cell(String::from("A1")), Token::EOF]); 
+        assert_eq!(lex(b"'[1]O''Brien'!A1")?, vec![Token::Sheet(String::from("[1]O'Brien")), Token::Cell(String::from("A1")), Token::EOF]); 
+        assert_eq!(lex(b"[1]Rates!B4")?, vec![Token::Sheet(String::from("[1]Rates")), Token::Cell(String::from("B4")), Token::EOF]); 
+        assert_eq!(lex(b"'[Assumptions.xlsx]My Rates'!")?, vec![Token::Sheet(String::from("[Assumptions.xlsx]My Rates")), Token::EOF]); 
+        assert_eq!(lex(b"'C:\\Models\\[Book.xlsx]Rates'!")?, vec![Token::Sheet(String::from("C:\\Models\\[Book.xlsx]Rates")), Token::EOF]); 
+        assert_eq!(lex(b"[1]!Growth")?, vec![Token::Sheet(String::from("[1]")), Token::Ident(String::from("Growth")), Token::EOF]); 
         Ok(())
     }
 
@@ -426,9 +675,24 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_r1c1() -> Result<(), Error> {
+        assert_eq!(lex(b"R[-1]C2")?, vec![Token::R1C1(String::from("R[-1]C2")), Token::EOF]); 
+        assert_eq!(lex(b"RC[3]+R5C")?, vec![Token::R1C1(String::from("RC[3]")), Token::Plus, Token::R1C1(String::from("R5C")), Token::EOF]); 
+        assert_eq!(lex(b"Sheet1!R1C1:R[2]C")?, vec![Token::Sheet(String::from("Sheet1")), Token::R1C1(String::from("R1C1:R[2]C")), Token::EOF]); 
+        assert_eq!(lex(b"R2")?, vec![Token::Cell(String::from("R2")), Token::EOF]); 
+        assert_eq!(lex(b"RC5")?, vec![Token::Cell(String::from("RC5")), Token::EOF]); 
+        assert_eq!(lex(b"RC4Key")?, vec![Token::Ident(String::from("RC4Key")), Token::EOF]); 
+        Ok(())
+    }
+
     #[test]
     fn test_ident() -> Result<(), Error> {
         assert_eq!(lex(b"test")?, vec![Token::Ident("test".to_string()), Token::EOF]); 
+        assert_eq!(lex(b"Tax_Rate")?, vec![Token::Ident("Tax_Rate".to_string()), Token::EOF]); 
+        assert_eq!(lex(b"Rate2024")?, vec![Token::Ident("Rate2024".to_string()), Token::EOF]); 
+        assert_eq!(lex(b"LOG10(")?, vec![Token::Ident("LOG10".to_string()), Token::LParen, Token::EOF]); 
+        assert_eq!(lex(b"_Total.Q1")?, vec![Token::Ident("_Total.Q1".to_string()), Token::EOF]); 
         Ok(())
     }
 }
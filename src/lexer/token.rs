@@ -18,6 +18,13 @@ pub enum Token {
     Num, 
     NA, 
     GettingData, 
+    Spill, 
+    Calc, 
+    Field, 
+    Blocked, 
+    Connect, 
+    Busy, 
+    Unknown, 
     // References
     MultiSheet(String), 
     Sheet(String), 
@@ -25,6 +32,8 @@ pub enum Token {
     Cell(String), 
     VRange(String), 
     HRange(String), 
+    R1C1(String), 
+    StructuredReference(String), 
     // Symbols
     Plus,
     Minus,
@@ -32,6 +41,10 @@ pub enum Token {
     Multiply,
     Exponent, 
     Ampersand, 
+    Hash, 
+    At, 
+    Percent, 
+    Space, 
     Equal,
 	Exclamation, 
     Comma,
@@ -77,21 +90,34 @@ impl fmt::Display for Token {
             Token::Cell(s) => write!(f, "{}", s), 
             Token::VRange(s) => write!(f, "{}", s), 
             Token::HRange(s) => write!(f, "{}", s), 
+            Token::R1C1(s) => write!(f, "{}", s), 
+            Token::StructuredReference(s) => write!(f, "{}", s), 
             Token::Ident(s) => write!(f, "{}", s), 
             Token::Null => write!(f, "#NULL!"), 
             Token::Div => write!(f, "#DIV/0!"), 
             Token::Value => write!(f, "#VALUE!"),
             Token::Ref => write!(f, "#REF!"), 
-            Token::Name => write!(f, "#NAME!"), 
+            Token::Name => write!(f, "#NAME?"), 
             Token::Num => write!(f, "#NUM!"), 
-            Token::NA => write!(f, "#N/A!"), 
+            Token::NA => write!(f, "#N/A"), 
             Token::GettingData => write!(f, "#GETTING_DATA"), 
+            Token::Spill => write!(f, "#SPILL!"), 
+            Token::Calc => write!(f, "#CALC!"), 
+            Token::Field => write!(f, "#FIELD!"), 
+            Token::Blocked => write!(f, "#BLOCKED!"), 
+            Token::Connect => write!(f, "#CONNECT!"), 
+            Token::Busy => write!(f, "#BUSY!"), 
+            Token::Unknown => write!(f, "#UNKNOWN!"), 
             Token::Plus => write!(f, "+"), 
             Token::Minus => write!(f, "-"), 
             Token::Divide => write!(f, "/"), 
             Token::Multiply => write!(f, "*"), 
             Token::Exponent => write!(f, "^"), 
             Token::Ampersand => write!(f, "&"), 
+            Token::Hash => write!(f, "#"), 
+            Token::At => write!(f, "@"), 
+            Token::Percent => write!(f, "%"), 
+            Token::Space => write!(f, " "), 
             Token::Equal => write!(f, "="), 
             Token::Exclamation => write!(f, "!"), 
             Token::Comma => write!(f, ","), 
@@ -111,17 +137,26 @@ impl fmt::Display for Token {
     }
 }
 
+// Byte range of a token or expression in the formula text 
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Span {
+    pub start: usize, 
+    pub end: usize
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct Tokens<'a> {
     pub tok: &'a [Token], 
+    pub spans: &'a [Span], // Where each token came from 
     pub start: usize, 
     pub end: usize, 
 }
 
 impl<'a> Tokens<'a> {
-    pub fn new(vec: &'a [Token]) -> Self {
+    pub fn new(vec: &'a [Token], spans: &'a [Span]) -> Self {
         Tokens {
             tok: vec,
+            spans, 
             start: 0,
             end: vec.len(),
         }
@@ -142,6 +177,7 @@ impl <'a> Input for Tokens<'a> {
     fn take(&self, count: usize) -> Self {
         Tokens {
             tok: &self.tok[0..count],
+            spans: &self.spans[0..count],
             start: 0,
             end: count,
         }
@@ -150,6 +186,7 @@ impl <'a> Input for Tokens<'a> {
     fn take_from(&self, count: usize) -> Self {
         Tokens {
             tok: &self.tok[count..],
+            spans: &self.spans[count..],
             start: 0,
             end: self.tok.len() - count,
         }
@@ -157,13 +194,16 @@ impl <'a> Input for Tokens<'a> {
 
     fn take_split(&self, count: usize) -> (Self, Self) {
         let (prefix, suffix) = self.tok.split_at(count);
+        let (prefix_spans, suffix_spans) = self.spans.split_at(count);
         let first = Tokens {
             tok: prefix,
+            spans: prefix_spans,
             start: 0,
             end: prefix.len(),
         };
         let second = Tokens {
             tok: suffix,
+            spans: suffix_spans,
             start: 0,
             end: suffix.len(),
         };
@@ -9,3 +9,8 @@ pub mod cell;
 pub mod reference; 
 pub mod dependency; 
 pub mod errors; 
+pub mod format; 
+pub mod ods; 
+pub mod csv; 
+pub mod xlsb; 
+pub mod store; 
@@ -0,0 +1,450 @@
+use std::io::BufRead; 
+use chrono::{NaiveDate, NaiveDateTime}; 
+use quick_xml::events::{BytesStart, Event}; 
+use quick_xml::Reader; 
+
+use crate::cell::Cell; 
+use crate::errors::Error; 
+use crate::evaluate::value::Value; 
+use crate::utils::DateSystem; 
+
+pub const MIME_TYPE: &str = "application/vnd.oasis.opendocument.spreadsheet"; 
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OdsCell {
+    pub row: usize, 
+    pub column: usize, 
+    pub value: Value, 
+    pub formula: Option<String>, // OpenFormula text as stored in table:formula
+    pub matrix: Option<(usize, usize)>, // Rows and columns of a matrix formula
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OdsTable {
+    pub name: String, 
+    pub cells: Vec<OdsCell>, 
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OdsName {
+    pub name: String, 
+    pub sheet: Option<usize>, // None for document scope
+    pub formula: String, // Excel syntax without the leading =
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OdsContent {
+    pub tables: Vec<OdsTable>, 
+    pub names: Vec<OdsName>, 
+    pub date_system: DateSystem, 
+}
+
+// Attributes of the table:table-cell being read
+#[derive(Debug, Default)]
+struct CellFlags {
+    value_type: Option<String>, 
+    value: Option<String>, 
+    formula: Option<String>, 
+    matrix_rows: usize, 
+    matrix_columns: usize, 
+    repeated: usize, 
+    text: Option<String>, 
+    paragraphs: usize, 
+}
+
+// Reads the tables of content.xml, repeated rows and cells are expanded only when they hold something
+pub fn read_content<R: BufRead>(source: R) -> Result<OdsContent, Error> {
+    let mut reader = Reader::from_reader(source); 
+    let mut buf = Vec::new(); 
+    let mut content = OdsContent::default(); 
+    let mut row: usize = 0; 
+    let mut column: usize = 0; 
+    let mut rows_repeated: usize = 1; 
+    let mut row_cells: Vec<OdsCell> = vec![]; 
+    let mut cell: Option<CellFlags> = None; 
+    let mut in_paragraph = false; 
+    let mut in_table = false; 
+    let mut annotation_depth: usize = 0; 
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) if annotation_depth > 0 || e.local_name().as_ref() == b"annotation" => {
+                annotation_depth += 1; 
+            }, 
+            Ok(Event::End(_)) if annotation_depth > 0 => {
+                annotation_depth -= 1; 
+            }, 
+            Ok(_) if annotation_depth > 0 => {}, 
+            Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"table" => {
+                let name = attribute(&reader, e, b"name")?.unwrap_or_default(); 
+                content.tables.push(OdsTable { name, cells: vec![] }); 
+                in_table = true; 
+                row = 0; 
+            }, 
+            Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"table-row" => {
+                rows_repeated = repeated(&reader, e, b"number-rows-repeated")?; 
+                column = 0; 
+            }, 
+            Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"table-row" => {
+                row += repeated(&reader, e, b"number-rows-repeated")?; 
+            }, 
+            Ok(Event::End(ref e)) if e.local_name().as_ref() == b"table-row" => {
+                if let Some(table) = content.tables.last_mut() {
+                    for offset in 0..rows_repeated {
+                        table.cells.extend(row_cells.iter().map(|x| OdsCell { row: x.row + offset, ..x.clone() })); 
+                    }
+                }
+                row_cells.clear(); 
+                row += rows_repeated; 
+            }, 
+            Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"table-cell" => {
+                cell = Some(cell_flags(&reader, e)?); 
+            }, 
+            Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"table-cell" => {
+                let flags = cell_flags(&reader, e)?; 
+                column += flags.repeated; 
+                row_cells.extend(finish_cell(flags, row + 1, column)?); 
+            }, 
+            Ok(Event::End(ref e)) if e.local_name().as_ref() == b"table-cell" => {
+                if let Some(flags) = cell.take() {
+                    column += flags.repeated; 
+                    row_cells.extend(finish_cell(flags, row + 1, column)?); 
+                }
+            }, 
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"covered-table-cell" => {
+                column += repeated(&reader, e, b"number-columns-repeated")?; 
+            }, 
+            Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"p" => {
+                if let Some(flags) = cell.as_mut() {
+                    let text = flags.text.get_or_insert_with(String::new); 
+                    if flags.paragraphs > 0 {
+                        text.push('\n'); 
+                    }
+                    flags.paragraphs += 1; 
+                    in_paragraph = true; 
+                }
+            }, 
+            Ok(Event::End(ref e)) if e.local_name().as_ref() == b"p" => {
+                in_paragraph = false; 
+            }, 
+            Ok(Event::Empty(ref e)) if in_paragraph => {
+                if let Some(text) = cell.as_mut().and_then(|x| x.text.as_mut()) {
+                    match e.local_name().as_ref() {
+                        b"s" => {
+                            text.push_str(&" ".repeat(repeated(&reader, e, b"c")?)); 
+                        }, 
+                        b"tab" => text.push('\t'), 
+                        b"line-break" => text.push('\n'), 
+                        _ => {}
+                    }
+                }
+            }, 
+            Ok(Event::Text(ref e)) if in_paragraph => {
+                if let Some(text) = cell.as_mut().and_then(|x| x.text.as_mut()) {
+                    text.push_str(&e.unescape()?); 
+                }
+            }, 
+            Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"null-date" => {
+                content.date_system = match attribute(&reader, e, b"date-value")?.as_deref() {
+                    Some("1904-01-01") => DateSystem::Excel1904, 
+                    _ => DateSystem::Excel1900
+                }; 
+            }, 
+            Ok(Event::Empty(ref e)) | Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"named-range" || e.local_name().as_ref() == b"named-expression" => {
+                // Names declared inside a table are scoped to it
+                let sheet = match in_table {
+                    true => content.tables.len().checked_sub(1), 
+                    false => None
+                }; 
+                let name = attribute(&reader, e, b"name")?.unwrap_or_default(); 
+                let formula = match (attribute(&reader, e, b"cell-range-address")?, attribute(&reader, e, b"expression")?) {
+                    (Some(address), _) => Some(translate_reference(&address)?), 
+                    (None, Some(expression)) => Some(translate_formula(&expression)?.trim_start_matches('=').to_string()), 
+                    (None, None) => None
+                }; 
+                if let Some(formula) = formula {
+                    content.names.push(OdsName { name, sheet, formula }); 
+                }
+            }, 
+            Ok(Event::End(ref e)) if e.local_name().as_ref() == b"table" => {
+                in_table = false; 
+            }, 
+            Ok(Event::Eof) => break, 
+            Err(e) => return Err(Error::from(e)), 
+            _ => {}
+        }
+        buf.clear(); 
+    }
+    Ok(content)
+}
+
+fn attribute<R>(reader: &Reader<R>, e: &BytesStart, key: &[u8]) -> Result<Option<String>, Error> {
+    for a in e.attributes() {
+        let a = a?; 
+        if a.key.local_name().as_ref() == key {
+            return Ok(Some(a.decode_and_unescape_value(reader.decoder())?.to_string())); 
+        }
+    }
+    Ok(None)
+}
+
+fn repeated<R>(reader: &Reader<R>, e: &BytesStart, key: &[u8]) -> Result<usize, Error> {
+    match attribute(reader, e, key)? {
+        Some(count) => parse_count(key, count), 
+        None => Ok(1)
+    }
+}
+
+fn parse_count(key: &[u8], count: String) -> Result<usize, Error> {
+    count.parse::<usize>().map_err(|_| Error::InvalidAttribute(String::from_utf8_lossy(key).to_string(), count))
+}
+
+fn cell_flags<R>(reader: &Reader<R>, e: &BytesStart) -> Result<CellFlags, Error> {
+    let mut flags = CellFlags { repeated: 1, ..CellFlags::default() }; 
+    for a in e.attributes() {
+        let a = a?; 
+        let value = a.decode_and_unescape_value(reader.decoder())?.to_string(); 
+        let key = a.key.local_name(); 
+        match key.as_ref() {
+            b"value-type" if a.key.prefix().is_some_and(|x| x.as_ref() == b"office") || flags.value_type.is_none() => { flags.value_type = Some(value); }, 
+            b"value" | b"date-value" | b"time-value" | b"boolean-value" | b"string-value" => { flags.value = Some(value); }, 
+            b"formula" => { flags.formula = Some(value); }, 
+            b"number-columns-repeated" => { flags.repeated = parse_count(key.as_ref(), value)?; }, 
+            b"number-matrix-rows-spanned" => { flags.matrix_rows = parse_count(key.as_ref(), value)?; }, 
+            b"number-matrix-columns-spanned" => { flags.matrix_columns = parse_count(key.as_ref(), value)?; }, 
+            _ => {}
+        }
+    }
+    Ok(flags)
+}
+
+// Cells ending at column, one per repetition, none when the cell is empty
+fn finish_cell(flags: CellFlags, row: usize, column: usize) -> Result<Vec<OdsCell>, Error> {
+    let start = column - flags.repeated + 1; 
+    let reference = Cell::from((row, start)).to_string(); 
+    let value = match (flags.value_type.as_deref(), flags.value.or(flags.text)) {
+        (Some("float") | Some("percentage") | Some("currency"), Some(v)) => {
+            Value::Num(v.trim().parse::<f64>().map_err(|_| Error::InvalidValue(reference, v))?)
+        }, 
+        (Some("date"), Some(v)) => parse_date(&v).ok_or(Error::InvalidValue(reference, v))?, 
+        (Some("time"), Some(v)) => parse_duration(&v).map(Value::Num).ok_or(Error::InvalidValue(reference, v))?, 
+        (Some("boolean"), Some(v)) => Value::Bool(v == "true" || v == "1"), 
+        (Some("string"), Some(v)) => Value::Text(v), 
+        _ => Value::Empty
+    }; 
+    if value == Value::Empty && flags.formula.is_none() {
+        return Ok(vec![]); 
+    }
+    let matrix = match (flags.matrix_rows, flags.matrix_columns) {
+        (0, 0) => None, 
+        (rows, columns) => Some((rows.max(1), columns.max(1)))
+    }; 
+    Ok((start..=column).map(|c| OdsCell { row, column: c, value: value.clone(), formula: flags.formula.clone(), matrix }).collect())
+}
+
+// office:date-value is an ISO date with an optional time
+fn parse_date(text: &str) -> Option<Value> {
+    if let Ok(d) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Some(Value::from(d)); 
+    }
+    let dt = NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f").ok()?; 
+    match dt.time() == chrono::NaiveTime::MIN {
+        true => Some(Value::from(dt.date())), 
+        false => Some(Value::from(dt))
+    }
+}
+
+// office:time-value is an ISO duration such as PT10H30M00S, returned in days
+fn parse_duration(text: &str) -> Option<f64> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(t) => (true, t), 
+        None => (false, text)
+    }; 
+    let text = text.strip_prefix('P')?; 
+    let (days, time) = text.split_once('T').unwrap_or((text, "")); 
+    let mut total: f64 = match days.strip_suffix('D') {
+        Some(d) => d.parse::<f64>().ok()?, 
+        None if days.is_empty() => 0.0, 
+        None => return None
+    }; 
+    let mut number = String::new(); 
+    for c in time.chars() {
+        match c {
+            'H' => { total += number.parse::<f64>().ok()? / 24.0; number.clear(); }, 
+            'M' => { total += number.parse::<f64>().ok()? / 1440.0; number.clear(); }, 
+            'S' => { total += number.parse::<f64>().ok()? / 86400.0; number.clear(); }, 
+            _ => number.push(c)
+        }
+    }
+    if !number.is_empty() {
+        return None; 
+    }
+    Some(if negative { -total } else { total })
+}
+
+// Rewrites an OpenFormula expression (of:=SUM([.A1:.A3])) in the A1 syntax the parser reads
+pub fn translate_formula(formula: &str) -> Result<String, Error> {
+    let body = match formula.split_once(":=") {
+        Some((namespace, rest)) if namespace.chars().all(|c| c.is_ascii_alphanumeric()) => rest, 
+        _ => formula.strip_prefix('=').unwrap_or(formula)
+    }; 
+    let mut output = String::from("="); 
+    let mut chars = body.chars().peekable(); 
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                output.push(c); 
+                while let Some(s) = chars.next() {
+                    output.push(s); 
+                    if s == '"' {
+                        if chars.peek() == Some(&'"') {
+                            output.push(chars.next().unwrap_or('"')); 
+                        } else {
+                            break; 
+                        }
+                    }
+                }
+            }, 
+            '[' => {
+                let mut reference = String::new(); 
+                let mut quoted = false; 
+                loop {
+                    match chars.next() {
+                        Some('\'') => { quoted = !quoted; reference.push('\''); }, 
+                        Some(']') if !quoted => break, 
+                        Some(r) => reference.push(r), 
+                        None => return Err(Error::UnableToParse(formula.to_string()))
+                    }
+                }
+                output.push_str(&translate_reference(&reference)?); 
+            }, 
+            ';' => output.push(','), 
+            '|' => output.push(';'), 
+            '~' => output.push(','), 
+            '!' => output.push(' '), 
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut identifier = String::from(c); 
+                while let Some(&n) = chars.peek() {
+                    if n.is_ascii_alphanumeric() || n == '_' || n == '.' {
+                        identifier.push(n); 
+                        chars.next(); 
+                    } else {
+                        break; 
+                    }
+                }
+                let identifier = identifier.strip_prefix("COM.MICROSOFT.")
+                    .or_else(|| identifier.strip_prefix("ORG.OPENOFFICE."))
+                    .unwrap_or(&identifier); 
+                output.push_str(identifier); 
+            }, 
+            _ => output.push(c)
+        }
+    }
+    Ok(output)
+}
+
+// [.A1], [Sheet1.A1:.B2] and ['My sheet'.A:.A] without the brackets
+pub fn translate_reference(reference: &str) -> Result<String, Error> {
+    if reference.contains("#REF!") {
+        return Ok(String::from("#REF!")); 
+    }
+    let mut parts: Vec<(Option<String>, String)> = vec![]; 
+    for part in split_unquoted(reference, ':') {
+        parts.push(split_sheet(part).ok_or_else(|| Error::UnableToParse(reference.to_string()))?); 
+    }
+    let (start_sheet, start) = parts.first().cloned().ok_or_else(|| Error::UnableToParse(reference.to_string()))?; 
+    let cells = parts.iter().map(|(_, c)| c.as_str()).collect::<Vec<&str>>().join(":"); 
+    let end_sheet = parts.get(1).and_then(|(s, _)| s.clone()).filter(|s| Some(s) != start_sheet.as_ref()); 
+    match (start_sheet, end_sheet) {
+        (None, _) if parts.len() == 1 => Ok(start), 
+        (None, _) => Ok(cells), 
+        (Some(s), None) => Ok(format!("{}!{}", quote_sheet(&s), cells)), 
+        (Some(s), Some(e)) => Ok(format!("{}:{}!{}", quote_sheet(&s), quote_sheet(&e), cells))
+    }
+}
+
+fn split_unquoted(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![]; 
+    let mut quoted = false; 
+    let mut start = 0; 
+    for (i, c) in text.char_indices() {
+        match c {
+            '\'' => quoted = !quoted, 
+            c if c == separator && !quoted => {
+                parts.push(&text[start..i]); 
+                start = i + c.len_utf8(); 
+            }, 
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]); 
+    parts
+}
+
+// Sheet name (None for the current sheet) and cell of one side of a reference
+fn split_sheet(part: &str) -> Option<(Option<String>, String)> {
+    let part = part.strip_prefix('$').unwrap_or(part); 
+    if let Some(quoted) = part.strip_prefix('\'') {
+        let mut name = String::new(); 
+        let mut chars = quoted.char_indices().peekable(); 
+        while let Some((i, c)) = chars.next() {
+            if c == '\'' {
+                if chars.peek().map(|x| x.1) == Some('\'') {
+                    chars.next(); 
+                    name.push('\''); 
+                } else {
+                    return Some((Some(name), quoted[i + 1..].strip_prefix('.')?.to_string())); 
+                }
+            } else {
+                name.push(c); 
+            }
+        }
+        return None; 
+    }
+    let (sheet, cell) = part.split_once('.')?; 
+    match sheet.is_empty() {
+        true => Some((None, cell.to_string())), 
+        false => Some((Some(sheet.to_string()), cell.to_string()))
+    }
+}
+
+fn quote_sheet(name: &str) -> String {
+    if name.chars().all(|c| c.is_alphanumeric() || c == '_') && !name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.to_string()
+    } else {
+        format!("'{}'", name.replace('\'', "''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ods::{translate_formula, translate_reference, parse_duration}; 
+    use crate::errors::Error; 
+
+    #[test]
+    fn test_translate_formula() -> Result<(), Error> {
+        assert_eq!(translate_formula("of:=SUM([.A1:.A3])")?, "=SUM(A1:A3)"); 
+        assert_eq!(translate_formula("of:=IF([.$B$2]>0;\"a;b\";[Data.C1])")?, "=IF($B$2>0,\"a;b\",Data!C1)"); 
+        assert_eq!(translate_formula("of:=SUM(['My ''Data'''.A:.A])")?, "=SUM('My ''Data'''!A:A)"); 
+        assert_eq!(translate_formula("of:=SUM({1;2|3;4})")?, "=SUM({1,2;3,4})"); 
+        assert_eq!(translate_formula("of:=COM.MICROSOFT.CONCAT(\"a\";\"\"\"b\")")?, "=CONCAT(\"a\",\"\"\"b\")"); 
+        assert_eq!(translate_formula("=[$Sheet1.A1]+1")?, "=Sheet1!A1+1"); 
+        assert!(translate_formula("of:=[.A1").is_err()); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_translate_reference() -> Result<(), Error> {
+        assert_eq!(translate_reference("$Sheet1.$A$1")?, "Sheet1!$A$1"); 
+        assert_eq!(translate_reference("Sheet1.A1:Sheet1.B2")?, "Sheet1!A1:B2"); 
+        assert_eq!(translate_reference("Jan.A1:Mar.B2")?, "Jan:Mar!A1:B2"); 
+        assert_eq!(translate_reference(".#REF!A1")?, "#REF!"); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("PT12H00M00S"), Some(0.5)); 
+        assert_eq!(parse_duration("P1DT06H"), Some(1.25)); 
+        assert_eq!(parse_duration("PT1X"), None); 
+    }
+}
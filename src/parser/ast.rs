@@ -1,9 +1,13 @@
 use std::fmt;  
+use std::str::FromStr; 
+use crate::reference::Reference; 
+use crate::lexer::token::Span; 
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Expr {
     Literal(Literal),
     Prefix(Prefix, Box<Expr>),
+    Postfix(Postfix, Box<Expr>),
     Infix(Infix, Box<Expr>, Box<Expr>),
 	Func {
         name: String, 
@@ -12,6 +16,19 @@ pub enum Expr {
     Reference {
         sheet: Option<String>, 
         reference: String 
+    }, 
+    MultiSheetReference {
+        sheets: (String, String), // First and last sheet
+        reference: String 
+    }, 
+    Name {
+        sheet: Option<String>, 
+        name: String
+    }, 
+    StructuredReference {
+        table: Option<String>, 
+        specifiers: Vec<TableSpecifier>, 
+        columns: Option<(String, String)> // First and last column
     }, 
 	Array(Vec<Expr>),
     Error(Error)
@@ -21,7 +38,10 @@ impl fmt::Display for Expr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Expr::Literal(l) => write!(f, "{}", l), 
+            // -A1% reads as (-A1)%, the other order needs parentheses 
+            Expr::Prefix(p, e) if matches!(**e, Expr::Postfix(Postfix::Percent, _)) => write!(f, "{}({})", p, e), 
             Expr::Prefix(p, e) => write!(f, "{}{}", p, e), 
+            Expr::Postfix(p, e) => write!(f, "{}{}", e, p), 
             Expr::Infix(p, a, b) => write!(f, "({}{}{})", a, p, b), 
             Expr::Func{name, args} => {
                 let output = format!("{}({})", name, exprs_string(args));
@@ -30,17 +50,62 @@ impl fmt::Display for Expr {
             Expr::Reference{sheet, reference} => {
                 match sheet {
                     Some(s) => {
-                        write!(f, "{}!{}", s, reference)
+                        write!(f, "{}!{}", quote_sheet(s), reference)
                     }, 
                     None => write!(f, "{}", reference)
                 }
             }, 
+            Expr::MultiSheetReference { sheets: (first, last), reference } => write!(f, "{}!{}", quote_sheet(&format!("{}:{}", first, last)), reference), 
+            Expr::Name{sheet, name} => {
+                match sheet {
+                    Some(s) => write!(f, "{}!{}", quote_sheet(s), name), 
+                    None => write!(f, "{}", name)
+                }
+            }, 
+            Expr::StructuredReference { table, specifiers, columns } => {
+                let mut items: Vec<String> = specifiers.iter().map(|x| format!("[{}]", x)).collect(); 
+                if let Some((first, last)) = columns {
+                    if first == last {
+                        items.push(format!("[{}]", escape_column(first))); 
+                    } else {
+                        items.push(format!("[{}]:[{}]", escape_column(first), escape_column(last))); 
+                    }
+                }
+                let table = table.clone().unwrap_or_default(); 
+                if items.len() == 1 && specifiers.is_empty() {
+                    write!(f, "{}{}", table, items[0])
+                } else {
+                    write!(f, "{}[{}]", table, items.join(","))
+                }
+            }, 
             Expr::Array(arr) => write!(f, "{{{}}}", exprs_string(arr)), 
             Expr::Error(e) => write!(f, "{}", e)
         }
     }
 }
 
+// Sheet names other than letters, digits and underscores are quoted, Jan:Dec as a whole: 'Jan 2024:Dec 2024' 
+fn quote_sheet(name: &str) -> String {
+    let plain = |s: &str| s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') && !s.starts_with(|c: char| c.is_ascii_digit()); 
+    match name.split_once(':') {
+        Some((first, last)) if plain(first) && plain(last) => name.to_string(), 
+        None if plain(name) => name.to_string(), 
+        _ => format!("'{}'", name.replace('\'', "''"))
+    }
+}
+
+// Brackets, pound signs and apostrophes in column names are escaped with an apostrophe 
+fn escape_column(s: &str) -> String {
+    let mut output = String::new(); 
+    for c in s.chars() {
+        if "[]#'".contains(c) {
+            output.push('\''); 
+        }
+        output.push(c); 
+    }
+    output
+}
+
 fn exprs_string(v: &Vec<Expr>) -> String {
     let mut output = String::new(); 
     for (i, arg) in v.iter().enumerate() {
@@ -77,6 +142,81 @@ impl From<bool> for Expr {
     }
 }
 
+impl Expr {
+    // References as R1C1 seen from the cell at row and column. Whole rows and columns stay A1, 
+    // R2 or C3 would read back as cells 
+    pub fn to_r1c1(&self, row: usize, column: usize) -> Expr {
+        self.map_references(&|r| {
+            if Reference::is_r1c1(r) {
+                return Some(r.to_string()); 
+            }
+            let reference = Reference::from(r); 
+            if reference.is_hrange() || reference.is_vrange() {
+                Some(r.to_string())
+            } else {
+                Some(reference.to_r1c1(row, column))
+            }
+        })
+    }
+
+    // R1C1 references back to A1 for the cell at row and column, #REF! when they fall off the sheet 
+    pub fn to_a1(&self, row: usize, column: usize) -> Expr {
+        self.map_references(&|r| {
+            if Reference::is_r1c1(r) {
+                Reference::from_r1c1(r, row, column).map(|x| x.to_string())
+            } else {
+                Some(r.to_string())
+            }
+        })
+    }
+
+    // Defined names used in the expression with the sheet they are qualified with 
+    pub fn names(&self) -> Vec<(Option<String>, String)> {
+        match self {
+            Expr::Name { sheet, name } => vec![(sheet.clone(), name.clone())], 
+            Expr::Prefix(_, a) | Expr::Postfix(_, a) => a.names(), 
+            Expr::Infix(_, a, b) => [a.names(), b.names()].concat(), 
+            Expr::Func { args: items, .. } | Expr::Array(items) => items.iter().flat_map(|a| a.names()).collect(), 
+            _ => vec![]
+        }
+    }
+
+    fn map_references(&self, f: &dyn Fn(&str) -> Option<String>) -> Expr {
+        let map = |e: &Expr| Box::new(e.map_references(f)); 
+        match self {
+            Expr::Reference { sheet, reference } => match f(reference) {
+                Some(reference) => Expr::Reference { sheet: sheet.clone(), reference }, 
+                None => Expr::Error(Error::Ref)
+            }, 
+            Expr::MultiSheetReference { sheets, reference } => match f(reference) {
+                Some(reference) => Expr::MultiSheetReference { sheets: sheets.clone(), reference }, 
+                None => Expr::Error(Error::Ref)
+            }, 
+            Expr::Prefix(p, a) => Expr::Prefix(p.clone(), map(a)), 
+            Expr::Postfix(p, a) => Expr::Postfix(p.clone(), map(a)), 
+            Expr::Infix(op, a, b) => Expr::Infix(op.clone(), map(a), map(b)), 
+            Expr::Func { name, args } => Expr::Func { name: name.clone(), args: args.iter().map(|a| a.map_references(f)).collect() }, 
+            Expr::Array(items) => Expr::Array(items.iter().map(|a| a.map_references(f)).collect()), 
+            e => e.clone()
+        }
+    }
+}
+
+// An expression with the parts of the formula it was parsed from. The spans mirror the expression tree 
+// so that the expression is built once rather than copied into every node 
+#[derive(PartialEq, Debug, Clone)]
+pub struct SpannedExpr {
+    pub expr: Expr, 
+    pub spans: Spans
+}
+
+// Span of an expression, children are the operands, arguments or array items in the order they appear 
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Spans {
+    pub span: Span, 
+    pub children: Vec<Spans>
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Error {
     Null,
@@ -86,7 +226,14 @@ pub enum Error {
     Name, 
     Num, 
     NA, 
-    GettingData
+    GettingData, 
+    Spill, 
+    Calc, 
+    Field, 
+    Blocked, 
+    Connect, 
+    Busy, 
+    Unknown
 }
 
 impl fmt::Display for Error {
@@ -96,10 +243,64 @@ impl fmt::Display for Error {
             Error::Div => write!(f, "#DIV/0!"), 
             Error::Value => write!(f, "#VALUE!"),
             Error::Ref => write!(f, "#REF!"),
-            Error::Name => write!(f, "#NAME!"), 
+            Error::Name => write!(f, "#NAME?"), 
             Error::Num => write!(f, "#NUM!"), 
-            Error::NA => write!(f, "#N/A!"), 
-            Error::GettingData => write!(f, "#GETTING_DATA")
+            Error::NA => write!(f, "#N/A"), 
+            Error::GettingData => write!(f, "#GETTING_DATA"), 
+            Error::Spill => write!(f, "#SPILL!"), 
+            Error::Calc => write!(f, "#CALC!"), 
+            Error::Field => write!(f, "#FIELD!"), 
+            Error::Blocked => write!(f, "#BLOCKED!"), 
+            Error::Connect => write!(f, "#CONNECT!"), 
+            Error::Busy => write!(f, "#BUSY!"), 
+            Error::Unknown => write!(f, "#UNKNOWN!")
+        }
+    }
+}
+
+impl FromStr for Error {
+    type Err = String; 
+
+    // Accepts both the spelling stored in workbooks (#NAME?, #N/A) and the displayed one 
+    fn from_str(s: &str) -> Result<Error, String> {
+        match s {
+            "#NULL!" => Ok(Error::Null), 
+            "#DIV/0!" => Ok(Error::Div), 
+            "#VALUE!" => Ok(Error::Value), 
+            "#REF!" => Ok(Error::Ref), 
+            "#NAME?" | "#NAME!" => Ok(Error::Name), 
+            "#NUM!" => Ok(Error::Num), 
+            "#N/A" | "#N/A!" => Ok(Error::NA), 
+            "#GETTING_DATA" => Ok(Error::GettingData), 
+            "#SPILL!" => Ok(Error::Spill), 
+            "#CALC!" => Ok(Error::Calc), 
+            "#FIELD!" => Ok(Error::Field), 
+            "#BLOCKED!" => Ok(Error::Blocked), 
+            "#CONNECT!" => Ok(Error::Connect), 
+            "#BUSY!" => Ok(Error::Busy), 
+            "#UNKNOWN!" => Ok(Error::Unknown), 
+            _ => Err(s.to_string())
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TableSpecifier {
+    All, 
+    Data, 
+    Headers, 
+    Totals, 
+    ThisRow 
+}
+
+impl fmt::Display for TableSpecifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TableSpecifier::All => write!(f, "#All"), 
+            TableSpecifier::Data => write!(f, "#Data"), 
+            TableSpecifier::Headers => write!(f, "#Headers"), 
+            TableSpecifier::Totals => write!(f, "#Totals"), 
+            TableSpecifier::ThisRow => write!(f, "#This Row"), 
         }
     }
 }
@@ -109,6 +310,7 @@ pub enum Literal {
     Number(f64),
     Boolean(bool),
     Text(String),
+    Empty, // An argument left out, IF(A1,,2) 
 }
 
 impl From<f64> for Literal {
@@ -146,7 +348,8 @@ impl fmt::Display for Literal {
                     write!(f, "FALSE")
                 }
             },
-            Literal::Text(s) => write!(f, "{}", s)
+            Literal::Text(s) => write!(f, "\"{}\"", s.replace('"', "\"\"")), 
+            Literal::Empty => Ok(())
         }
     }
 }
@@ -155,6 +358,7 @@ impl fmt::Display for Literal {
 pub enum Prefix {
     Plus,
     Minus,
+    ImplicitIntersection, 
 }
 
 impl fmt::Display for Prefix {
@@ -162,6 +366,22 @@ impl fmt::Display for Prefix {
         match self {
             Prefix::Plus => write!(f, "+"), 
             Prefix::Minus => write!(f, "-"), 
+            Prefix::ImplicitIntersection => write!(f, "@"), 
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Postfix {
+    Spill, 
+    Percent, 
+}
+
+impl fmt::Display for Postfix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Postfix::Spill => write!(f, "#"), 
+            Postfix::Percent => write!(f, "%"), 
         }
     }
 }
@@ -181,6 +401,9 @@ pub enum Infix {
     LessThanEqual,
     GreaterThan,
     LessThan,
+    Range, 
+    Intersect, 
+    Union, 
 }
 
 impl fmt::Display for Infix {
@@ -197,7 +420,10 @@ impl fmt::Display for Infix {
             Infix::GreaterThanEqual => write!(f, ">="), 
             Infix::LessThanEqual => write!(f, "<="), 
             Infix::GreaterThan => write!(f, ">"), 
-            Infix::LessThan => write!(f, "<") 
+            Infix::LessThan => write!(f, "<"), 
+            Infix::Range => write!(f, ":"), 
+            Infix::Intersect => write!(f, " "), 
+            Infix::Union => write!(f, ",") 
         }
     }
 }
@@ -211,4 +437,7 @@ pub enum Precedence {
     MultDiv, 
     Exponent, 
     Percent, 
+    Union, 
+    Intersect, 
+    Range, 
 }
@@ -1,27 +1,140 @@
 use nom::branch::*;
 use nom::bytes::complete::take;
-use nom::combinator::{map, verify, opt};
+use nom::combinator::{map, opt, cut};
 use nom::multi::many0;
 use nom::sequence::{preceded, delimited, pair, terminated};
 use nom::*;
 use nom::Err; 
-use nom::error::{Error as NomError, ErrorKind}; 
+use nom::error::{ParseError as NomParseError, ErrorKind}; 
 
 pub mod ast; 
 
 use crate::{
     lexer::{
         Lexer,
-        token::{Token, Tokens}, 
+        token::{Token, Tokens, Span}, 
     }, 
-    parser::ast::{Expr, Error as ExcelError, Literal, Prefix, Infix, Precedence}, 
-    errors::Error
+    parser::ast::{Expr, SpannedExpr, Spans, Error as ExcelError, Literal, Prefix, Postfix, Infix, Precedence, TableSpecifier}, 
+    errors::{Error, Diagnostic}
 }; 
 
+// The furthest token the parser reached and what could have come there, alternatives failing 
+// at the same token add up 
+#[derive(Debug, PartialEq)]
+pub struct ParseError<'a> {
+    pub input: Tokens<'a>, 
+    pub expected: Expected
+}
+
+// What could have come instead, one bit per kind of expression and per token. Backtracking fails often, 
+// so nothing is allocated until the diagnostic is built 
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Expected(u64); 
+
+const KINDS: [&str; 6] = ["a value", "an error", "a name", "a sheet", "a reference", "a table reference"]; 
+
+static TAGS: [Token; 21] = [
+    Token::Comma, Token::Plus, Token::Minus, Token::Divide, Token::Multiply, Token::Exponent, Token::Ampersand, 
+    Token::Hash, Token::At, Token::Percent, Token::Colon, Token::Space, Token::Equal, Token::SemiColon, 
+    Token::LAngle, Token::RAngle, Token::LParen, Token::RParen, Token::LBrace, Token::RBrace, Token::EOF
+]; 
+
+impl Expected {
+    pub const VALUE: Expected = Expected(1); 
+    pub const ERROR: Expected = Expected(1 << 1); 
+    pub const NAME: Expected = Expected(1 << 2); 
+    pub const SHEET: Expected = Expected(1 << 3); 
+    pub const REFERENCE: Expected = Expected(1 << 4); 
+    pub const TABLE_REFERENCE: Expected = Expected(1 << 5); 
+
+    // Tags are unit variants, comparing variants avoids printing both tokens 
+    fn token(token: &Token) -> Expected {
+        match TAGS.iter().position(|t| std::mem::discriminant(t) == std::mem::discriminant(token)) {
+            Some(i) => Expected(1 << (KINDS.len() + i)), 
+            None => Expected::default()
+        }
+    }
+
+    pub fn descriptions(&self) -> Vec<String> {
+        let kinds = KINDS.iter().map(|k| k.to_string()); 
+        let tags = TAGS.iter().map(|t| match t {
+            Token::EOF => String::from("end of formula"), 
+            t => format!("'{}'", t)
+        }); 
+        kinds.chain(tags).enumerate().filter(|(i, _)| self.0 & (1 << i) != 0).map(|(_, s)| s).collect()
+    }
+}
+
+impl<'a> NomParseError<Tokens<'a>> for ParseError<'a> {
+    fn from_error_kind(input: Tokens<'a>, _: ErrorKind) -> Self {
+        ParseError { input, expected: Expected::default() }
+    }
+
+    fn append(_: Tokens<'a>, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn or(mut self, other: Self) -> Self {
+        match self.input.tok.len().cmp(&other.input.tok.len()) {
+            std::cmp::Ordering::Less => self, 
+            std::cmp::Ordering::Greater => other, 
+            std::cmp::Ordering::Equal => {
+                self.expected = Expected(self.expected.0 | other.expected.0); 
+                self
+            }
+        }
+    }
+}
+
+impl<'a> ParseError<'a> {
+    pub fn diagnostic(&self, formula: &str) -> Diagnostic {
+        let offset: usize = self.input.spans.first().map(|x| x.start).unwrap_or(formula.len()); 
+        let found: String = match (self.input.tok.first(), self.input.spans.first()) {
+            (Some(Token::EOF) | None, _) => String::from("end of formula"), 
+            (Some(_), Some(span)) => format!("'{}'", &formula[span.start..span.end]), 
+            (Some(token), None) => format!("'{}'", token)
+        }; 
+        Diagnostic { formula: formula.to_string(), offset, expected: self.expected.descriptions(), found }
+    }
+}
+
+type IResult<'a, O> = nom::IResult<Tokens<'a>, O, ParseError<'a>>; 
+
+fn expected<'a>(input: Tokens<'a>, expected: Expected) -> Err<ParseError<'a>> {
+    Err::Error(ParseError { input, expected })
+}
+
+// Byte range of the tokens a parser consumed between input and rest 
+fn consumed(input: Tokens, rest: Tokens) -> Span {
+    let count: usize = input.tok.len() - rest.tok.len(); 
+    match (input.spans.first(), count) {
+        (Some(first), 0) => Span { start: first.start, end: first.start }, 
+        (Some(first), _) => Span { start: first.start, end: input.spans[count-1].end }, 
+        (None, _) => Span::default()
+    }
+}
+
+// Expressions with the span of everything the parser consumed for them 
+fn spanned<'a, P>(mut parser: P) -> impl FnMut(Tokens<'a>) -> IResult<'a, SpannedExpr> 
+where P: Parser<Tokens<'a>, Output = (Expr, Vec<Spans>), Error = ParseError<'a>> {
+    move |input: Tokens<'a>| {
+        let (rest, (expr, children)) = parser.parse(input)?; 
+        Ok((rest, SpannedExpr { expr, spans: Spans { span: consumed(input, rest), children } }))
+    }
+}
+
+fn leaf<'a>(parser: fn(Tokens<'a>) -> IResult<'a, Expr>) -> impl FnMut(Tokens<'a>) -> IResult<'a, SpannedExpr> {
+    spanned(map(parser, |expr| (expr, vec![])))
+}
+
 macro_rules! tag_token (
 	($func_name:ident, $tag: expr) => (
-		fn $func_name(tokens: Tokens) -> IResult<Tokens, Tokens> {
-			verify(take(1usize), |t: &Tokens| t.tok[0] == $tag).parse(tokens)
+		fn $func_name(tokens: Tokens) -> IResult<Tokens> {
+            let token: IResult<Tokens> = take(1usize).parse(tokens); 
+            match token {
+                Ok((rest, t)) if t.tok[0] == $tag => Ok((rest, t)), 
+                _ => Err(expected(tokens, Expected::token(&$tag)))
+            }
 		}
 	)
 );
@@ -33,6 +146,11 @@ tag_token!(divide_tag, Token::Divide);
 tag_token!(multiply_tag, Token::Multiply); 
 tag_token!(exponent_tag, Token::Exponent); 
 tag_token!(ampersand_tag, Token::Ampersand); 
+tag_token!(hash_tag, Token::Hash); 
+tag_token!(at_tag, Token::At); 
+tag_token!(percent_tag, Token::Percent); 
+tag_token!(colon_tag, Token::Colon); 
+tag_token!(space_tag, Token::Space); 
 tag_token!(equal_tag, Token::Equal); 
 tag_token!(semicolon_tag, Token::SemiColon); 
 tag_token!(langle_tag, Token::LAngle); 
@@ -43,29 +161,29 @@ tag_token!(lbrace_tag, Token::LBrace);
 tag_token!(rbrace_tag, Token::RBrace); 
 tag_token!(eof_tag, Token::EOF); 
 
-fn parse_literal(input: Tokens) -> IResult<Tokens, Literal> {
+fn parse_literal(input: Tokens) -> IResult<Literal> {
     let (i1, t1) = take(1usize).parse(input)?;
 	if t1.tok.is_empty() {
-        Err(Err::Error(NomError::new(input, ErrorKind::Tag)))
+        Err(expected(input, Expected::VALUE))
     } else {
         match t1.tok[0].clone() {
             Token::Integer(x) => Ok((i1, Literal::Number(x as f64))), 
             Token::Float(x) => Ok((i1, Literal::Number(x))), 
             Token::Text(s) => Ok((i1, Literal::Text(s))),
             Token::Boolean(b) => Ok((i1, Literal::Boolean(b))),
-            _ => Err(Err::Error(NomError::new(input, ErrorKind::Tag))),
+            _ => Err(expected(input, Expected::VALUE)),
         }
     }
 }
 
-fn parse_literal_expr(input: Tokens) -> IResult<Tokens, Expr> {
+fn parse_literal_expr(input: Tokens) -> IResult<Expr> {
     map(parse_literal, Expr::Literal).parse(input)
 }
 
-fn parse_error(input: Tokens) -> IResult<Tokens, ExcelError> {
+fn parse_error(input: Tokens) -> IResult<ExcelError> {
     let (i1, t1) = take(1usize).parse(input)?;
 	if t1.tok.is_empty() {
-        Err(Err::Error(NomError::new(input, ErrorKind::Tag)))
+        Err(expected(input, Expected::ERROR))
     } else {
         match t1.tok[0].clone() {
             Token::Null => Ok((i1, ExcelError::Null)), 
@@ -76,68 +194,82 @@ fn parse_error(input: Tokens) -> IResult<Tokens, ExcelError> {
             Token::Num => Ok((i1, ExcelError::Num)), 
             Token::NA => Ok((i1, ExcelError::NA)), 
             Token::GettingData => Ok((i1, ExcelError::GettingData)), 
-            _ => Err(Err::Error(NomError::new(input, ErrorKind::Tag)))
+            Token::Spill => Ok((i1, ExcelError::Spill)), 
+            Token::Calc => Ok((i1, ExcelError::Calc)), 
+            Token::Field => Ok((i1, ExcelError::Field)), 
+            Token::Blocked => Ok((i1, ExcelError::Blocked)), 
+            Token::Connect => Ok((i1, ExcelError::Connect)), 
+            Token::Busy => Ok((i1, ExcelError::Busy)), 
+            Token::Unknown => Ok((i1, ExcelError::Unknown)), 
+            _ => Err(expected(input, Expected::ERROR))
         }
     }
 }
 
-fn parse_error_expr(input: Tokens) -> IResult<Tokens, Expr> {
+fn parse_error_expr(input: Tokens) -> IResult<Expr> {
     map(parse_error, Expr::Error).parse(input)
 }
 
-fn parse_ident(input: Tokens) -> IResult<Tokens, Token> {
+fn parse_ident(input: Tokens) -> IResult<Token> {
     let (i1, t1) = take(1usize).parse(input)?;
     if t1.tok.is_empty() {
-        Err(Err::Error(NomError::new(input, ErrorKind::Tag)))
+        Err(expected(input, Expected::NAME))
     } else if matches!(t1.tok[0], Token::Ident(_)) {
         Ok((i1, t1.tok[0].clone()))
     } else {
-        Err(Err::Error(NomError::new(input, ErrorKind::Tag)))
+        Err(expected(input, Expected::NAME))
     }
 }
 
-fn parse_func_expr(input: Tokens) -> IResult<Tokens, Expr> {
-   map(
+fn parse_func_expr(input: Tokens) -> IResult<SpannedExpr> {
+   spanned(map(
        pair(
            parse_ident, 
-           delimited(
+           // Nothing else follows a name with an opening parenthesis 
+           preceded(
                lparen_tag,
-               alt((parse_exprs, empty_boxed_vec)),
-               rparen_tag,
+               cut(terminated(alt((empty_args, parse_args)), rparen_tag)),
            )
         ),
-        |(ident, exprs)| {
-            Expr::Func { name: format!("{}", ident), args: exprs }
+        |(ident, args)| {
+            let (mut exprs, spans): (Vec<Expr>, Vec<Spans>) = args.into_iter().map(|x| (x.expr, x.spans)).unzip(); 
+            // Workbooks store A1# and @ as functions 
+            let expr = match format!("{}", ident).as_str() {
+                "ANCHORARRAY" if exprs.len() == 1 => Expr::Postfix(Postfix::Spill, Box::new(exprs.remove(0))), 
+                "SINGLE" if exprs.len() == 1 => Expr::Prefix(Prefix::ImplicitIntersection, Box::new(exprs.remove(0))), 
+                name => Expr::Func { name: name.to_string(), args: exprs }
+            }; 
+            (expr, spans)
         }
-   ).parse(input)
+   )).parse(input)
 }
 
-fn parse_prefix_expr(input: Tokens) -> IResult<Tokens, Expr> {
-    map(
-        pair(alt((plus_tag, minus_tag)), parse_atom_expr), 
+fn parse_prefix_expr(input: Tokens) -> IResult<SpannedExpr> {
+    spanned(map(
+        pair(alt((plus_tag, minus_tag, at_tag)), parse_atom_expr), 
         |(pre, expr)| {
             let prefix = match &pre.tok[0] {
                 Token::Plus => Prefix::Plus, 
                 Token::Minus => Prefix::Minus, 
+                Token::At => Prefix::ImplicitIntersection, 
                 _ => unreachable!()
             }; 
-            let box_expr = Box::new(expr); 
-            Expr::Prefix(prefix, box_expr)
+            (Expr::Prefix(prefix, Box::new(expr.expr)), vec![expr.spans])
         }
-    ).parse(input)
+    )).parse(input)
 }
 
 
-fn parse_comma_exprs(input: Tokens) -> IResult<Tokens, Expr> {
+fn parse_comma_exprs(input: Tokens) -> IResult<SpannedExpr> {
     map(
-        preceded(alt((comma_tag, semicolon_tag)), parse_expr), 
+        preceded(alt((comma_tag, semicolon_tag)), cut(parse_expr)), 
         |expr| {
             expr
         }
     ).parse(input)
 }
 
-fn parse_exprs(input: Tokens) -> IResult<Tokens, Vec<Expr>> {
+fn parse_exprs(input: Tokens) -> IResult<Vec<SpannedExpr>> {
     map(
         pair(parse_expr, many0(parse_comma_exprs)),
         |(first, second)| {
@@ -146,68 +278,237 @@ fn parse_exprs(input: Tokens) -> IResult<Tokens, Vec<Expr>> {
     ).parse(input)
 }
 
-fn empty_boxed_vec(input: Tokens) -> IResult<Tokens, Vec<Expr>> {
+// Arguments may be left out, IF(A1,,2) passes an empty second argument 
+fn parse_args(input: Tokens) -> IResult<Vec<SpannedExpr>> {
+    map(
+        pair(parse_arg, many0(preceded(alt((comma_tag, semicolon_tag)), cut(parse_arg)))),
+        |(first, rest)| {
+            [vec![first], rest].concat()
+        }
+    ).parse(input)
+}
+
+fn parse_arg(input: Tokens) -> IResult<SpannedExpr> {
+    match input.tok.first() {
+        Some(Token::Comma | Token::SemiColon | Token::RParen) => {
+            let spans = Spans { span: consumed(input, input), children: vec![] }; 
+            Ok((input, SpannedExpr { expr: Expr::Literal(Literal::Empty), spans }))
+        }, 
+        _ => parse_expr(input)
+    }
+}
+
+fn empty_args(input: Tokens) -> IResult<Vec<SpannedExpr>> {
+    match input.tok.first() {
+        Some(Token::RParen) => Ok((input, vec![])), 
+        _ => Err(expected(input, Expected::token(&Token::RParen)))
+    }
+}
+
+fn empty_boxed_vec(input: Tokens) -> IResult<Vec<SpannedExpr>> {
     Ok((input, vec![]))
 }
 
-fn parse_array_expr(input: Tokens) -> IResult<Tokens, Expr> {
-    map(
+fn parse_array_expr(input: Tokens) -> IResult<SpannedExpr> {
+    spanned(map(
         delimited(
             lbrace_tag, 
             alt((parse_exprs, empty_boxed_vec)),
             rbrace_tag,
         ), 
         |exprs| {
-            Expr::Array(exprs)
+            let (items, spans): (Vec<Expr>, Vec<Spans>) = exprs.into_iter().map(|x| (x.expr, x.spans)).unzip(); 
+            (Expr::Array(items), spans)
         }
-    ).parse(input)
+    )).parse(input)
 }
 
-fn parse_sheet_or_multisheet(input: Tokens) -> IResult<Tokens, Token> {
+fn parse_sheet_or_multisheet(input: Tokens) -> IResult<Token> {
     let (i1, t1) = take(1usize).parse(input)?;
     if t1.tok.is_empty() {
-        Err(Err::Error(NomError::new(input, ErrorKind::Tag)))
+        Err(expected(input, Expected::SHEET))
     } else {
         match &t1.tok[0] {
             Token::MultiSheet(s) => Ok((i1, Token::MultiSheet(s.to_string()))), 
             Token::Sheet(s) => Ok((i1, Token::Sheet(s.to_string()))), 
-            _ => Err(Err::Error(NomError::new(input, ErrorKind::Tag)))
+            _ => Err(expected(input, Expected::SHEET))
         }
     }
 }
 
-fn parse_cell_or_range(input: Tokens) -> IResult<Tokens, Token> {
+fn parse_cell_or_range(input: Tokens) -> IResult<Token> {
     let (i1, t1) = take(1usize).parse(input)?;
     if t1.tok.is_empty() {
-        Err(Err::Error(NomError::new(input, ErrorKind::Tag)))
+        Err(expected(input, Expected::REFERENCE))
     } else {
         match &t1.tok[0] {
             Token::Range(s) => Ok((i1, Token::Range(s.to_string()))), 
             Token::Cell(s) => Ok((i1, Token::Cell(s.to_string()))), 
             Token::VRange(s) => Ok((i1, Token::VRange(s.to_string()))), 
             Token::HRange(s) => Ok((i1, Token::HRange(s.to_string()))), 
-            _ => Err(Err::Error(NomError::new(input, ErrorKind::Tag)))
+            Token::R1C1(s) => Ok((i1, Token::R1C1(s.to_string()))), 
+            _ => Err(expected(input, Expected::REFERENCE))
         }
     }
 }
 
-fn parse_reference_expr(input: Tokens) -> IResult<Tokens, Expr> {
+fn parse_reference_expr(input: Tokens) -> IResult<Expr> {
     map(
         pair(
             opt(parse_sheet_or_multisheet), parse_cell_or_range
         ), 
         |(sheet, range)| {
-            let sheet : Option<String> = sheet.map(|x| format!("{}", x));
-            Expr::Reference {
-                sheet, reference: format!("{}", range)
+            match sheet {
+                Some(Token::MultiSheet(s)) => {
+                    let (first, last) = s.split_once(':').unwrap(); 
+                    Expr::MultiSheetReference {
+                        sheets: (first.to_string(), last.to_string()), reference: format!("{}", range)
+                    }
+                }, 
+                _ => {
+                    let sheet : Option<String> = sheet.map(|x| format!("{}", x));
+                    Expr::Reference {
+                        sheet, reference: format!("{}", range)
+                    }
+                }
             }
        }
     ).parse(input)
 }
 
+fn parse_spill_expr(input: Tokens) -> IResult<SpannedExpr> {
+    spanned(map(
+        terminated(alt((leaf(parse_reference_expr), leaf(parse_name_expr))), hash_tag), 
+        |expr| (Expr::Postfix(Postfix::Spill, Box::new(expr.expr)), vec![expr.spans])
+    )).parse(input)
+}
+
+fn parse_name_expr(input: Tokens) -> IResult<Expr> {
+    map(
+        pair(
+            opt(parse_sheet_or_multisheet), parse_ident
+        ), 
+        |(sheet, name)| {
+            let sheet : Option<String> = sheet.map(|x| format!("{}", x));
+            Expr::Name { sheet, name: format!("{}", name) }
+        }
+    ).parse(input)
+}
+
+fn parse_structured_reference_expr(input: Tokens) -> IResult<Expr> {
+    let (i1, t1) = take(1usize).parse(input)?;
+    if t1.tok.is_empty() {
+        Err(expected(input, Expected::TABLE_REFERENCE))
+    } else {
+        match &t1.tok[0] {
+            Token::StructuredReference(s) => {
+                match split_structured_reference(s) {
+                    Some(expr) => Ok((i1, expr)), 
+                    None => Err(expected(input, Expected::TABLE_REFERENCE))
+                }
+            }, 
+            _ => Err(expected(input, Expected::TABLE_REFERENCE))
+        }
+    }
+}
+
+// Sales[[#This Row],[Qty]:[Price]] -> table, specifiers and column range 
+fn split_structured_reference(s: &str) -> Option<Expr> {
+    let open = s.find('[')?; 
+    let table: Option<String> = if open > 0 { Some(s[..open].to_string()) } else { None }; 
+    let mut specifiers: Vec<TableSpecifier> = vec![]; 
+    let mut columns: Option<(String, String)> = None; 
+    let mut rest: &str = s[open+1..s.len()-1].trim(); 
+    if let Some(r) = rest.strip_prefix('@') {
+        specifiers.push(TableSpecifier::ThisRow); 
+        rest = r.trim_start(); 
+    }
+    if !rest.starts_with('[') {
+        // A single item without inner brackets: Sales[Amount], Sales[#All], [@Qty]
+        if !rest.is_empty() {
+            push_structured_item(rest, &mut specifiers, &mut columns, false)?; 
+        }
+    } else {
+        let mut is_range = false; 
+        while !rest.is_empty() {
+            let end = closing_bracket(rest)?; 
+            push_structured_item(&rest[1..end], &mut specifiers, &mut columns, is_range)?; 
+            rest = rest[end+1..].trim_start(); 
+            is_range = rest.starts_with(':'); 
+            if is_range || rest.starts_with(',') {
+                rest = rest[1..].trim_start(); 
+            } else if !rest.is_empty() {
+                return None
+            }
+        }
+    }
+    Some(Expr::StructuredReference { table, specifiers, columns })
+}
+
+fn closing_bracket(s: &str) -> Option<usize> {
+    let mut chars = s.char_indices(); 
+    chars.next(); // Opening bracket
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' => { chars.next(); }, 
+            ']' => return Some(i), 
+            _ => {}
+        }
+    }
+    None
+}
+
+fn push_structured_item(item: &str, specifiers: &mut Vec<TableSpecifier>, columns: &mut Option<(String, String)>, is_range: bool) -> Option<()> {
+    if item.trim_start().starts_with('#') {
+        let specifier = match item.trim().to_ascii_lowercase().as_str() {
+            "#all" => TableSpecifier::All, 
+            "#data" => TableSpecifier::Data, 
+            "#headers" => TableSpecifier::Headers, 
+            "#totals" => TableSpecifier::Totals, 
+            "#this row" => TableSpecifier::ThisRow, 
+            _ => return None
+        }; 
+        specifiers.push(specifier); 
+    } else {
+        let mut column = String::new(); 
+        let mut chars = item.chars(); 
+        while let Some(c) = chars.next() {
+            match c {
+                '\'' => column.extend(chars.next()), 
+                _ => column.push(c)
+            }
+        }
+        match columns {
+            Some((_, last)) if is_range => *last = column, 
+            None if !is_range => *columns = Some((column.clone(), column)), 
+            _ => return None
+        }
+    }
+    Some(())
+}
+
+// Parentheses are not kept in the expression but belong to its span 
+fn parse_paren_expr(input: Tokens) -> IResult<SpannedExpr> {
+    let (rest, expr) = delimited(lparen_tag, parse_union_expr, rparen_tag).parse(input)?; 
+    Ok((rest, SpannedExpr { spans: Spans { span: consumed(input, rest), ..expr.spans }, ..expr }))
+}
+
+// Commas separate the areas of a union wherever they do not separate arguments: SUM((A1:A3,C1:C3)) 
+fn parse_union_expr(input: Tokens) -> IResult<SpannedExpr> {
+    map(
+        pair(parse_expr, many0(preceded(comma_tag, cut(parse_expr)))), 
+        |(first, rest)| {
+            rest.into_iter().fold(first, |a, b| infix_node(Infix::Union, a, b))
+        }
+    ).parse(input)
+}
 
-fn parse_paren_expr(input: Tokens) -> IResult<Tokens, Expr> {
-    delimited(lparen_tag, parse_expr, rparen_tag).parse(input)
+fn infix_node(infix: Infix, a: SpannedExpr, b: SpannedExpr) -> SpannedExpr {
+    let span = Span { start: a.spans.span.start, end: b.spans.span.end }; 
+    SpannedExpr {
+        expr: Expr::Infix(infix, Box::new(a.expr), Box::new(b.expr)), 
+        spans: Spans { span, children: vec![a.spans, b.spans] }
+    }
 }
 
 
@@ -223,10 +524,13 @@ fn infix_precedence(infix: Infix) -> Precedence {
         Infix::Plus | Infix::Minus => Precedence::PlusMinus, 
         Infix::Multiply | Infix::Divide => Precedence::MultDiv, 
         Infix::Exponent => Precedence::Exponent, 
+        Infix::Union => Precedence::Union, 
+        Infix::Intersect => Precedence::Intersect, 
+        Infix::Range => Precedence::Range, 
     }
 }
 
-fn parse_infix_tags(input: Tokens) -> IResult<Tokens, Infix> {
+fn parse_infix_tags(input: Tokens) -> IResult<Infix> {
     alt((
         map(plus_tag, |_| Infix::Plus), 
         map(minus_tag, |_| Infix::Minus), 
@@ -240,21 +544,33 @@ fn parse_infix_tags(input: Tokens) -> IResult<Tokens, Infix> {
         map(langle_tag, |_| Infix::LessThan), 
         map(exponent_tag, |_| Infix::Exponent), 
         map(ampersand_tag, |_| Infix::Ampersand), 
+        map(colon_tag, |_| Infix::Range), 
+        map(space_tag, |_| Infix::Intersect), 
     )).parse(input)
 }
 
-fn parse_pratt(input: Tokens, precedence: Precedence) -> IResult<Tokens, Expr> {
+fn parse_pratt(input: Tokens, precedence: Precedence) -> IResult<SpannedExpr> {
     let (i1, left) = parse_atom_expr(input)?;
     go_parse_pratt(i1, left, precedence)
 }
 
-fn go_parse_pratt(input: Tokens, lhs: Expr, precedence: Precedence) -> IResult<Tokens, Expr> {
+fn go_parse_pratt(input: Tokens, lhs: SpannedExpr, precedence: Precedence) -> IResult<SpannedExpr> {
     let (i1, t1) = take(1usize).parse(input)?; 
     if t1.tok.is_empty() {
         Ok((i1, lhs))
     } else {
         match t1.tok[0] {
             Token::EOF => Ok((input, lhs)), 
+            // Percent binds tighter than any infix operator, 2^50% is 2^0.5 
+            Token::Percent if precedence < Precedence::Percent => {
+                let (i2, percent) = percent_tag(input)?; 
+                let span = Span { start: lhs.spans.span.start, end: percent.spans[0].end }; 
+                let expr = SpannedExpr {
+                    expr: Expr::Postfix(Postfix::Percent, Box::new(lhs.expr)), 
+                    spans: Spans { span, children: vec![lhs.spans] }
+                }; 
+                go_parse_pratt(i2, expr, precedence)
+            }, 
             _ => {
                 match parse_infix_tags(input) {
                     Ok((_, infix)) => {
@@ -273,7 +589,7 @@ fn go_parse_pratt(input: Tokens, lhs: Expr, precedence: Precedence) -> IResult<T
     }
 }
 
-fn parse_infix(input: Tokens, lhs: Expr) -> IResult<Tokens, Expr> {
+fn parse_infix(input: Tokens, lhs: SpannedExpr) -> IResult<SpannedExpr> {
     let (_i1, t1) = take(1usize).parse(input)?;
     if t1.tok.is_empty() {
         Err(Err::Error(error_position!(input, ErrorKind::Tag)))
@@ -281,50 +597,62 @@ fn parse_infix(input: Tokens, lhs: Expr) -> IResult<Tokens, Expr> {
         let (i2, infix) = parse_infix_tags(input)?;
         let p = infix_precedence(infix.clone()); 
         let (i3, rhs) = parse_pratt(i2, p)?;
-        Ok((i3, Expr::Infix(infix, Box::new(lhs), Box::new(rhs))))
+        Ok((i3, infix_node(infix, lhs, rhs)))
     }
 }
 
-fn parse_infix_expr(input: Tokens) -> IResult<Tokens, Expr> {
+fn parse_infix_expr(input: Tokens) -> IResult<SpannedExpr> {
     parse_pratt(input, Precedence::Lowest)
 }
 
-fn parse_atom_expr(input: Tokens) -> IResult<Tokens, Expr> {
+fn parse_atom_expr(input: Tokens) -> IResult<SpannedExpr> {
     alt((
         parse_prefix_expr,
         parse_paren_expr, 
-        parse_error_expr, 
+        leaf(parse_error_expr), 
         parse_func_expr, 
         parse_array_expr, 
-        parse_reference_expr, 
-        parse_literal_expr, 
+        leaf(parse_structured_reference_expr), 
+        parse_spill_expr, 
+        leaf(parse_reference_expr), 
+        leaf(parse_name_expr), 
+        leaf(parse_literal_expr), 
     )).parse(input)
 }
 
-fn parse_expr(input: Tokens) -> IResult<Tokens, Expr> {
-    alt((
-        parse_infix_expr, 
-        parse_atom_expr,
-    )).parse(input)
+fn parse_expr(input: Tokens) -> IResult<SpannedExpr> {
+    parse_infix_expr(input)
+}
+
+// Defined names may refer to a union without parentheses, Sheet1!$A$1:$B$2,Sheet1!$D$1:$E$2 
+pub fn parse(input: Tokens) -> IResult<Expr> {
+    map(parse_spanned, |x| x.expr).parse(input)
 }
 
-pub fn parse(input: Tokens) -> IResult<Tokens, Expr> {
-    terminated(parse_expr, eof_tag).parse(input)
+pub fn parse_spanned(input: Tokens) -> IResult<SpannedExpr> {
+    terminated(parse_union_expr, eof_tag).parse(input)
 }
 
 pub fn parse_str(s: &str) -> Result<Expr, Error> {
-    let t = Lexer::lex_tokens(s.as_bytes())?; 
-    let tokens = Tokens::new(&t); 
-    match parse(tokens) {
+    parse_str_spanned(s).map(|x| x.expr)
+}
+
+// Tokens left after the expression, such as the ) in SUM(A1)), are reported rather than dropped 
+pub fn parse_str_spanned(s: &str) -> Result<SpannedExpr, Error> {
+    let (t, spans) = Lexer::lex_spanned_tokens(s.as_bytes())?; 
+    let tokens = Tokens::new(&t, &spans); 
+    match parse_spanned(tokens) {
         Ok((_, expr)) => Ok(expr),
-        _ => Err(Error::UnableToParse(s.to_owned()))
+        Err(Err::Error(e) | Err::Failure(e)) => Err(Error::InvalidFormula(e.diagnostic(s))), 
+        Err(Err::Incomplete(_)) => Err(Error::UnableToParse(s.to_owned()))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::parser::parse_str; 
-    use crate::parser::ast::{Expr, Error as ExcelError, Prefix, Infix}; 
+    use crate::parser::{parse_str, parse_str_spanned}; 
+    use crate::parser::ast::{Expr, SpannedExpr, Spans, Error as ExcelError, Prefix, Postfix, Infix, Literal, TableSpecifier}; 
+    use crate::lexer::token::Span; 
     use crate::errors::Error; 
 
     #[test]
@@ -346,14 +674,40 @@ mod tests {
         assert_eq!(parse_str("#REF!")?, Expr::Error(ExcelError::Ref)); 
         assert_eq!(parse_str("#NAME!")?, Expr::Error(ExcelError::Name)); 
         assert_eq!(parse_str("#NUM!")?, Expr::Error(ExcelError::Num)); 
-        assert_eq!(parse_str("#N/A!")?, Expr::Error(ExcelError::NA)); 
+        assert_eq!(parse_str("#N/A")?, Expr::Error(ExcelError::NA)); 
         assert_eq!(parse_str("#GETTING_DATA")?, Expr::Error(ExcelError::GettingData)); 
+        assert_eq!(parse_str("#CALC!")?, Expr::Error(ExcelError::Calc)); 
+        assert_eq!(parse_str("#BUSY!")?, Expr::Error(ExcelError::Busy)); 
+        assert_eq!(parse_str("#NAME?")?, Expr::Error(ExcelError::Name)); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), Error> {
+        let errors = ["#NULL!", "#DIV/0!", "#VALUE!", "#REF!", "#NAME?", "#NUM!", "#N/A", "#GETTING_DATA", "#SPILL!"]; 
+        for s in errors {
+            assert_eq!(parse_str(s)?.to_string(), s); 
+            assert_eq!(parse_str(&parse_str(s)?.to_string())?, parse_str(s)?); 
+        }
+        let formulas = [
+            "IF(A1=1,#N/A,2)", "\"say \"\"hi\"\"\"&\"\"", "'Data Sheet'!A1+Sheet1!B2", "SUM('Jan 2024:Dec 2024'!B2)", 
+            "Jan:Dec!A1", "'Q1 Rates'!Rate*2", "[1]Rates!A1"
+        ]; 
+        for s in formulas {
+            assert_eq!(parse_str(&parse_str(s)?.to_string())?, parse_str(s)?); 
+        }
+        assert_eq!(parse_str("\"say \"\"hi\"\"\"")?, Expr::from("say \"hi\"")); 
         Ok(())
     }
 
     #[test]
     fn test_function() -> Result<(), Error> {
         assert_eq!(parse_str("test(\"a\", \"b\")")?, Expr::Func {name: String::from("test"), args: vec![Expr::from("a"), Expr::from("b")]}); 
+        let empty = Expr::Literal(Literal::Empty); 
+        assert_eq!(parse_str("IF(A1,,2)")?, Expr::Func { name: String::from("IF"), args: vec![Expr::Reference { sheet: None, reference: String::from("A1") }, empty.clone(), Expr::from(2.0)] }); 
+        assert_eq!(parse_str("F(,)")?, Expr::Func { name: String::from("F"), args: vec![empty.clone(), empty] }); 
+        assert_eq!(parse_str("NOW()")?, Expr::Func { name: String::from("NOW"), args: vec![] }); 
+        assert_eq!(parse_str(&parse_str("IF(A1,,2)")?.to_string())?, parse_str("IF(A1,,2)")?); 
         Ok(())
     }
 
@@ -364,6 +718,110 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_name() -> Result<(), Error> {
+        assert_eq!(parse_str("Revenue")?, Expr::Name { sheet: None, name: "Revenue".to_string() }); 
+        assert_eq!(parse_str("Sheet1!Local")?, Expr::Name { sheet: Some("Sheet1".to_string()), name: "Local".to_string() }); 
+        assert_eq!(parse_str("[1]!Growth")?, Expr::Name { sheet: Some("[1]".to_string()), name: "Growth".to_string() }); 
+        assert_eq!(parse_str("Revenue*2")?, Expr::Infix(
+                Infix::Multiply, 
+                Box::new(Expr::Name { sheet: None, name: "Revenue".to_string() }), 
+                Box::new(Expr::from(2.0))
+        )); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_structured_reference() -> Result<(), Error> {
+        let amount = parse_str("Sales[Amount]")?; 
+        assert_eq!(amount, Expr::StructuredReference {
+            table: Some("Sales".to_string()), 
+            specifiers: vec![], 
+            columns: Some(("Amount".to_string(), "Amount".to_string()))
+        }); 
+        assert_eq!(amount.to_string(), "Sales[Amount]"); 
+        let this_row = Expr::StructuredReference {
+            table: None, 
+            specifiers: vec![TableSpecifier::ThisRow], 
+            columns: Some(("Unit Price".to_string(), "Unit Price".to_string()))
+        }; 
+        assert_eq!(parse_str("[@[Unit Price]]")?, this_row); 
+        assert_eq!(parse_str("[[#This Row], [Unit Price]]")?, this_row); 
+        assert_eq!(parse_str(&this_row.to_string())?, this_row); 
+        let headers = parse_str("Sales[[#Headers],[#Data],[Qty]:[Price]]")?; 
+        assert_eq!(headers, Expr::StructuredReference {
+            table: Some("Sales".to_string()), 
+            specifiers: vec![TableSpecifier::Headers, TableSpecifier::Data], 
+            columns: Some(("Qty".to_string(), "Price".to_string()))
+        }); 
+        assert_eq!(parse_str(&headers.to_string())?, headers); 
+        assert_eq!(parse_str("Sales[#Totals]")?, Expr::StructuredReference {
+            table: Some("Sales".to_string()), 
+            specifiers: vec![TableSpecifier::Totals], 
+            columns: None
+        }); 
+        assert_eq!(parse_str("Sales[['#Code]]")?.to_string(), "Sales['#Code]"); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_dynamic_arrays() -> Result<(), Error> {
+        let spill = Expr::Postfix(Postfix::Spill, Box::new(Expr::Reference { sheet: Some("Sheet1".to_string()), reference: "B1".to_string() })); 
+        assert_eq!(parse_str("Sheet1!B1#")?, spill); 
+        assert_eq!(parse_str("ANCHORARRAY(Sheet1!B1)")?, spill); 
+        assert_eq!(spill.to_string(), "Sheet1!B1#"); 
+        let intersection = Expr::Prefix(Prefix::ImplicitIntersection, Box::new(Expr::Reference { sheet: None, reference: "A1:A3".to_string() })); 
+        assert_eq!(parse_str("@A1:A3")?, intersection); 
+        assert_eq!(parse_str("SINGLE(A1:A3)")?, intersection); 
+        assert_eq!(intersection.to_string(), "@A1:A3"); 
+        assert_eq!(parse_str("SUM(B1#)*2")?.to_string(), "(SUM(B1#)*2)"); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_percent() -> Result<(), Error> {
+        let a1 = || Box::new(Expr::Reference { sheet: None, reference: "A1".to_string() }); 
+        assert_eq!(parse_str("A1%")?, Expr::Postfix(Postfix::Percent, a1())); 
+        assert_eq!(parse_str("A1*15%")?, Expr::Infix(
+                Infix::Multiply, 
+                a1(), 
+                Box::new(Expr::Postfix(Postfix::Percent, Box::new(Expr::from(15.0))))
+        )); 
+        assert_eq!(parse_str("2^50%")?.to_string(), "(2^50%)"); 
+        assert_eq!(parse_str("A1%^2")?.to_string(), "(A1%^2)"); 
+        assert_eq!(parse_str("-A1%")?, Expr::Postfix(Postfix::Percent, Box::new(Expr::Prefix(Prefix::Minus, a1())))); 
+        assert_eq!(parse_str("(A1+1)%%")?.to_string(), "(A1+1)%%"); 
+        let negated = Expr::Prefix(Prefix::Minus, Box::new(Expr::Postfix(Postfix::Percent, a1()))); 
+        assert_eq!(negated.to_string(), "-(A1%)"); 
+        assert_eq!(parse_str(&negated.to_string())?, negated); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_reference_operators() -> Result<(), Error> {
+        let reference = |r: &str| Box::new(Expr::Reference { sheet: None, reference: r.to_string() }); 
+        let range = parse_str("A1:INDEX(B:B, 5)")?; 
+        assert_eq!(range, Expr::Infix(
+                Infix::Range, 
+                reference("A1"), 
+                Box::new(Expr::Func { name: "INDEX".to_string(), args: vec![*reference("B:B"), Expr::from(5.0)] })
+        )); 
+        assert_eq!(range.to_string(), "(A1:INDEX(B:B, 5))"); 
+        let intersection = parse_str("B:B 3:3")?; 
+        assert_eq!(intersection, Expr::Infix(Infix::Intersect, reference("B:B"), reference("3:3"))); 
+        assert_eq!(parse_str(&intersection.to_string())?, intersection); 
+        let union = parse_str("SUM((A1:A3,C1:C3))")?; 
+        assert_eq!(union, Expr::Func { 
+            name: "SUM".to_string(), 
+            args: vec![Expr::Infix(Infix::Union, reference("A1:A3"), reference("C1:C3"))] 
+        }); 
+        assert_eq!(parse_str(&union.to_string())?, union); 
+        assert_eq!(parse_str("SUM(A1:A3,C1:C3)")?.to_string(), "SUM(A1:A3, C1:C3)"); 
+        assert_eq!(parse_str("Sheet1!$A$1,Sheet1!$B$2")?.to_string(), "(Sheet1!$A$1,Sheet1!$B$2)"); 
+        assert_eq!(parse_str("A1:B2 B2:C3*2")?.to_string(), "((A1:B2 B2:C3)*2)"); 
+        Ok(())
+    }
+
     #[test]
     fn test_array() -> Result<(), Error> {
         assert_eq!(parse_str("{1, 2, 3, 4}")?, Expr::Array(vec![Expr::from(1.0), Expr::from(2.0), Expr::from(3.0), Expr::from(4.0)])); 
@@ -430,9 +888,64 @@ mod tests {
     #[test]
     fn test_reference_formula() -> Result<(), Error> {
         assert_eq!(parse_str("SUM(Sheet1!A1:A10)")?, Expr::Func { name: "SUM".to_string(), args: vec![Expr::Reference { sheet: Some("Sheet1".to_string()), reference: "A1:A10".to_string() }] }); 
+        assert_eq!(parse_str("'[Assumptions.xlsx]Rates'!B4*2")?, Expr::Infix(Infix::Multiply, Box::new(Expr::Reference { sheet: Some("[Assumptions.xlsx]Rates".to_string()), reference: "B4".to_string() }), Box::new(Expr::from(2.0)))); 
+        let multisheet = Expr::MultiSheetReference { sheets: ("Jan".to_string(), "Dec".to_string()), reference: "B2".to_string() }; 
+        assert_eq!(parse_str("SUM(Jan:Dec!B2)")?, Expr::Func { name: "SUM".to_string(), args: vec![multisheet.clone()] }); 
+        assert_eq!(multisheet.to_string(), "Jan:Dec!B2"); 
         Ok(())
     }
 
+    #[test]
+    fn test_r1c1() -> Result<(), Error> {
+        let r1c1 = parse_str("SUM(R[-1]C2:R[-1]C[1])+Sheet2!RC[3]")?; 
+        assert_eq!(r1c1.to_string(), "(SUM(R[-1]C2:R[-1]C[1])+Sheet2!RC[3])"); 
+        assert_eq!(r1c1.to_a1(5, 2).to_string(), "(SUM($B4:C4)+Sheet2!E5)"); 
+        assert_eq!(r1c1.to_a1(5, 2).to_r1c1(5, 2), r1c1); 
+        assert_eq!(parse_str("R[-1]C")?.to_a1(1, 1), Expr::Error(ExcelError::Ref)); 
+        // Formulas copied down a column share one R1C1 form, whole columns stay A1 and RC5 is column RC 
+        assert_eq!(parse_str("A1*$B$1")?.to_r1c1(2, 1), parse_str("A2*$B$1")?.to_r1c1(3, 1)); 
+        assert_eq!(parse_str("SUM(B:B)+RC5+$C5")?.to_r1c1(5, 2).to_string(), "((SUM(B:B)+RC[469])+R[0]C3)"); 
+        assert_eq!(parse_str("R[0]C3")?.to_a1(5, 2).to_string(), "$C5"); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_spans() -> Result<(), Error> {
+        let formula = "SUM((A1+2)*B1%, -C1)"; 
+        let expr: SpannedExpr = parse_str_spanned(formula)?; 
+        let text = |e: &Spans| formula[e.span.start..e.span.end].to_string(); 
+        assert_eq!(expr.spans.span, Span { start: 0, end: formula.len() }); 
+        assert_eq!(expr.spans.children.iter().map(text).collect::<Vec<String>>(), vec!["(A1+2)*B1%", "-C1"]); 
+        let product = &expr.spans.children[0]; 
+        assert_eq!(product.children.iter().map(text).collect::<Vec<String>>(), vec!["(A1+2)", "B1%"]); 
+        assert_eq!(text(&product.children[0].children[1]), "2"); 
+        assert_eq!(expr.expr, parse_str(formula)?); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let diagnostic = |formula: &str| match parse_str(formula) {
+            Err(Error::InvalidFormula(d)) | Err(Error::UnableToLex(d)) => d, 
+            other => panic!("{} gave {:?}", formula, other)
+        }; 
+        // Trailing input is an error rather than ignored 
+        let trailing = diagnostic("SUM(A1))"); 
+        assert_eq!((trailing.offset, trailing.expected.clone(), trailing.found.as_str()), (7, vec![String::from("end of formula")], "')'")); 
+        assert_eq!(trailing.snippet(), "SUM(A1))\n       ^"); 
+        assert_eq!(diagnostic("#N/A!").offset, 4); 
+        let missing = diagnostic("IF(A1>1,\"x\""); 
+        assert_eq!((missing.offset, missing.found.as_str()), (11, "end of formula")); 
+        assert!(diagnostic("A1+)").expected.contains(&String::from("a reference"))); 
+        assert_eq!(diagnostic("A1 ~ B1").found, "'~'"); 
+        // Long formulas show the part around the offset 
+        let long = format!("{}+)", vec!["A1"; 100].join("+")); 
+        let snippet = diagnostic(&long).snippet(); 
+        let lines: Vec<&str> = snippet.lines().collect(); 
+        assert!(lines[0].starts_with("...") && lines[0].ends_with("+)")); 
+        assert_eq!(lines[1].len() - 1, lines[0].len() - 1); 
+    }
+
     #[test]
     fn test_floor() -> Result<(), Error> {
         assert_eq!(parse_str("FLOOR(3.7, 1)")?, Expr::Func {
@@ -2,7 +2,7 @@ use std::fmt;
 use std::cmp::Ordering; 
 use std::hash::{Hasher, Hash}; 
 
-use crate::cell::Cell;
+use crate::cell::{Cell, CellIndex};
 
 #[derive(Clone, Copy, Eq)]
 pub struct Reference {
@@ -190,6 +190,98 @@ impl Reference {
         Self::get_cells_from_dim(start_row, start_column, num_rows, num_cols)
     }
 
+    // First row, first column, last row and last column, whole columns and rows have 0 for the missing side
+    fn bounds(&self) -> (usize, usize, usize, usize) {
+        let end_cell: Cell = self.end_cell.unwrap_or(self.start_cell); 
+        (self.row(), self.column(), end_cell.row.index, end_cell.column.index)
+    }
+
+    fn from_bounds((first_row, first_column, last_row, last_column): (usize, usize, usize, usize)) -> Reference {
+        if first_row == last_row && first_column == last_column && first_row > 0 && first_column > 0 {
+            Reference::from((first_row, first_column))
+        } else {
+            Reference::from((first_row, first_column, last_row, last_column))
+        }
+    }
+
+    // A1:B2 with C3 gives A1:C3, the : operator between two references 
+    pub fn span(&self, other: &Reference) -> Reference {
+        let span = |a: (usize, usize), b: (usize, usize)| {
+            if a.0 == 0 || b.0 == 0 { (0, 0) } else { (a.0.min(b.0), a.1.max(b.1)) }
+        }; 
+        let (a, b) = (self.bounds(), other.bounds()); 
+        let rows = span((a.0, a.2), (b.0, b.2)); 
+        let columns = span((a.1, a.3), (b.1, b.3)); 
+        Reference::from_bounds((rows.0, columns.0, rows.1, columns.1))
+    }
+
+    // B:B with 3:3 gives B3, the space operator, None when the references do not overlap 
+    pub fn intersect(&self, other: &Reference) -> Option<Reference> {
+        let overlap = |a: (usize, usize), b: (usize, usize)| {
+            match (a.0, b.0) {
+                (0, _) => Some(b), 
+                (_, 0) => Some(a), 
+                _ => Some((a.0.max(b.0), a.1.min(b.1))).filter(|(first, last)| first <= last)
+            }
+        }; 
+        let (a, b) = (self.bounds(), other.bounds()); 
+        let rows = overlap((a.0, a.2), (b.0, b.2))?; 
+        let columns = overlap((a.1, a.3), (b.1, b.3))?; 
+        Some(Reference::from_bounds((rows.0, columns.0, rows.1, columns.1)))
+    }
+
+    // R1C1 notation seen from the cell at row and column: R[-1]C2, RC[3], R5C, R1C1:R[1]C, whole rows R2 and columns C[1] 
+    pub fn from_r1c1(s: &str, row: usize, column: usize) -> Option<Reference> {
+        let index = |part: R1C1Part, base: usize| {
+            match part {
+                None => Some(CellIndex::from((0, false))), 
+                Some((n, true)) => Some(CellIndex::from((n as usize, true))).filter(|_| n >= 1), 
+                Some((n, false)) => Some(CellIndex::from(((base as i64 + n) as usize, false))).filter(|_| base as i64 + n >= 1)
+            }
+        }; 
+        let mut cells: Vec<Cell> = vec![]; 
+        for part in s.split(':') {
+            let (row_part, column_part) = r1c1_parts(part)?; 
+            cells.push(Cell { row: index(row_part, row)?, column: index(column_part, column)? }); 
+        }
+        match cells.as_slice() {
+            [a] if a.is_hrange() || a.is_vrange() => Some(Reference::from((*a, Some(*a)))), 
+            [a] => Some(Reference::from(*a)), 
+            [a, b] => Some(Reference::from((*a, Some(*b)))), 
+            _ => None
+        }
+    }
+
+    // R1C1 references the parser reads, every cell has both a row and a column part and RC5 is an A1 cell 
+    pub fn is_r1c1(s: &str) -> bool {
+        s.split(':').count() <= 2 && s.split(':').all(|x| {
+            matches!(r1c1_parts(x), Some((Some(_), Some(_)))) && x.strip_prefix("RC").is_none_or(|n| n.parse::<usize>().is_err())
+        })
+    }
+
+    // Absolute rows and columns keep their number, relative ones become offsets from row and column 
+    pub fn to_r1c1(&self, row: usize, column: usize) -> String {
+        let part = |prefix: char, index: &CellIndex, base: usize| {
+            match (index.index, index.anchor) {
+                (0, _) => String::new(), 
+                (i, true) => format!("{}{}", prefix, i), 
+                (i, false) if i == base => prefix.to_string(), 
+                (i, false) => format!("{}[{}]", prefix, i as i64 - base as i64)
+            }
+        }; 
+        let cell = |c: &Cell| {
+            match (part('R', &c.row, row), part('C', &c.column, column)) {
+                // RC5 would read back as an A1 cell 
+                (r, col) if r == "R" && col.len() > 1 && !col.contains('[') => format!("R[0]{}", col), 
+                (r, col) => format!("{}{}", r, col)
+            }
+        }; 
+        match self.end_cell {
+            Some(ref end_cell) => format!("{}:{}", cell(&self.start_cell), cell(end_cell)), 
+            None => cell(&self.start_cell)
+        }
+    }
+
     pub fn offset(&mut self, offset: (i32, i32)) {
         if !self.start_cell.row.anchor && !self.start_cell.is_vrange() {
                 self.start_cell.row.index = (self.row() as i32 + offset.0) as usize;
@@ -211,3 +303,30 @@ impl Reference {
     }
 }
 
+// Row or column number and whether it is absolute, None when the cell leaves it out 
+type R1C1Part = Option<(i64, bool)>; 
+
+// Row and column parts of one R1C1 cell, R[-2]C3 gives (-2, false) and (3, true) 
+fn r1c1_parts(s: &str) -> Option<(R1C1Part, R1C1Part)> {
+    let mut rest: &str = s; 
+    let mut part = |prefix: char| -> Option<R1C1Part> {
+        let Some(after) = rest.strip_prefix(prefix) else { return Some(None) }; 
+        if let Some(bracketed) = after.strip_prefix('[') {
+            let (offset, after) = bracketed.split_once(']')?; 
+            rest = after; 
+            return offset.parse::<i64>().ok().map(|n| Some((n, false))); 
+        }
+        let digits: usize = after.chars().take_while(|c| c.is_ascii_digit()).count(); 
+        rest = &after[digits..]; 
+        match digits {
+            0 => Some(Some((0, false))), 
+            _ => after[..digits].parse::<i64>().ok().map(|n| Some((n, true)))
+        }
+    }; 
+    let row_part = part('R')?; 
+    let column_part = part('C')?; 
+    if !rest.is_empty() || (row_part.is_none() && column_part.is_none()) {
+        return None; 
+    }
+    Some((row_part, column_part))
+}
@@ -0,0 +1,118 @@
+use std::collections::BTreeMap; 
+use std::ops::{Index, IndexMut, Range}; 
+use ndarray::Array2; 
+
+// Sparse cell storage indexed like an Array2 with zero-based [row, column], 
+// only cells that were written take up memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellStore<T> {
+    rows: BTreeMap<usize, BTreeMap<usize, T>>, 
+    default: T, 
+    num_rows: usize, 
+    num_columns: usize
+}
+
+impl<T: Clone> CellStore<T> {
+    pub fn new(default: T) -> CellStore<T> {
+        CellStore { rows: BTreeMap::new(), default, num_rows: 0, num_columns: 0 }
+    }
+
+    // Rows and columns up to the furthest stored cell
+    pub fn dim(&self) -> (usize, usize) {
+        (self.num_rows, self.num_columns)
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.values().map(|x| x.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn get(&self, [row, column]: [usize; 2]) -> Option<&T> {
+        self.rows.get(&row).and_then(|x| x.get(&column))
+    }
+
+    pub fn get_mut(&mut self, [row, column]: [usize; 2]) -> Option<&mut T> {
+        self.rows.get_mut(&row).and_then(|x| x.get_mut(&column))
+    }
+
+    pub fn insert(&mut self, [row, column]: [usize; 2], value: T) {
+        self.extend(row, column); 
+        self.rows.entry(row).or_default().insert(column, value); 
+    }
+
+    fn extend(&mut self, row: usize, column: usize) {
+        self.num_rows = self.num_rows.max(row + 1); 
+        self.num_columns = self.num_columns.max(column + 1); 
+    }
+
+    // Dense copy of a block, cells that were never written are the default
+    pub fn slice(&self, rows: Range<usize>, columns: Range<usize>) -> Array2<T> {
+        self.slice_map(rows, columns, T::clone)
+    }
+
+    // Dense block of what f makes of each cell, without copying the cells themselves
+    pub fn slice_map<U: Clone>(&self, rows: Range<usize>, columns: Range<usize>, f: impl Fn(&T) -> U) -> Array2<U> {
+        let mut output: Array2<U> = Array2::from_elem((rows.len(), columns.len()), f(&self.default)); 
+        for (row, cells) in self.rows.range(rows.clone()) {
+            for (column, value) in cells.range(columns.clone()) {
+                output[[row - rows.start, column - columns.start]] = f(value); 
+            }
+        }
+        output
+    }
+
+    // Stored cells in row then column order
+    pub fn indexed_iter(&self) -> impl Iterator<Item = ((usize, usize), &T)> {
+        self.rows.iter().flat_map(|(row, cells)| {
+            cells.iter().map(move |(column, value)| ((*row, *column), value))
+        })
+    }
+}
+
+impl<T: Clone> Index<[usize; 2]> for CellStore<T> {
+    type Output = T; 
+    fn index(&self, index: [usize; 2]) -> &T {
+        self.get(index).unwrap_or(&self.default)
+    }
+}
+
+impl<T: Clone> IndexMut<[usize; 2]> for CellStore<T> {
+    fn index_mut(&mut self, [row, column]: [usize; 2]) -> &mut T {
+        self.extend(row, column); 
+        let default = self.default.clone(); 
+        self.rows.entry(row).or_default().entry(column).or_insert(default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::CellStore; 
+    use ndarray::arr2; 
+
+    #[test]
+    fn test_sparse_store() {
+        let mut store: CellStore<usize> = CellStore::new(0); 
+        store[[1048575, 16383]] = 1; 
+        store.insert([2, 1], 2); 
+        assert_eq!(store.dim(), (1048576, 16384)); 
+        assert_eq!(store.len(), 2); 
+        assert_eq!(store[[0, 0]], 0); 
+        assert_eq!(store.get([0, 0]), None); 
+        assert_eq!(store[[1048575, 16383]], 1); 
+        assert_eq!(store.indexed_iter().collect::<Vec<_>>(), vec![((2, 1), &2), ((1048575, 16383), &1)]); 
+    }
+
+    #[test]
+    fn test_slice() {
+        let mut store: CellStore<usize> = CellStore::new(0); 
+        store.insert([0, 0], 1); 
+        store.insert([1, 1], 2); 
+        store.insert([5, 5], 3); 
+        assert_eq!(store.slice(0..2, 0..3), arr2(&[[1, 0, 0], [0, 2, 0]])); 
+        assert_eq!(store.slice(1..3, 1..2), arr2(&[[2], [0]])); 
+        assert_eq!(store.slice_map(0..2, 0..2, |x| x * 10), arr2(&[[10, 0], [0, 20]])); 
+    }
+}
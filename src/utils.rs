@@ -1,4 +1,4 @@
-use chrono::{NaiveDate, Duration}; 
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Duration}; 
 
 use crate::parser::{
     parse_str, 
@@ -7,14 +7,76 @@ use crate::parser::{
 use crate::reference::Reference; 
 use crate::errors::Error; 
 
-pub fn excel_to_date(serial: f64)  -> NaiveDate {
-    let start_date = NaiveDate::from_ymd_opt(1899, 12, 30).expect("Invalid date"); 
-    let mut duration = Duration::days(serial as i64); 
-    let frac = (serial - serial.floor()) as i64; 
-    if frac > 0 {
-        duration = duration.checked_add(&Duration::hours(24 * frac)).expect("Could not add two dates together"); 
+const MILLISECONDS_PER_DAY: f64 = 86_400_000.0; 
+
+// Workbooks count serial dates either from 1900 (Windows default) or from 1904 (<workbookPr date1904="1"/>) 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DateSystem {
+    #[default]
+    Excel1900, 
+    Excel1904 
+}
+
+impl DateSystem {
+    pub fn epoch(&self) -> NaiveDateTime {
+        let epoch = match self {
+            // Serial 60 is the nonexistent 1900-02-29, so counting from 1899-12-30 is exact from March 1900 on 
+            DateSystem::Excel1900 => NaiveDate::from_ymd_opt(1899, 12, 30), 
+            DateSystem::Excel1904 => NaiveDate::from_ymd_opt(1904, 1, 1)
+        }; 
+        epoch.expect("Invalid date").and_time(NaiveTime::MIN)
+    }
+}
+
+// Fractional days rounded to the millisecond, the precision Excel keeps for times. None when too long to represent 
+pub fn days_to_duration(days: f64) -> Option<Duration> {
+    let milliseconds = (days * MILLISECONDS_PER_DAY).round(); 
+    match milliseconds.is_finite() {
+        true => Duration::try_milliseconds(milliseconds as i64), 
+        false => None
     }
-    start_date.checked_add_signed(duration).unwrap()
+}
+
+pub fn duration_to_days(duration: Duration) -> f64 {
+    duration.num_milliseconds() as f64 / MILLISECONDS_PER_DAY
+}
+
+// None for serials outside the dates that can be represented, such as 1e10 
+pub fn serial_to_datetime(serial: f64, date_system: DateSystem) -> Option<NaiveDateTime> {
+    date_system.epoch().checked_add_signed(days_to_duration(serial)?)
+}
+
+pub fn datetime_to_serial(datetime: NaiveDateTime, date_system: DateSystem) -> f64 {
+    duration_to_days(datetime.signed_duration_since(date_system.epoch()))
+}
+
+pub fn excel_to_date(serial: f64)  -> Option<NaiveDate> {
+    serial_to_datetime(serial, DateSystem::Excel1900).map(|x| x.date())
+}
+
+// OOXML escapes characters that cannot appear in XML as _xHHHH_ (with _x005F_ for a literal underscore) 
+pub fn decode_ooxml_escapes(s: &str) -> String {
+    let mut output = String::with_capacity(s.len()); 
+    let mut rest = s; 
+    while let Some(idx) = rest.find("_x") {
+        output.push_str(&rest[..idx]); 
+        let escaped = rest.get(idx+2..idx+7)
+            .filter(|x| x.ends_with('_'))
+            .and_then(|x| u32::from_str_radix(&x[..4], 16).ok())
+            .and_then(char::from_u32); 
+        match escaped {
+            Some(c) => {
+                output.push(c); 
+                rest = &rest[idx+7..]; 
+            }, 
+            None => {
+                output.push_str("_x"); 
+                rest = &rest[idx+2..]; 
+            }
+        }
+    }
+    output.push_str(rest); 
+    output
 }
 
 pub fn adjust_formula(
@@ -35,7 +97,7 @@ pub fn adjust_expression(
     expression: &mut Expr
 ) -> Result<(), Error> {
     match *expression {
-        Expr::Reference { sheet: _, ref mut reference } => {
+        Expr::Reference { sheet: _, ref mut reference } | Expr::MultiSheetReference { sheets: _, ref mut reference } => {
             let mut r = Reference::from(reference.to_string());
 			r.offset((row_offset, column_offset));
 			*reference = r.to_string(); 
@@ -44,7 +106,7 @@ pub fn adjust_expression(
             adjust_expression(row_offset, column_offset, a)?; 
             adjust_expression(row_offset, column_offset, b)?; 
         }, 
-        Expr::Prefix(_, ref mut a) => {
+        Expr::Prefix(_, ref mut a) | Expr::Postfix(_, ref mut a) => {
             adjust_expression(row_offset, column_offset, a)?; 
         }, 
         Expr::Func { name: _, ref mut args } => {
@@ -65,7 +127,8 @@ pub fn adjust_expression(
 #[cfg(test)]
 mod tests {
     use crate::reference::Reference; 
-    use crate::utils::adjust_formula; 
+    use crate::utils::{adjust_formula, decode_ooxml_escapes, excel_to_date, serial_to_datetime, datetime_to_serial, DateSystem}; 
+    use chrono::NaiveDate; 
     use crate::errors::Error; 
     
     #[test]
@@ -89,4 +152,34 @@ mod tests {
        assert_eq!(&adjust_formula(base_reference, current_reference, String::from("{A1, A2}"))?, &"{B2, B3}"); 
         Ok(())
     }
+
+    #[test]
+    fn test_decode_ooxml_escapes() {
+        assert_eq!(decode_ooxml_escapes("Tab_x0009_Here"), "Tab\tHere"); 
+        assert_eq!(decode_ooxml_escapes("_x005F_x0041_"), "_x0041_"); 
+        assert_eq!(decode_ooxml_escapes("A_x_B_x00"), "A_x_B_x00"); 
+        assert_eq!(decode_ooxml_escapes("No escapes"), "No escapes"); 
+    }
+
+    #[test]
+    fn test_date_systems() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap(); 
+        assert_eq!(excel_to_date(45366.0), Some(date)); 
+        assert_eq!(serial_to_datetime(45366.0, DateSystem::Excel1900).map(|x| x.date()), Some(date)); 
+        assert_eq!(serial_to_datetime(43904.0, DateSystem::Excel1904).map(|x| x.date()), Some(date)); 
+        assert_eq!(serial_to_datetime(0.0, DateSystem::Excel1904).map(|x| x.date()), NaiveDate::from_ymd_opt(1904, 1, 1)); 
+        for serial in [1e10, -1e10, 1e300, f64::NAN] {
+            assert_eq!(serial_to_datetime(serial, DateSystem::Excel1900), None); 
+        }
+    }
+
+    #[test]
+    fn test_time_fractions() {
+        let datetime = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap().and_hms_opt(18, 30, 15).unwrap(); 
+        let serial = 45366.0 + (18.0 * 3600.0 + 30.0 * 60.0 + 15.0) / 86400.0; 
+        assert_eq!(serial_to_datetime(serial, DateSystem::Excel1900), Some(datetime)); 
+        assert_eq!(datetime_to_serial(datetime, DateSystem::Excel1900), serial); 
+        assert_eq!(datetime_to_serial(datetime, DateSystem::Excel1904), serial - 1462.0); 
+        assert_eq!(excel_to_date(45366.75), NaiveDate::from_ymd_opt(2024, 3, 15)); 
+    }
 }
@@ -1,18 +1,26 @@
 use quick_xml::events::BytesStart;
-use zip::read::{ZipArchive, ZipFile};
+use zip::{read::{ZipArchive, ZipFile}, result::ZipError, write::{FileOptions, ZipWriter}, CompressionMethod};
 use indicatif::ProgressBar; 
 use std::fs::File;
+use std::path::Path; 
 use std::fmt; 
-use std::io::BufReader; 
+use std::io::{BufReader, Cursor, Read, Seek, Write}; 
+use std::collections::{BTreeMap, BTreeSet}; 
+use std::collections::HashMap;
+use std::sync::Mutex; 
+use std::thread;  
 use quick_xml::{
+    NsReader, 
     Reader, 
+    Writer, 
     events::{
-        Event, BytesText, 
+        Event, BytesText, BytesEnd, 
         attributes::Attribute
     }, 
-    name::QName
+    name::{Namespace, QName, ResolveResult}
 };
-use ndarray::{Array2, Array, s, Axis, ArrayView}; 
+use ndarray::Array2; 
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime}; 
 use crate::{
     evaluate::{
         value::Value, 
@@ -21,33 +29,56 @@ use crate::{
     }, 
     utils::adjust_formula, 
     dependency::{CellId, DependencyTree}, 
-    utils::excel_to_date, 
+    utils::{decode_ooxml_escapes, DateSystem}, 
     reference::Reference,
     parser::{
         parse_str, 
-        ast::Expr
+        ast::{Expr, Error as ExcelError, Infix, TableSpecifier}
     }, 
     cell::Cell, 
+    store::CellStore, 
+    format::{builtin_format_code, classify, format_value, FormatKind}, 
+    ods, 
+    csv, 
+    xlsb, 
     errors::Error
 }; 
 
-pub type ZipType = ZipArchive<File>; 
+pub trait ReadSeek: Read + Seek + Send {}
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+pub type ZipType = ZipArchive<Box<dyn ReadSeek>>; 
+
+// Transitional and ISO Strict namespaces of SpreadsheetML parts 
+const SPREADSHEETML_NAMESPACES: [&[u8]; 2] = [
+    b"http://schemas.openxmlformats.org/spreadsheetml/2006/main", 
+    b"http://purl.oclc.org/ooxml/spreadsheetml/main"
+]; 
 
 pub struct Book {
     zip: Option<ZipType>, 
+    path: Option<String>, // Kept so that each loading thread can open its own reader 
     pub sheets: Vec<Sheet>, 
     shared_strings: Vec<SharedString>, 
     styles: Vec<Style>, 
     formulas: Vec<(CellId, String)>, // CellId, Formula Text
+    pub defined_names: Vec<DefinedName>, 
+    pub tables: Vec<Table>, 
+    pub array_formulas: Vec<CellId>, // Anchor cell with the dimensions of the block
+    array_formula_cells: HashMap<(usize, usize, usize), usize>, // Sheet, row, column of every cell in a block to its array_formulas index
+    pub spills: Vec<Spill>, 
+    pub external_links: Vec<ExternalLink>, // Referenced as [1], [2], ... in formulas
+    pub date_system: DateSystem, 
     pub current_sheet: usize, 
+    pub current_cell: (usize, usize), // Row, column of the cell being calculated
     pub dependencies: DependencyTree, 
     // pub cells: HashMap<Sheet, Array2<Value>>
 }
 
+// Panics when the file can't be opened, use Book::open to handle the error 
 impl From<String> for Book {
     fn from(s: String) -> Self {
-        let zip = Self::zip_from_path(&s); 
-        Book { zip: Some(zip), sheets: vec![], shared_strings: vec![], styles: vec![], current_sheet: 0, dependencies: DependencyTree::new(), formulas: vec![] }
+        Book::open(&s).unwrap_or_else(|e| panic!("Unable to open {}: {}", s, e))
     }
 }
 
@@ -65,46 +96,320 @@ impl Default for Book {
 
 impl Book {
     pub fn new() -> Book {
-        Book { zip: None, sheets: vec![], shared_strings: vec![], styles: vec![], current_sheet: 0, dependencies: DependencyTree::new(), formulas: vec![] }
+        Book { zip: None, path: None, sheets: vec![], shared_strings: vec![], styles: vec![], current_sheet: 0, current_cell: (1, 1), dependencies: DependencyTree::new(), formulas: vec![], defined_names: vec![], tables: vec![], array_formulas: vec![], array_formula_cells: HashMap::new(), spills: vec![], external_links: vec![], date_system: DateSystem::default() }
+    }
+
+    pub fn open(path: &str) -> Result<Book, Error> {
+        Ok(Book { zip: Some(Book::zip_from_path(path)?), path: Some(path.to_string()), ..Book::new() })
+    }
+
+    pub fn from_reader<R: Read + Seek + Send + 'static>(reader: R) -> Result<Book, Error> {
+        Ok(Book { zip: Some(Book::zip_from_reader(reader)?), ..Book::new() })
+    }
+
+    // A book with a single sheet read from a CSV or TSV file and named after it 
+    pub fn from_csv(path: &str) -> Result<Book, Error> {
+        let mut book = Book::new(); 
+        let name: String = Path::new(path).file_stem().map_or(String::from("Sheet1"), |x| x.to_string_lossy().to_string()); 
+        book.import_csv(path, &name)?; 
+        Ok(book)
+    }
+
+    pub fn import_csv(&mut self, path: &str, sheet_name: &str) -> Result<usize, Error> {
+        let text = std::fs::read_to_string(path)?; 
+        self.import_delimited(&text, csv::delimiter_for(path), sheet_name)
+    }
+
+    // Creates the sheet or replaces all of its cells, returning its index. 
+    // Formulas on other sheets keep their references and recalculate against the imported values 
+    pub fn import_delimited(&mut self, text: &str, delimiter: char, sheet_name: &str) -> Result<usize, Error> {
+        let sheet_idx: usize = match self.sheets.iter().position(|x| x.name == sheet_name) {
+            Some(idx) => idx, 
+            None => {
+                self.sheets.push(Sheet::from((sheet_name, self.sheets.len()))); 
+                self.sheets.len() - 1
+            }
+        }; 
+        self.formulas.retain(|(cell_id, _)| cell_id.sheet != sheet_idx); 
+        self.array_formulas.retain(|cell_id| cell_id.sheet != sheet_idx); 
+        self.array_formula_cells.clear(); 
+        self.index_array_formulas(0); 
+        self.spills.retain(|spill| spill.anchor.sheet != sheet_idx); 
+        let sheet = self.get_mut_sheet_by_idx(sheet_idx); 
+        sheet.values = CellStore::new(SheetValue::new()); 
+        sheet.max_rows = 0; 
+        sheet.max_columns = 0; 
+        sheet.declared = None; 
+        for (row, record) in csv::read_records(text, delimiter).into_iter().enumerate() {
+            for (column, field) in record.iter().enumerate() {
+                let value = csv::infer_value(field); 
+                if value != Value::Empty {
+                    sheet.resize(row + 1, column + 1); 
+                    sheet.values[[row, column]] = SheetValue { value: value.clone(), calculated: value, dirty: false, cached: None }; 
+                }
+            }
+        }
+        self.dependencies = DependencyTree::new(); 
+        self.load_dependencies()?; 
+        Ok(sheet_idx)
     }
 
     pub fn load(&mut self, progress: bool) -> Result<(), Error> {
-        self.load_sheet_names()?; 
-        self.load_shared_strings()?; 
-        self.load_styles()?; 
-        self.load_sheets(progress)?; 
+        self.load_with(progress, false)
+    }
+
+    // Same as load with the worksheets read on several threads 
+    pub fn load_parallel(&mut self, progress: bool) -> Result<(), Error> {
+        self.load_with(progress, true)
+    }
+
+    fn load_with(&mut self, progress: bool, parallel: bool) -> Result<(), Error> {
+        if self.is_ods()? {
+            self.load_ods()?; 
+        } else if self.is_xlsb()? {
+            self.load_xlsb()?; 
+        } else {
+            self.load_sheet_names()?; 
+            self.load_tables()?; 
+            self.load_shared_strings()?; 
+            self.load_styles()?; 
+            match parallel {
+                true => self.load_sheets_parallel(progress)?, 
+                false => self.load_sheets(progress)?
+            }
+        }
         self.load_dependencies()?; 
         Ok(())
     }
 
-    pub fn load_dependencies(&mut self) -> Result<(), Error> {
-        for (cell_id, formula_text) in self.formulas.iter() {
-            self.dependencies.add_formula(cell_id.clone(), &formula_text, &self.sheets)?; 
+    pub fn is_ods(&mut self) -> Result<bool, Error> {
+        Ok(Book::read_part(&mut self.zip, "mimetype")?.is_some_and(|m| m.trim_ascii() == ods::MIME_TYPE.as_bytes()))
+    }
+
+    // OpenDocument spreadsheets keep every table in content.xml with formulas in OpenFormula syntax 
+    pub fn load_ods(&mut self) -> Result<(), Error> {
+        let content = match Book::open_part(&mut self.zip, "content.xml")? {
+            Some(f) => ods::read_content(BufReader::new(f))?, 
+            None => return Err(Error::MissingPart(String::from("content.xml")))
+        }; 
+        self.date_system = content.date_system; 
+        for table in content.tables {
+            let mut sheet = Sheet::from((table.name, self.sheets.len())); 
+            let mut loaded = LoadedSheet::default(); 
+            for cell in table.cells {
+                let (value, cached): (Value, Option<Value>) = match &cell.formula {
+                    Some(f) => (Value::Formula(ods::translate_formula(f)?), Some(cell.value).filter(|x| !x.is_empty())), 
+                    None => (cell.value, None)
+                }; 
+                let dimensions: (usize, usize) = match cell.matrix {
+                    Some((num_rows, num_cols)) if value.is_formula() => {
+                        loaded.array_formulas.push(CellId::from((sheet.idx, cell.row, cell.column, num_rows, num_cols, true))); 
+                        (num_rows, num_cols)
+                    }, 
+                    _ => (1, 1)
+                }; 
+                loaded.insert(&mut sheet, cell.row, cell.column, value, dimensions); 
+                sheet.values[[cell.row-1, cell.column-1]].cached = cached; 
+            }
+            loaded.expand_blocks(&mut sheet); 
+            self.sheets.push(sheet); 
+            self.append_loaded(loaded); 
+        }
+        for name in content.names {
+            self.defined_names.push(DefinedName { name: name.name, sheet: name.sheet, formula: name.formula }); 
+        }
+        Ok(())
+    }
+
+    pub fn is_xlsb(&mut self) -> Result<bool, Error> {
+        Ok(Book::open_part(&mut self.zip, "xl/workbook.bin")?.is_some())
+    }
+
+    // Binary workbooks store the same parts as BIFF12 records, formulas are decoded from their tokens into A1 text 
+    pub fn load_xlsb(&mut self) -> Result<(), Error> {
+        let relationships: Vec<Relationship> = self.load_relationships("xl/workbook.bin")?; 
+        let workbook = match Book::open_part(&mut self.zip, "xl/workbook.bin")? {
+            Some(f) => xlsb::read_workbook(BufReader::new(f))?, 
+            None => return Err(Error::MissingPart(String::from("xl/workbook.bin")))
+        }; 
+        self.date_system = workbook.date_system; 
+        let strings: Vec<String> = match Book::open_part(&mut self.zip, "xl/sharedStrings.bin")? {
+            Some(f) => xlsb::read_shared_strings(BufReader::new(f))?, 
+            None => vec![]
+        }; 
+        self.shared_strings.extend(strings.iter().cloned().map(SharedString)); 
+        if let Some(f) = Book::open_part(&mut self.zip, "xl/styles.bin")? {
+            let (cell_formats, number_formats) = xlsb::read_styles(BufReader::new(f))?; 
+            for number_format_id in cell_formats {
+                let format_code = number_formats.get(&number_format_id).cloned()
+                    .or_else(|| builtin_format_code(number_format_id).map(String::from))
+                    .unwrap_or_else(|| String::from("General")); 
+                self.styles.push(Style { number_format_id, apply_number_format: true, format_code }); 
+            }
+        }
+        // Chartsheets, dialog sheets and macro sheets have no cells to load, they still count towards 
+        // the sheet positions names are scoped with 
+        let mut worksheets: Vec<(String, String)> = vec![]; // Name, part 
+        let mut sheet_indices: Vec<Option<usize>> = vec![]; 
+        for (position, (name, rel_id)) in workbook.sheets.iter().enumerate() {
+            let part: Option<String> = match relationships.iter().find(|r| &r.id == rel_id) {
+                Some(r) if r.kind() == "worksheet" => Some(r.target.clone()), 
+                Some(_) => None, 
+                None => Some(format!("xl/worksheets/sheet{}.bin", position + 1))
+            }; 
+            match part {
+                Some(part) => {
+                    sheet_indices.push(Some(worksheets.len())); 
+                    worksheets.push((name.clone(), part)); 
+                }, 
+                None => sheet_indices.push(None)
+            }
+        }
+        // Formulas on any sheet may name a table, so every table is read before the cells 
+        let mut tables: Vec<xlsb::XlsbTable> = vec![]; 
+        for (sheet_idx, (_, part)) in worksheets.iter().enumerate() {
+            for relationship in self.load_relationships(part)?.iter().filter(|r| r.kind() == "table") {
+                if let Some(f) = Book::open_part(&mut self.zip, &relationship.target)? {
+                    let table = xlsb::read_table(BufReader::new(f))?; 
+                    self.tables.push(Table {
+                        name: table.name.clone(), sheet: sheet_idx, 
+                        reference: Reference::from((table.first_row, table.first_column, table.last_row, table.last_column)).to_string(), 
+                        header_rows: table.header_rows, totals_rows: table.totals_rows, columns: table.columns.clone()
+                    }); 
+                    tables.push(table); 
+                }
+            }
+        }
+        let context = workbook.context(&tables); 
+        for (sheet_idx, (name, part)) in worksheets.into_iter().enumerate() {
+            let records = match Book::open_part(&mut self.zip, &part)? {
+                Some(f) => xlsb::read_sheet(BufReader::new(f))?, 
+                None => xlsb::XlsbSheet::default()
+            }; 
+            let mut sheet = Sheet::from((name, sheet_idx)); 
+            sheet.part = Some(part); 
+            sheet.declared = records.dimension; 
+            let mut loaded = LoadedSheet::default(); 
+            let shared_formulas = xlsb::BlockIndex::new(&records.shared_formulas); 
+            let array_formulas = xlsb::BlockIndex::new(&records.array_formulas); 
+            for cell in records.cells.iter() {
+                let mut dimensions: (usize, usize) = (1, 1); 
+                let is_date = self.styles.get(cell.style).is_some_and(|style| style.apply_number_format && style.kind().is_date()); 
+                let cached: Value = xlsb::cell_value(&cell.value, &strings, is_date, self.date_system); 
+                // Formulas that do not decode keep the value Excel saved for them 
+                let decode = |rgce: &[u8], rgcb: &[u8]| xlsb::formula_value(rgce, rgcb, cell.row, cell.column, &context).ok(); 
+                let value: Value = match &cell.formula {
+                    Some((rgce, _)) if xlsb::is_block_member(rgce) => {
+                        // Array formulas are kept on their anchor and copied over the block afterwards 
+                        let shared = shared_formulas.find(rgce, cell.row, cell.column); 
+                        let array = array_formulas.find(rgce, cell.row, cell.column); 
+                        match (shared, array) {
+                            (_, Some(b)) if b.first_row == cell.row && b.first_column == cell.column => {
+                                match decode(&b.rgce, &b.rgcb) {
+                                    Some(formula) => {
+                                        dimensions = (b.last_row - b.first_row + 1, b.last_column - b.first_column + 1); 
+                                        loaded.array_formulas.push(CellId::from((sheet_idx, cell.row, cell.column, dimensions.0, dimensions.1, true))); 
+                                        formula
+                                    }, 
+                                    None => cached.clone()
+                                }
+                            }, 
+                            (_, Some(_)) => {
+                                loaded.insert(&mut sheet, cell.row, cell.column, cached, dimensions); 
+                                continue
+                            }, 
+                            (Some(b), None) => decode(&b.rgce, &b.rgcb).unwrap_or_else(|| cached.clone()), 
+                            (None, None) => cached.clone()
+                        }
+                    }, 
+                    Some((rgce, rgcb)) => decode(rgce, rgcb).unwrap_or_else(|| cached.clone()), 
+                    None => cached.clone()
+                }; 
+                let is_formula: bool = value.is_formula(); 
+                loaded.insert(&mut sheet, cell.row, cell.column, value, dimensions); 
+                if is_formula {
+                    sheet.values[[cell.row-1, cell.column-1]].cached = Some(cached).filter(|x| !x.is_empty()); 
+                }
+            }
+            loaded.expand_blocks(&mut sheet); 
+            self.sheets.push(sheet); 
+            self.append_loaded(loaded); 
+        }
+        // Hidden entries without a formula name the newer functions used in formulas 
+        for name in workbook.names.iter().filter(|n| !n.rgce.is_empty()) {
+            // Names that do not decode are left out and evaluate to #NAME? 
+            if let Ok(expr) = xlsb::decode_formula(&name.rgce, &name.rgcb, 1, 1, &context) {
+                let sheet: Option<usize> = name.sheet.and_then(|position| sheet_indices.get(position).copied().flatten()); 
+                self.defined_names.push(DefinedName { name: name.name.clone(), sheet, formula: expr.to_string() }); 
+            }
         }
         Ok(())
     }
 
+    pub fn load_dependencies(&mut self) -> Result<(), Error> {
+        // The tree is taken out so that it can be built against the rest of the book 
+        let mut dependencies = std::mem::take(&mut self.dependencies); 
+        let mut converted: Vec<(usize, String)> = vec![]; 
+        let result = self.formulas.iter().enumerate().try_for_each(|(idx, (cell_id, formula_text))| {
+            let mut chars = formula_text.chars(); // Remove = at beginning
+            chars.next(); 
+            let expression: Expr = parse_str(chars.as_str())?; 
+            // R1C1 formulas are kept as A1 from here on so that calculating does not convert them again 
+            let a1: Expr = expression.to_a1(cell_id.row, cell_id.column); 
+            if a1 != expression {
+                converted.push((idx, format!("={}", a1))); 
+            }
+            dependencies.add_parsed_formula(*cell_id, a1, self)
+        }); 
+        self.dependencies = dependencies; 
+        for (idx, formula_text) in converted {
+            let cell_id = self.formulas[idx].0; 
+            self.sheets[cell_id.sheet].values[[cell_id.row-1, cell_id.column-1]].value = Value::Formula(formula_text.clone()); 
+            self.formulas[idx].1 = formula_text; 
+        }
+        result
+    }
+
     pub fn load_shared_strings(&mut self) -> Result<(), Error> {
         let mut buf = Vec::new(); 
-        if let Ok(f) = self.zip.as_mut().unwrap().by_name("xl/sharedStrings.xml") {
-            let mut reader: Reader<BufReader<ZipFile>> = Reader::<BufReader<ZipFile>>::from_reader(BufReader::new(f)); 
-            let mut is_shared_string: bool = false; 
+        if let Some(f) = Book::open_part(&mut self.zip, "xl/sharedStrings.xml")? {
+            let mut reader: NsReader<BufReader<ZipFile>> = NsReader::from_reader(BufReader::new(f)); 
+            // Every <si> is one entry, whether it is empty, plain text or several rich text runs 
+            let mut shared_string: Option<String> = None; 
+            let mut is_text: bool = false; 
+            let mut is_phonetic: bool = false; 
             loop {
                 match reader.read_event_into(&mut buf) {
-                    Ok(Event::Start(ref e)) if e.name() == QName(b"t") => {
-                        is_shared_string = true;
+                    Ok(Event::Start(ref e)) => {
+                        match Book::element_name(&reader, e.name()).unwrap_or_default() {
+                            b"si" => { shared_string = Some(String::new()); }, 
+                            b"t" => { is_text = true; }, 
+                            b"rPh" => { is_phonetic = true; }, 
+                            _ => {}
+                        }
                     }, 
-                    Ok(Event::Text(ref e)) => {
-                        if is_shared_string {
-                            let decoded_text: String = Self::decode_text_event(&reader, e);
-                            if !decoded_text.is_empty() {
-                                self.shared_strings.push(
-                                    SharedString(Self::decode_text_event(&reader, e))
-                                )
-                            }
+                    Ok(Event::End(ref e)) => {
+                        match Book::element_name(&reader, e.name()).unwrap_or_default() {
+                            b"si" => {
+                                if let Some(s) = shared_string.take() {
+                                    self.shared_strings.push(SharedString(decode_ooxml_escapes(&s))); 
+                                }
+                            }, 
+                            b"t" => { is_text = false; }, 
+                            b"rPh" => { is_phonetic = false; }, 
+                            _ => {}
+                        }
+                    }, 
+                    Ok(Event::Empty(ref e)) if Book::is_element(&reader, e.name(), b"si") => {
+                        self.shared_strings.push(SharedString(String::new())); 
+                    }, 
+                    Ok(Event::Text(ref e)) if is_text && !is_phonetic => {
+                        if let Some(s) = shared_string.as_mut() {
+                            s.push_str(&Self::decode_text_event(&reader, e)?); 
                         }
                     }, 
                     Ok(Event::Eof) => break, 
+                    Err(e) => return Err(Error::from(e)), 
                     _ => {}
                 }
                 buf.clear(); 
@@ -115,298 +420,896 @@ impl Book {
 
     pub fn load_styles(&mut self) -> Result<(), Error> {
         let mut buf = Vec::new();
-        if let Ok(f) = self.zip.as_mut().unwrap().by_name("xl/styles.xml") {
-            let mut reader: Reader<BufReader<ZipFile>> = Reader::<BufReader<ZipFile>>::from_reader(BufReader::new(f)); 
+        if let Some(f) = Book::open_part(&mut self.zip, "xl/styles.xml")? {
+            let mut reader: NsReader<BufReader<ZipFile>> = NsReader::from_reader(BufReader::new(f)); 
             let mut is_cell_xfs: bool = false;
+            let mut number_formats: HashMap<usize, String> = HashMap::new(); 
             loop {
                 match reader.read_event_into(&mut buf) {
-                    Ok(Event::Start(ref e)) if e.name() == QName(b"cellXfs") || e.name() == QName(b"xf") => {
-                        match e.name() {
-                            QName(b"cellXfs") => { is_cell_xfs = true; },
-                            QName(b"xf") => {
-                                if is_cell_xfs {
-                                    self.styles.push(Book::decode_style(&reader, e)); 
-                                }
-                            },
-                            _ => {}
+                    Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if Book::is_element(&reader, e.name(), b"numFmt") => {
+                        let mut number_format_id: usize = 0; 
+                        let mut format_code = String::new(); 
+                        for a in e.attributes() {
+                            let a = a?; 
+                            match a.key.local_name().as_ref() {
+                                b"numFmtId" => { number_format_id = Book::decode_attribute_usize(&reader, a)?; }, 
+                                b"formatCode" => { format_code = Book::decode_attribute_string(&reader, a)?; }, 
+                                _ => {}
+                            }
                         }
+                        number_formats.insert(number_format_id, format_code); 
                     }, 
-                    Ok(Event::Empty(ref e)) if e.name() == QName(b"xf") => {
+                    Ok(Event::Start(ref e)) if Book::is_element(&reader, e.name(), b"cellXfs") => { is_cell_xfs = true; }, 
+                    Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if Book::is_element(&reader, e.name(), b"xf") => {
                         if is_cell_xfs {
-                            self.styles.push(Book::decode_style(&reader, e)); 
+                            self.styles.push(Book::decode_style(&reader, e)?); 
                         }
                     }, 
-                    Ok(Event::End(ref e)) if e.name() == QName(b"cellXfs") => { is_cell_xfs = false; }, 
+                    Ok(Event::End(ref e)) if Book::is_element(&reader, e.name(), b"cellXfs") => { is_cell_xfs = false; }, 
                     Ok(Event::Eof) => break, 
+                    Err(e) => return Err(Error::from(e)), 
                     _ => {}
                 }
                 buf.clear(); 
             }
+            // Custom formats override the built-in codes for the same id
+            for style in self.styles.iter_mut() {
+                style.format_code = number_formats.get(&style.number_format_id).cloned()
+                    .or_else(|| builtin_format_code(style.number_format_id).map(String::from))
+                    .unwrap_or_else(|| String::from("General")); 
+            }
         }
         Ok(())
     }
 
-    pub fn load_sheet_names(&mut self) -> Result<(), Error> {
+    pub fn load_relationships(&mut self, part: &str) -> Result<Vec<Relationship>, Error> {
         let mut buf = Vec::new();
-        if let Ok(f) = self.zip.as_mut().unwrap().by_name("xl/workbook.xml") {
+        let mut relationships: Vec<Relationship> = vec![]; 
+        if let Some(f) = Book::open_part(&mut self.zip, &Relationship::rels_path(part))? {
             let mut reader: Reader<BufReader<ZipFile>> = Reader::<BufReader<ZipFile>>::from_reader(BufReader::new(f)); 
-            let mut sheet_idx: usize = 0; 
             loop {
                 match reader.read_event_into(&mut buf) {
-                    Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"sheet" => {
+                    Ok(Event::Empty(ref e)) | Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"Relationship" => {
+                        let mut id = String::new(); 
+                        let mut rel_type = String::new(); 
+                        let mut target = String::new(); 
+                        let mut is_external: bool = false; 
                         for a in e.attributes() {
-                            let a = a.unwrap();
-                            if let QName(b"name") = a.key {
-                                let name = String::from_utf8(a.value.to_vec()).unwrap();
-                                self.sheets.push(Sheet::from((name, sheet_idx))); 
-                                sheet_idx += 1; 
+                            let a = a?; 
+                            match a.key.local_name().as_ref() {
+                                b"Id" => { id = Book::decode_attribute_string(&reader, a)?; }, 
+                                b"Type" => { rel_type = Book::decode_attribute_string(&reader, a)?; }, 
+                                b"Target" => { target = Book::decode_attribute_string(&reader, a)?; }, 
+                                b"TargetMode" => { is_external = a.value.as_ref() == b"External"; }, 
+                                _ => {}
                             }
                         }
+                        if !is_external {
+                            target = Relationship::resolve_target(part, &target); 
+                        }
+                        relationships.push(Relationship { id, rel_type, target }); 
                     }, 
                     Ok(Event::Eof) => break, 
+                    Err(e) => return Err(Error::from(e)), 
                     _ => {}
                 }
                 buf.clear(); 
             }
         }
-        Ok(())
+        Ok(relationships)
     }
 
-    pub fn load_sheets(&mut self, progress: bool) -> Result<(), Error> { 
-        for sheet_id in 0..self.sheets.len() {
-            self.load_sheet(sheet_id, progress)?; 
+    pub fn load_sheet_names(&mut self) -> Result<(), Error> {
+        let mut buf = Vec::new();
+        let relationships: Vec<Relationship> = self.load_relationships("xl/workbook.xml")?; 
+        let mut external_parts: Vec<Option<String>> = vec![]; 
+        if let Some(f) = Book::open_part(&mut self.zip, "xl/workbook.xml")? {
+            let mut reader: NsReader<BufReader<ZipFile>> = NsReader::from_reader(BufReader::new(f)); 
+            let mut sheet_idx: usize = 0; 
+            let mut sheet_position: usize = 0; 
+            let mut sheet_indices: Vec<Option<usize>> = vec![]; // localSheetId counts every sheet 
+            let mut defined_name: Option<DefinedName> = None; 
+            loop {
+                match reader.read_event_into(&mut buf) {
+                    Ok(Event::Empty(ref e)) | Ok(Event::Start(ref e)) if Book::is_element(&reader, e.name(), b"sheet") => {
+                        let mut name = String::new(); 
+                        let mut rel_id = String::new(); 
+                        for a in e.attributes() {
+                            let a = a?;
+                            match a.key.local_name().as_ref() {
+                                b"name" => { name = Book::decode_attribute_string(&reader, a)?; }, 
+                                b"id" => { rel_id = Book::decode_attribute_string(&reader, a)?; }, 
+                                _ => {}
+                            }
+                        }
+                        sheet_position += 1; 
+                        // Chartsheets, dialog sheets and macro sheets have no cells to load. 
+                        // Without a relationship fall back to the conventional part name. 
+                        let part: Option<String> = match relationships.iter().find(|r| r.id == rel_id) {
+                            Some(r) if r.kind() == "worksheet" => Some(r.target.clone()), 
+                            Some(_) => None, 
+                            None => Some(format!("xl/worksheets/sheet{}.xml", sheet_position))
+                        }; 
+                        if let Some(part) = part {
+                            let mut sheet = Sheet::from((name, sheet_idx)); 
+                            sheet.part = Some(part); 
+                            self.sheets.push(sheet); 
+                            sheet_indices.push(Some(sheet_idx)); 
+                            sheet_idx += 1; 
+                        } else {
+                            sheet_indices.push(None); 
+                        }
+                    }, 
+                    Ok(Event::Empty(ref e)) | Ok(Event::Start(ref e)) if Book::is_element(&reader, e.name(), b"workbookPr") => {
+                        for a in e.attributes() {
+                            let a = a?;
+                            if a.key.local_name().as_ref() == b"date1904" {
+                                let date1904 = Book::decode_attribute_string(&reader, a)?; 
+                                if date1904 == "1" || date1904 == "true" {
+                                    self.date_system = DateSystem::Excel1904; 
+                                }
+                            }
+                        }
+                    }, 
+                    Ok(Event::Start(ref e)) if Book::is_element(&reader, e.name(), b"definedName") => {
+                        let mut name = DefinedName { name: String::new(), sheet: None, formula: String::new() }; 
+                        for a in e.attributes() {
+                            let a = a?;
+                            match a.key.local_name().as_ref() {
+                                b"name" => { name.name = Book::decode_attribute_string(&reader, a)?; }, 
+                                b"localSheetId" => {
+                                    let position = Book::decode_attribute_usize(&reader, a)?; 
+                                    name.sheet = sheet_indices.get(position).copied().flatten(); 
+                                }, 
+                                _ => {}
+                            }
+                        }
+                        defined_name = Some(name); 
+                    }, 
+                    Ok(Event::Text(ref e)) => {
+                        if let Some(name) = defined_name.as_mut() {
+                            name.formula.push_str(&Book::decode_text_event(&reader, e)?); 
+                        }
+                    }, 
+                    Ok(Event::End(ref e)) if Book::is_element(&reader, e.name(), b"definedName") => {
+                        if let Some(name) = defined_name.take() {
+                            self.defined_names.push(name); 
+                        }
+                    }, 
+                    Ok(Event::Empty(ref e)) if Book::is_element(&reader, e.name(), b"externalReference") => {
+                        for a in e.attributes() {
+                            let a = a?;
+                            if a.key.local_name().as_ref() == b"id" {
+                                let rel_id = Book::decode_attribute_string(&reader, a)?; 
+                                external_parts.push(relationships.iter().find(|r| r.id == rel_id).map(|r| r.target.clone())); 
+                            }
+                        }
+                    }, 
+                    Ok(Event::Eof) => break, 
+                    Err(e) => return Err(Error::from(e)), 
+                    _ => {}
+                }
+                buf.clear(); 
+            }
         }
-        Ok(()) 
+        // Links keep their position even when their part is missing, formulas refer to them by index 
+        for part in external_parts {
+            let link = match part {
+                Some(part) => self.load_external_link(&part)?, 
+                None => ExternalLink::new(String::new())
+            }; 
+            self.external_links.push(link); 
+        }
+        Ok(())
     }
 
-    pub fn load_sheet(&mut self, sheet_idx: usize, progress: bool) -> Result<(), Error> {
+    // Target of an external link with the sheet names, defined names and values cached from it 
+    pub fn load_external_link(&mut self, part: &str) -> Result<ExternalLink, Error> {
         let mut buf = Vec::new();
-        let max_rows = self.get_sheet_by_idx(sheet_idx).max_rows.clone(); 
-        let max_columns = self.get_sheet_by_idx(sheet_idx).max_columns.clone(); 
-        let pb = match progress {
-            true => ProgressBar::new((max_rows * max_columns) as u64), 
-            false => ProgressBar::hidden()
-        }; 
-        if let Ok(f) = self.zip.as_mut().unwrap().by_name(&format!("xl/worksheets/sheet{}.xml", sheet_idx + 1)) {
+        let relationships: Vec<Relationship> = self.load_relationships(part)?; 
+        let mut link = ExternalLink::new(String::new()); 
+        if let Some(f) = Book::open_part(&mut self.zip, part)? {
             let mut reader: Reader<BufReader<ZipFile>> = Reader::<BufReader<ZipFile>>::from_reader(BufReader::new(f)); 
-            let mut flags = SheetFlags::new(); 
+            let cached: &mut Book = &mut link.cached; 
+            let mut sheet_idx: Option<usize> = None; 
+            let mut cell: Option<(String, Vec<u8>)> = None; // Reference and type 
+            let mut is_value: bool = false; 
             loop {
                 match reader.read_event_into(&mut buf) {
-                    Ok(Event::Start(ref e)) if e.name() == QName(b"c") => {
+                    Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"externalBook" => {
                         for a in e.attributes() {
-                            let a = a.unwrap(); 
-                            match a.key {
-                                QName(b"r") => {
-                                    // Cell reference
-                                    flags.reset(); 
-                                    flags.current_cell_reference = String::from_utf8(a.value.to_vec()).unwrap();
-                                }, 
-                                QName(b"t") => {
-                                    // Cell type
-                                    let a_value = String::from_utf8(a.value.to_vec()).unwrap();
-                                    if a_value == *"s" {
-                                        flags.is_string = true; 
-                                    }
-                                },
-                                QName(b"s") => {
-                                    // Cell style / date
-                                    let cell_style_idx: usize = String::from_utf8(a.value.to_vec()).unwrap().parse::<usize>().unwrap(); 
-                                    let style: &Style = self.styles.get(cell_style_idx).expect("Could not find style index");
-                                    if style.number_format_id >= 14 && style.number_format_id <= 22 && style.apply_number_format {
-                                        flags.is_date = true;
-                                    }
-                                }, 
-                                _ => {}
+                            let a = a?;
+                            if a.key.local_name().as_ref() == b"id" {
+                                let rel_id = Book::decode_attribute_string(&reader, a)?; 
+                                if let Some(r) = relationships.iter().find(|r| r.id == rel_id) {
+                                    link.target = r.target.clone(); 
+                                }
                             }
                         }
                     }, 
-                    Ok(Event::Start(ref e)) if e.name() == QName(b"f") => {
-                        // Formula flag
-                        flags.is_formula = true;
+                    Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"sheetName" => {
                         for a in e.attributes() {
-                            let a = a.unwrap();
-                            if a.key.0 == b"ref" {
-                                flags.is_shared_formula = true; 
+                            let a = a?;
+                            if a.key.local_name().as_ref() == b"val" {
+                                let name = Book::decode_attribute_string(&reader, a)?; 
+                                let idx = cached.sheets.len(); 
+                                cached.sheets.push(Sheet::from((name, idx))); 
                             }
                         }
                     }, 
-                    Ok(Event::Empty(ref e)) if e.name() == QName(b"f") => {
-                        // Shared formula
+                    Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"definedName" => {
+                        let mut name = DefinedName { name: String::new(), sheet: None, formula: String::new() }; 
                         for a in e.attributes() {
-                            let a = a.unwrap();
-                            if let QName(b"si") = a.key {
-                                let formula_index: usize = String::from_utf8(a.value.to_vec()).unwrap().parse::<usize>().unwrap(); 
-                                let (start_cell, formula_text): &(Cell, String) = flags.shared_formulas.get(formula_index).unwrap(); 
-                                let base_reference = Reference::from(start_cell.as_tuple()); 
-                                let current_cell = Cell::from(flags.current_cell_reference.clone()); 
-                                let current_reference = Reference::from(current_cell.as_tuple());
-                                let adjusted_formula: Value = Value::Formula(format!("={}", adjust_formula(base_reference, current_reference, formula_text.clone())?)); 
-                                let sheet = self.sheets.get_mut(sheet_idx).unwrap(); 
-                                let (row, column): (usize, usize) = current_cell.as_tuple(); 
-                                sheet.resize(row, column); 
-                                sheet.values[[row-1, column-1]].value = adjusted_formula.clone(); 
-                                let cell_id = CellId::from((sheet_idx, row, column, 1, 1, true)); 
-                                self.formulas.push((cell_id, adjusted_formula.to_string())); 
-                                flags.reset(); 
+                            let a = a?;
+                            match a.key.local_name().as_ref() {
+                                b"name" => { name.name = Book::decode_attribute_string(&reader, a)?; }, 
+                                b"refersTo" => { name.formula = Book::decode_attribute_string(&reader, a)?.trim_start_matches('=').to_string(); }, 
+                                b"sheetId" => { name.sheet = Some(Book::decode_attribute_usize(&reader, a)?); }, 
+                                _ => {}
                             }
                         }
+                        cached.defined_names.push(name); 
                     }, 
-                    Ok(Event::Start(ref e)) if e.name() == QName(b"v") => {
-                        // Value
-                        flags.is_value = true; 
-                    }, 
-                    Ok(Event::Text(ref e)) => {
-                        let cell_text = Book::decode_text_event(&reader, e); 
-                        if !cell_text.is_empty() && !flags.current_cell_reference.is_empty() {
-                            let cell_text = Book::decode_text_event(&reader, e); 
-                            let value: Value; 
-                            if flags.is_formula {
-                                value = Value::Formula(format!("={}", &cell_text.replace("_xlfn.", "").to_owned()));
-                                if flags.is_shared_formula {
-                                    flags.shared_formulas.push(
-                                        (Cell::from(flags.current_cell_reference.clone()), cell_text.clone())
-                                    )
-                                }
-                            } else if flags.is_string {
-                                let shared_string_idx: usize = cell_text.parse::<usize>().unwrap();
-                                let SharedString(s) = self.shared_strings.get(shared_string_idx).unwrap();
-                                value = Value::from(s.clone()); 
-                            } else if flags.is_date {
-                                value = Value::from(excel_to_date(cell_text.parse::<f64>().unwrap())); 
-                            } else if !cell_text.is_empty() {
-                                value = match &*cell_text {
-                                    "TRUE" => Value::Bool(true), 
-                                    "FALSE" => Value::Bool(false), 
-                                    _ => {
-                                        Value::Num(cell_text.parse::<f64>().expect("Unable to parse to number"))
-                                    }
-                                }; 
-                            } else {
-                                value = Value::Empty; 
+                    Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"sheetData" => {
+                        for a in e.attributes() {
+                            let a = a?;
+                            if a.key.local_name().as_ref() == b"sheetId" {
+                                sheet_idx = Some(Book::decode_attribute_usize(&reader, a)?); 
                             }
-                            let cell = Cell::from(flags.current_cell_reference.clone()); 
-                            let (row, column): (usize, usize) = cell.as_tuple(); 
- 
-                            if value.is_formula() {
-                                let cell_id = CellId::from((sheet_idx, row, column, 1, 1, true)); 
-                                self.formulas.push((cell_id, value.to_string())); 
+                        }
+                    }, 
+                    Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"cell" => {
+                        let mut reference = String::new(); 
+                        let mut cell_type: Vec<u8> = b"n".to_vec(); 
+                        for a in e.attributes() {
+                            let a = a?;
+                            match a.key.local_name().as_ref() {
+                                b"r" => { reference = Book::decode_attribute_string(&reader, a)?; }, 
+                                b"t" => { cell_type = a.value.to_vec(); }, 
+                                _ => {}
                             }
-
-                            let sheet = self.sheets.get_mut(sheet_idx).unwrap(); 
-                            sheet.resize(row, column); 
-                            sheet.values[[row-1, column-1]] = SheetValue { value: value.clone(), calculated: value, dirty: false }; 
-                            pb.set_position((row * max_columns + column) as u64); 
-                            flags.reset(); 
+                        }
+                        cell = Some((reference, cell_type)); 
+                    }, 
+                    Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"v" => { is_value = true; }, 
+                    Ok(Event::End(ref e)) if e.local_name().as_ref() == b"v" => { is_value = false; }, 
+                    Ok(Event::Text(ref e)) if is_value => {
+                        let text = Book::decode_text_event(&reader, e)?; 
+                        if let (Some((reference, cell_type)), Some(sheet)) = (&cell, sheet_idx.and_then(|i| cached.sheets.get_mut(i))) {
+                            let invalid = || Error::InvalidValue(reference.clone(), text.clone()); 
+                            let value: Value = match cell_type.as_slice() {
+                                b"str" | b"s" | b"inlineStr" => Value::from(decode_ooxml_escapes(&text)), 
+                                b"b" => Value::Bool(text == "1" || text == "TRUE"), 
+                                b"e" => Value::Error(text.parse::<ExcelError>().map_err(|_| invalid())?), 
+                                _ => Value::Num(text.trim().parse::<f64>().map_err(|_| invalid())?)
+                            }; 
+                            sheet.set_value(Reference::from(reference.as_str()), value); 
                         }
                     }, 
                     Ok(Event::Eof) => break, 
-                    _ => {
-                    } 
+                    Err(e) => return Err(Error::from(e)), 
+                    _ => {}
                 }
+                buf.clear(); 
             }
         }
-        Ok(())
+        Ok(link)
     }
 
-    pub fn zip_from_path(path: &str) -> ZipType {
-        let file: File = File::open(path).expect("Unable to find file"); 
-        zip::ZipArchive::new(file).expect("Unable to create zip") 
+    // Formulas referring to the workbook at target read from book instead of the values cached in the file, 
+    // returns the position of the link 
+    pub fn link_book(&mut self, target: &str, book: Book) -> usize {
+        let file_name = ExternalLink::file_name(target).to_lowercase(); 
+        let idx = match self.external_links.iter().position(|l| ExternalLink::file_name(&l.target).to_lowercase() == file_name) {
+            Some(idx) => idx, 
+            None => {
+                self.external_links.push(ExternalLink::new(target.to_string())); 
+                self.external_links.len() - 1
+            }
+        }; 
+        self.external_links[idx].live = Some(book); 
+        idx
     }
 
-    pub fn decode_text_event(_reader: &Reader<BufReader<ZipFile>>, e: &BytesText) -> String {
-        e.unescape().expect("Failed to unescape text").to_string()
+    // The linked book and sheet name of a sheet such as [1]Rates or [Assumptions.xlsx]Rates 
+    pub fn get_external_book<'a>(&'a self, sheet: &'a str) -> Option<(&'a Book, &'a str)> {
+        let (book, sheet_name) = ExternalLink::split(sheet)?; 
+        let link: &ExternalLink = match book.parse::<usize>() {
+            Ok(n) => self.external_links.get(n.checked_sub(1)?)?, 
+            Err(_) => self.external_links.iter().find(|l| ExternalLink::file_name(&l.target).eq_ignore_ascii_case(book))?
+        }; 
+        Some((link.book(), sheet_name))
     }
 
-    pub fn decode_attribute_usize(_reader: &Reader<BufReader<ZipFile>>, a: Attribute) -> usize {
-        String::from_utf8(a.value.to_vec()).unwrap().parse::<usize>().unwrap()
+    // Defined name of a linked book, with its references pointing back into that book 
+    pub fn get_external_name(&self, sheet: &str, name: &str) -> Option<Expr> {
+        let (book, sheet_name) = self.get_external_book(sheet)?; 
+        let (prefix, _) = ExternalLink::split(sheet)?; 
+        let sheet_idx: Option<usize> = match sheet_name.is_empty() {
+            true => None, 
+            false => Some(book.sheets.iter().position(|s| s.name == sheet_name)?)
+        }; 
+        let defined_name = book.defined_names.iter()
+            .find(|d| d.name.eq_ignore_ascii_case(name) && d.sheet == sheet_idx)
+            .or_else(|| book.defined_names.iter().find(|d| d.name.eq_ignore_ascii_case(name) && d.sheet.is_none()))?; 
+        if book.is_circular_name(name, sheet_idx) {
+            return None; 
+        }
+        let expr: Expr = defined_name.expression().ok()?; 
+        Some(ExternalLink::qualify(expr, prefix, sheet_name))
     }
 
-    pub fn decode_style(reader: &Reader<BufReader<ZipFile>>, e: &BytesStart) -> Style {
-        let mut number_format_id : usize = 0; 
-        let mut apply_number_format: bool = false; 
-        for a in e.attributes() {
-            let a = a.unwrap(); 
-            match a.key.0 {
-                b"numFmtId" => {
-                    number_format_id = Book::decode_attribute_usize(reader, a); 
-                }, 
-                b"applyNumberFormat" => {
-                    apply_number_format = Book::decode_attribute_usize(reader, a) != 0; 
-                }, 
-                _ => {}
+    pub fn load_tables(&mut self) -> Result<(), Error> {
+        for sheet_idx in 0..self.sheets.len() {
+            if let Some(part) = self.get_sheet_by_idx(sheet_idx).part.clone() {
+                for relationship in self.load_relationships(&part)? {
+                    if relationship.kind() == "table" {
+                        self.load_table(sheet_idx, &relationship.target)?; 
+                    }
+                }
             }
         }
-        Style { number_format_id, apply_number_format }
+        Ok(())
     }
 
-    pub fn get_mut_sheet_by_name<'a>(&'a mut self, s: &'a str) -> &'a mut Sheet {
-        let idx = self.sheets.iter().position(|x| x.name == s).unwrap(); 
-        self.get_mut_sheet_by_idx(idx)
+    pub fn load_table(&mut self, sheet_idx: usize, part: &str) -> Result<(), Error> {
+        let mut buf = Vec::new();
+        if let Some(f) = Book::open_part(&mut self.zip, part)? {
+            let mut reader: Reader<BufReader<ZipFile>> = Reader::<BufReader<ZipFile>>::from_reader(BufReader::new(f)); 
+            let mut table = Table { name: String::new(), sheet: sheet_idx, reference: String::new(), header_rows: 1, totals_rows: 0, columns: vec![] }; 
+            loop {
+                match reader.read_event_into(&mut buf) {
+                    Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"table" => {
+                        for a in e.attributes() {
+                            let a = a?; 
+                            match a.key.local_name().as_ref() {
+                                b"displayName" => { table.name = Book::decode_attribute_string(&reader, a)?; }, 
+                                b"ref" => { table.reference = Book::decode_attribute_string(&reader, a)?; }, 
+                                b"headerRowCount" => { table.header_rows = Book::decode_attribute_usize(&reader, a)?; }, 
+                                b"totalsRowCount" => { table.totals_rows = Book::decode_attribute_usize(&reader, a)?; }, 
+                                _ => {}
+                            }
+                        }
+                    }, 
+                    Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"tableColumn" => {
+                        for a in e.attributes() {
+                            let a = a?; 
+                            if a.key.local_name().as_ref() == b"name" {
+                                table.columns.push(Book::decode_attribute_string(&reader, a)?); 
+                            }
+                        }
+                    }, 
+                    Ok(Event::Eof) => break, 
+                    Err(e) => return Err(Error::from(e)), 
+                    _ => {}
+                }
+                buf.clear(); 
+            }
+            self.tables.push(table); 
+        }
+        Ok(())
     }
 
-    pub fn get_mut_sheet_by_idx(&mut self, idx: usize) -> &mut Sheet {
-        self.sheets.get_mut(idx).unwrap()
+    pub fn load_sheets(&mut self, progress: bool) -> Result<(), Error> { 
+        for sheet_id in 0..self.sheets.len() {
+            self.load_sheet(sheet_id, progress)?; 
+        }
+        Ok(()) 
     }
 
-    pub fn get_sheet_by_name(&self, s: String) -> &Sheet {
-        let idx = self.sheets.iter().position(|x| x.name == s.as_str()).unwrap(); 
-        self.get_sheet_by_idx(idx)
+    // Threads take the next unread sheet and open their own reader over the package, 
+    // books that were not opened from a path are read one sheet at a time 
+    pub fn load_sheets_parallel(&mut self, progress: bool) -> Result<(), Error> {
+        let path: String = match &self.path {
+            Some(p) => p.clone(), 
+            None => return self.load_sheets(progress)
+        }; 
+        let num_threads = thread::available_parallelism().map_or(1, |n| n.get()).min(self.sheets.len()).max(1); 
+        let pb = match progress {
+            true => ProgressBar::new(self.sheets.len() as u64), 
+            false => ProgressBar::hidden()
+        }; 
+        let Book { sheets, shared_strings, styles, date_system, .. } = self; 
+        let (shared_strings, styles, date_system) = (&*shared_strings, &*styles, *date_system); 
+        let queue = Mutex::new(sheets.iter_mut().enumerate()); 
+        let results: Vec<Result<Vec<(usize, LoadedSheet)>, Error>> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..num_threads).map(|_| scope.spawn(|| {
+                let mut zip: Option<ZipType> = Some(Book::zip_from_path(&path)?); 
+                let mut output: Vec<(usize, LoadedSheet)> = vec![]; 
+                loop {
+                    let next = queue.lock().unwrap_or_else(|e| e.into_inner()).next(); 
+                    let Some((sheet_idx, sheet)) = next else { break }; 
+                    let part = sheet.part.clone(); 
+                    if let Some(f) = part.map(|p| Book::open_part(&mut zip, &p)).transpose()?.flatten() {
+                        let loaded = SheetLoader::new(sheet_idx, sheet, shared_strings, styles, date_system).read(f, false)?; 
+                        output.push((sheet_idx, loaded)); 
+                    }
+                    pb.inc(1); 
+                }
+                Ok(output)
+            })).collect(); 
+            handles.into_iter().map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e))).collect()
+        }); 
+        pb.finish(); 
+        let mut loaded: Vec<(usize, LoadedSheet)> = vec![]; 
+        for result in results {
+            loaded.extend(result?); 
+        }
+        loaded.sort_by_key(|(sheet_idx, _)| *sheet_idx); 
+        for (_, l) in loaded {
+            self.append_loaded(l); 
+        }
+        Ok(())
     }
 
-    pub fn get_sheet_by_idx(&self, idx: usize) -> &Sheet {
-        self.sheets.get(idx).unwrap()
+    pub fn load_sheet(&mut self, sheet_idx: usize, progress: bool) -> Result<(), Error> {
+        let part: String = match &self.get_sheet_by_idx(sheet_idx).part {
+            Some(p) => p.clone(), 
+            None => return Ok(())
+        }; 
+        let Book { zip, sheets, shared_strings, styles, date_system, .. } = self; 
+        let loaded = match Book::open_part(zip, &part)? {
+            Some(f) => SheetLoader::new(sheet_idx, &mut sheets[sheet_idx], shared_strings, styles, *date_system).read(f, progress)?, 
+            None => return Ok(())
+        }; 
+        self.append_loaded(loaded); 
+        Ok(())
     }
 
-    pub fn resolve_str_ref(&self, s: &str) -> Result<Array2<Value>, Error> {
-        let expr: Expr = parse_str(s)?; 
-        if matches!(expr, Expr::Reference { sheet: _, reference: _}) {
-            self.resolve_ref(expr)
-        } else {
-            panic!("Could not resolve {} to a reference", s); 
-        }
+    // Merged in sheet order so that the book is the same however the sheets were read 
+    fn append_loaded(&mut self, loaded: LoadedSheet) {
+        self.formulas.extend(loaded.formulas); 
+        let start: usize = self.array_formulas.len(); 
+        self.array_formulas.extend(loaded.array_formulas); 
+        self.spills.extend(loaded.spills); 
+        self.index_array_formulas(start); 
     }
 
-    pub fn resolve_ref(&self, expr: Expr) -> Result<Array2<Value>, Error> {
-        if let Expr::Reference {sheet, reference} = expr {
-            let (mut row, mut col, mut num_rows, mut num_cols) = Reference::from(reference).get_dimensions();
-            let sheet: &Sheet = match sheet {
-                Some(s) => self.get_sheet_by_name(s), 
-                None => self.get_sheet_by_idx(self.current_sheet)
-            };
-            if num_rows == usize::MAX { 
-                num_rows = sheet.values.dim().0; 
-                row = 1; // To avoid subtract overflow on row_idx_start
-            }
-            if num_cols == usize::MAX { 
-                num_cols = sheet.values.dim().0; 
-                col = 1; // To avoid subtract overflow on col_idx_start
-            }
-            let row_idx_start: usize = sheet.values.dim().0.min(row-1);
-            let row_idx_end: usize = sheet.values.dim().0.min(row+num_rows-1);
-            let rows_append: usize = num_rows - (row_idx_end - row_idx_start);
-            let col_idx_start: usize = sheet.values.dim().1.min(col-1);
-            let col_idx_end: usize = sheet.values.dim().1.min(col+num_cols-1);
-            let cols_append: usize = num_cols - (col_idx_end - col_idx_start);
-            let mut output: Array2<SheetValue> = sheet.values.slice(s![row_idx_start..row_idx_end, col_idx_start..col_idx_end]).into_owned(); 
-            if rows_append > 0 {
-                for _ in 0..rows_append {
-                    output.push(Axis(0), ArrayView::from(&Array::from_elem(output.dim().1, SheetValue::new()))).unwrap(); 
-                }
+    // Looks up blocks from the given index onwards by each of their cells 
+    fn index_array_formulas(&mut self, start: usize) {
+        for (idx, block) in self.array_formulas.iter().enumerate().skip(start) {
+            for (row, column) in Reference::get_cells_from_dim(block.row, block.column, block.num_row, block.num_col) {
+                self.array_formula_cells.insert((block.sheet, row, column), idx); 
             }
-            if cols_append > 0 {
-                for _ in 0..cols_append {
-                    output.push(Axis(1), ArrayView::from(&Array::from_elem(output.dim().0, SheetValue::new()))).unwrap(); 
+        }
+    }
+
+    fn decode_cell_value(shared_strings: &[SharedString], date_system: DateSystem, flags: &SheetFlags, cell_text: &str) -> Result<Value, Error> {
+        let invalid = || Error::InvalidValue(flags.current_cell_reference.clone(), cell_text.to_string()); 
+        match flags.cell_type {
+            CellType::SharedString => {
+                let shared_string_idx: usize = cell_text.parse::<usize>().map_err(|_| invalid())?; 
+                let SharedString(s) = shared_strings.get(shared_string_idx).ok_or_else(invalid)?; 
+                Ok(Value::from(s.clone()))
+            }, 
+            CellType::InlineString | CellType::FormulaString => Ok(Value::from(decode_ooxml_escapes(cell_text))), 
+            CellType::Boolean => {
+                match cell_text {
+                    "1" | "TRUE" => Ok(Value::Bool(true)), 
+                    "0" | "FALSE" => Ok(Value::Bool(false)), 
+                    _ => Err(invalid())
                 }
-            }
-            Ok(output.map(|b| {
-                if b.is_calculated() {
-                    b.calculated.clone()
+            }, 
+            CellType::Error => cell_text.parse::<ExcelError>().map(Value::Error).map_err(|_| invalid()), 
+            CellType::Date => {
+                // ISO 8601, as Strict workbooks store dates 
+                let text = cell_text.trim(); 
+                let datetime: NaiveDateTime = NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f")
+                    .or_else(|_| NaiveDate::parse_from_str(text, "%Y-%m-%d").map(|d| d.and_time(NaiveTime::MIN)))
+                    .map_err(|_| invalid())?; 
+                // Midnight is a plain date, the same as a whole serial number 
+                if datetime.time() == NaiveTime::MIN {
+                    Ok(Value::from(datetime.date()))
                 } else {
-                    b.value.clone()
+                    Ok(Value::from(datetime))
                 }
-           }))
-        } else {
-            panic!("Can only resolve a reference expression.")
+            }, 
+            CellType::Number => {
+                match cell_text {
+                    "TRUE" => Ok(Value::Bool(true)), 
+                    "FALSE" => Ok(Value::Bool(false)), 
+                    _ => {
+                        let num = cell_text.trim().parse::<f64>().map_err(|_| invalid())?; 
+                        if flags.is_date {
+                            Ok(Value::from_serial(num, date_system))
+                        } else {
+                            Ok(Value::Num(num))
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Bottom right corner of a declared <dimension>, None when it cannot be trusted 
+    fn parse_dimension(declared: &str) -> Option<(usize, usize)> {
+        let end = declared.rsplit(':').next()?.replace('$', ""); 
+        let letters = end.chars().take_while(|c| c.is_ascii_alphabetic()).count(); 
+        if letters == 0 || letters == end.len() || !end[letters..].chars().all(|c| c.is_ascii_digit()) {
+            return None; 
+        }
+        let (row, column) = Cell::from(end).as_tuple(); 
+        match (row, column) {
+            (1..=1048576, 1..=16384) => Some((row, column)), 
+            _ => None
+        }
+    }
+
+    pub fn save(&mut self, path: &str) -> Result<(), Error> {
+        // Written in memory first, the package being read may be the file being replaced 
+        let mut output = Cursor::new(Vec::new()); 
+        self.save_to_writer(&mut output)?; 
+        std::fs::write(path, output.into_inner())?; 
+        Ok(())
+    }
+
+    // Rewrites cells in the worksheets and adds new shared strings, copying every other part unchanged 
+    pub fn save_to_writer<W: Write + Seek>(&mut self, writer: W) -> Result<(), Error> {
+        if self.zip.is_none() {
+            return Err(Error::MissingPackage); 
+        }
+        if self.is_ods()? {
+            return Err(Error::UnsupportedSave(String::from(ods::MIME_TYPE))); 
+        }
+        if self.is_xlsb()? {
+            return Err(Error::UnsupportedSave(String::from("xlsb"))); 
+        }
+        let mut parts: HashMap<String, Vec<u8>> = HashMap::new(); 
+        let shared_strings_xml = Book::read_part(&mut self.zip, "xl/sharedStrings.xml")?; 
+        let mut shared_strings = SharedStringTable::new(&self.shared_strings, shared_strings_xml.is_some()); 
+        for sheet_idx in 0..self.sheets.len() {
+            if let Some(part) = self.sheets[sheet_idx].part.clone() 
+                && let Some(xml) = Book::read_part(&mut self.zip, &part)? {
+                let sheet_xml = SheetWriter::new(self, sheet_idx, &mut shared_strings).write_xml(&xml)?; 
+                parts.insert(part, sheet_xml); 
+            }
+        }
+        if let Some(xml) = shared_strings_xml 
+            && !shared_strings.added.is_empty() {
+            parts.insert(String::from("xl/sharedStrings.xml"), shared_strings.write_xml(&xml)?); 
+        }
+        let zip = self.zip.as_mut().ok_or(Error::MissingPackage)?; 
+        let mut output = ZipWriter::new(writer); 
+        for idx in 0..zip.len() {
+            let file = zip.by_index_raw(idx)?; 
+            if let Some(bytes) = parts.get(file.name()) {
+                let options = FileOptions::default().compression_method(CompressionMethod::Deflated); 
+                output.start_file(file.name(), options)?; 
+                output.write_all(bytes)?; 
+            } else {
+                output.raw_copy_file(file)?; 
+            }
+        }
+        output.finish()?; 
+        Ok(())
+    }
+
+    pub fn read_part(zip: &mut Option<ZipType>, part: &str) -> Result<Option<Vec<u8>>, Error> {
+        match Book::open_part(zip, part)? {
+            Some(mut f) => {
+                let mut bytes: Vec<u8> = vec![]; 
+                f.read_to_end(&mut bytes)?; 
+                Ok(Some(bytes))
+            }, 
+            None => Ok(None)
+        }
+    }
+
+    pub fn zip_from_path(path: &str) -> Result<ZipType, Error> {
+        Book::zip_from_reader(File::open(path)?)
+    }
+
+    pub fn zip_from_reader<R: Read + Seek + Send + 'static>(reader: R) -> Result<ZipType, Error> {
+        Ok(ZipArchive::new(Box::new(reader) as Box<dyn ReadSeek>)?)
+    }
+
+    // Parts missing from the package are skipped, other zip failures are errors 
+    pub fn open_part<'a>(zip: &'a mut Option<ZipType>, part: &str) -> Result<Option<ZipFile<'a>>, Error> {
+        match zip.as_mut().map(|z| z.by_name(part)) {
+            Some(Ok(f)) => Ok(Some(f)), 
+            Some(Err(ZipError::FileNotFound)) | None => Ok(None), 
+            Some(Err(e)) => Err(Error::from(e))
+        }
+    }
+
+    pub fn decode_text_event(_reader: &Reader<BufReader<ZipFile>>, e: &BytesText) -> Result<String, Error> {
+        Ok(e.unescape()?.to_string())
+    }
+
+    pub fn decode_attribute_usize(reader: &Reader<BufReader<ZipFile>>, a: Attribute) -> Result<usize, Error> {
+        let key = String::from_utf8_lossy(a.key.as_ref()).to_string(); 
+        let value = Book::decode_attribute_string(reader, a)?; 
+        value.trim().parse::<usize>().map_err(|_| Error::InvalidAttribute(key, value))
+    }
+
+    pub fn decode_attribute_string(reader: &Reader<BufReader<ZipFile>>, a: Attribute) -> Result<String, Error> {
+        Ok(a.decode_and_unescape_value(reader.decoder())?.to_string())
+    }
+
+    pub fn decode_style(reader: &Reader<BufReader<ZipFile>>, e: &BytesStart) -> Result<Style, Error> {
+        let mut number_format_id : usize = 0; 
+        let mut apply_number_format: bool = false; 
+        for a in e.attributes() {
+            let a = a?; 
+            match a.key.local_name().as_ref() {
+                b"numFmtId" => {
+                    number_format_id = Book::decode_attribute_usize(reader, a)?; 
+                }, 
+                b"applyNumberFormat" => {
+                    apply_number_format = Book::decode_attribute_usize(reader, a)? != 0; 
+                }, 
+                _ => {}
+            }
+        }
+        Ok(Style { number_format_id, apply_number_format, format_code: String::new() })
+    }
+
+    // Local name of a SpreadsheetML element under whatever prefix the part binds, in the Transitional or the Strict 
+    // namespace. Extension elements such as <xm:f> reuse its local names and are None 
+    pub fn element_name<'n>(reader: &NsReader<BufReader<ZipFile>>, name: QName<'n>) -> Option<&'n [u8]> {
+        match reader.resolve_element(name) {
+            (ResolveResult::Bound(Namespace(uri)), local) if SPREADSHEETML_NAMESPACES.contains(&uri) => Some(local.into_inner()), 
+            (ResolveResult::Unbound, local) => Some(local.into_inner()), 
+            _ => None
+        }
+    }
+
+    pub fn is_element(reader: &NsReader<BufReader<ZipFile>>, name: QName, local_name: &[u8]) -> bool {
+        Book::element_name(reader, name) == Some(local_name)
+    }
+
+    pub fn get_mut_sheet_by_name<'a>(&'a mut self, s: &'a str) -> &'a mut Sheet {
+        let idx = self.sheets.iter().position(|x| x.name == s).unwrap(); 
+        self.get_mut_sheet_by_idx(idx)
+    }
+
+    pub fn get_mut_sheet_by_idx(&mut self, idx: usize) -> &mut Sheet {
+        self.sheets.get_mut(idx).unwrap()
+    }
+
+    pub fn get_sheet_by_name(&self, s: String) -> &Sheet {
+        let idx = self.sheets.iter().position(|x| x.name == s.as_str()).unwrap(); 
+        self.get_sheet_by_idx(idx)
+    }
+
+    // Sheets between the two ends of a 3D reference in workbook order, None when either is missing 
+    pub fn get_sheet_span(&self, first: &str, last: &str) -> Option<Vec<String>> {
+        let a: usize = self.sheets.iter().position(|x| x.name == first)?; 
+        let b: usize = self.sheets.iter().position(|x| x.name == last)?; 
+        Some(self.sheets[a.min(b)..=a.max(b)].iter().map(|x| x.name.clone()).collect())
+    }
+
+    pub fn get_sheet_by_idx(&self, idx: usize) -> &Sheet {
+        self.sheets.get(idx).unwrap()
+    }
+
+    // Names scoped to the sheet take precedence over workbook names 
+    pub fn get_defined_name(&self, name: &str, sheet_idx: usize) -> Option<&DefinedName> {
+        let mut candidates = self.defined_names.iter().filter(|x| x.name.eq_ignore_ascii_case(name)); 
+        candidates.clone().find(|x| x.sheet == Some(sheet_idx)).or_else(|| candidates.find(|x| x.sheet.is_none()))
+    }
+
+    // Names that lead back to themselves through the names in their formulas, X = X+1, evaluate to #REF! 
+    pub fn is_circular_name(&self, name: &str, sheet_idx: Option<usize>) -> bool {
+        self.find_circular_name(name, sheet_idx, &mut vec![])
+    }
+
+    // Walks the names a name refers to, resolving holds the defined names on the current path 
+    fn find_circular_name(&self, name: &str, sheet_idx: Option<usize>, resolving: &mut Vec<usize>) -> bool {
+        let mut candidates = self.defined_names.iter().enumerate().filter(|(_, x)| x.name.eq_ignore_ascii_case(name)); 
+        let found = candidates.clone().find(|(_, x)| sheet_idx.is_some() && x.sheet == sheet_idx).or_else(|| candidates.find(|(_, x)| x.sheet.is_none())); 
+        let Some((idx, defined_name)) = found else { return false }; 
+        if resolving.contains(&idx) {
+            return true; 
+        }
+        let Ok(expr) = defined_name.expression() else { return false }; 
+        resolving.push(idx); 
+        let circular = expr.names().into_iter().any(|(sheet, name)| {
+            let scope = match sheet {
+                Some(s) => self.sheets.iter().position(|x| x.name == s), 
+                None => sheet_idx
+            }; 
+            self.find_circular_name(&name, scope, resolving)
+        }); 
+        resolving.pop(); 
+        circular
+    }
+
+    // Tables without a name are the ones containing the cell 
+    pub fn get_table(&self, name: &Option<String>, sheet_idx: usize, row: usize, column: usize) -> Option<&Table> {
+        match name {
+            Some(n) => self.tables.iter().find(|x| x.name.eq_ignore_ascii_case(n)), 
+            None => self.tables.iter().find(|x| x.sheet == sheet_idx && x.contains(row, column))
+        }
+    }
+
+    pub fn get_array_formula(&self, sheet_idx: usize, row: usize, column: usize) -> Option<CellId> {
+        self.array_formula_cells.get(&(sheet_idx, row, column)).map(|idx| self.array_formulas[*idx])
+    }
+
+    pub fn get_spill_idx(&self, cell_id: &CellId) -> Option<usize> {
+        self.spills.iter().position(|x| x.anchor.sheet == cell_id.sheet && x.anchor.row == cell_id.row && x.anchor.column == cell_id.column)
+    }
+
+    pub fn get_spill(&self, sheet_idx: usize, row: usize, column: usize) -> Option<&Spill> {
+        self.spills.iter().find(|x| x.anchor.sheet == sheet_idx && x.contains(row, column))
+    }
+
+    // A1# refers to everything the formula in A1 currently spills into 
+    pub fn resolve_spill(&self, expr: &Expr) -> Expr {
+        if let Expr::Reference { sheet, reference } = expr {
+            let sheet_idx: usize = match sheet {
+                Some(s) => match self.sheets.iter().position(|x| &x.name == s) {
+                    Some(idx) => idx, 
+                    None => return Expr::Error(ExcelError::Ref)
+                }, 
+                None => self.current_sheet
+            }; 
+            let reference = Reference::from(reference.as_str()); 
+            match self.get_spill(sheet_idx, reference.row(), reference.column()) {
+                Some(spill) if spill.anchor.row == reference.row() && spill.anchor.column == reference.column() => {
+                    Expr::Reference { sheet: sheet.clone(), reference: spill.reference().to_string() }
+                }, 
+                _ => Expr::Error(ExcelError::Ref)
+            }
+        } else {
+            Expr::Error(ExcelError::Ref)
+        }
+    }
+
+    // Writes an array result into the cells next to the formula, or #SPILL! if any of them are taken 
+    fn spill(&mut self, spill_idx: usize, value: Value) -> Result<(), Error> {
+        let previous: Spill = self.spills[spill_idx]; 
+        let cell_id: CellId = previous.anchor; 
+        let (num_rows, num_cols) = value.dimensions(); 
+        let mut next = Spill { anchor: previous.anchor, num_rows, num_cols }; 
+        let sheet: &Sheet = self.get_sheet_by_idx(cell_id.sheet); 
+        let is_blocked = next.cells().into_iter().skip(1).any(|(row, column)| {
+            let is_input = sheet.values.get([row-1, column-1]).is_some_and(|x| !x.value.is_empty()); 
+            let is_other_spill = self.spills.iter().any(|x| x.anchor != previous.anchor && x.anchor.sheet == cell_id.sheet && x.contains(row, column)); 
+            is_input || is_other_spill
+        }); 
+        if is_blocked {
+            next = Spill { anchor: previous.anchor, num_rows: 1, num_cols: 1 }; 
+        }
+        let sheet: &mut Sheet = self.get_mut_sheet_by_idx(cell_id.sheet); 
+        for (row, column) in previous.cells().into_iter().skip(1) {
+            sheet.values[[row-1, column-1]].calculated = Value::Empty; 
+        }
+        sheet.resize(next.anchor.row + next.num_rows - 1, next.anchor.column + next.num_cols - 1); 
+        for (row, column) in next.cells() {
+            let element = if is_blocked {
+                Value::Error(ExcelError::Spill)
+            } else {
+                value.broadcast_get(row - next.anchor.row, column - next.anchor.column).unwrap_or(Value::Empty)
+            }; 
+            sheet.values[[row-1, column-1]].calculated = element; 
+            sheet.values[[row-1, column-1]].dirty = false; 
+        }
+        self.spills[spill_idx] = next; 
+        if next == previous {
+            Ok(())
+        } else {
+            Err(Error::Spill(cell_id))
+        }
+    }
+
+    pub fn resolve_structured_reference(&self, expr: &Expr, sheet_idx: usize, row: usize, column: usize) -> Expr {
+        if let Expr::StructuredReference { table, specifiers, columns } = expr {
+            let resolved = match self.get_table(table, sheet_idx, row, column) {
+                Some(t) => t.resolve(specifiers, columns, row).map(|r| (t.sheet, r)), 
+                None => Err(ExcelError::Ref)
+            }; 
+            match resolved {
+                Ok((sheet, reference)) => Expr::Reference { 
+                    sheet: Some(self.get_sheet_by_idx(sheet).name.clone()), 
+                    reference: reference.to_string() 
+                }, 
+                Err(e) => Expr::Error(e)
+            }
+        } else {
+            panic!("Can only resolve a structured reference expression.")
+        }
+    }
+
+    pub fn resolve_str_ref(&self, s: &str) -> Result<Array2<Value>, Error> {
+        let expr: Expr = parse_str(s)?; 
+        if matches!(expr, Expr::Reference { .. } | Expr::MultiSheetReference { .. } | Expr::Infix(Infix::Range | Infix::Intersect | Infix::Union, _, _)) {
+            self.resolve_ref(expr)
+        } else {
+            panic!("Could not resolve {} to a reference", s); 
+        }
+    }
+
+    // Areas of reference operators between plain references: A1:B2 C:C, (A1,C1:C2). None when an operand 
+    // has to be evaluated first, such as INDEX or OFFSET, or the references are on different sheets 
+    pub fn reference_areas(expr: &Expr) -> Option<Vec<(Option<String>, Reference)>> {
+        match expr {
+            Expr::Reference { sheet, reference } => Some(vec![(sheet.clone(), Reference::from(reference.as_str()))]), 
+            Expr::Infix(Infix::Union, a, b) => Some([Book::reference_areas(a)?, Book::reference_areas(b)?].concat()), 
+            Expr::Infix(op @ (Infix::Range | Infix::Intersect), a, b) => {
+                match (Book::reference_areas(a)?.as_slice(), Book::reference_areas(b)?.as_slice()) {
+                    ([(sheet_a, reference_a)], [(sheet_b, reference_b)]) if sheet_a.is_none() || sheet_b.is_none() || sheet_a == sheet_b => {
+                        let sheet: Option<String> = sheet_a.clone().or(sheet_b.clone()); 
+                        match op {
+                            Infix::Range => Some(vec![(sheet, reference_a.span(reference_b))]), 
+                            _ => Some(reference_a.intersect(reference_b).map(|r| (sheet, r)).into_iter().collect())
+                        }
+                    }, 
+                    _ => None
+                }
+            }, 
+            _ => None
+        }
+    }
+
+    pub fn resolve_ref(&self, expr: Expr) -> Result<Array2<Value>, Error> {
+        if matches!(expr, Expr::Infix(Infix::Range | Infix::Intersect | Infix::Union, _, _)) {
+            // An empty intersection is #NULL!, unions are read one area after another into a single row 
+            let Some(areas) = Book::reference_areas(&expr) else {
+                return Ok(Array2::from_elem((1, 1), Value::Error(ExcelError::Value))); 
+            }; 
+            let mut values: Vec<Value> = vec![]; 
+            for (sheet, reference) in areas.iter() {
+                let value = self.resolve_ref(Expr::Reference { sheet: sheet.clone(), reference: reference.to_string() })?; 
+                if areas.len() == 1 {
+                    return Ok(value); 
+                }
+                values.extend(value); 
+            }
+            if values.is_empty() {
+                values.push(Value::Error(ExcelError::Null)); 
+            }
+            return Ok(Array2::from_shape_fn((1, values.len()), |(_, column)| values[column].clone())); 
+        }
+        if let Expr::MultiSheetReference { sheets: (ref first, ref last), ref reference } = expr {
+            // Each sheet's block is read in workbook order into a single row, #REF! if a sheet is missing 
+            let Some(sheets) = self.get_sheet_span(first, last) else {
+                return Ok(Array2::from_elem((1, 1), Value::Error(ExcelError::Ref))); 
+            }; 
+            let mut values: Vec<Value> = vec![]; 
+            for sheet in sheets.into_iter() {
+                values.extend(self.resolve_ref(Expr::Reference { sheet: Some(sheet), reference: reference.clone() })?); 
+            }
+            return Ok(Array2::from_shape_fn((1, values.len()), |(_, column)| values[column].clone())); 
+        }
+        if let Expr::Reference { sheet: Some(ref s), ref reference } = expr 
+            && ExternalLink::split(s).is_some() {
+            // Other workbooks resolve against the attached book or the cached values, #REF! without either 
+            return match self.get_external_book(s) {
+                Some((book, sheet_name)) if book.sheets.iter().any(|x| x.name == sheet_name) => {
+                    book.resolve_ref(Expr::Reference { sheet: Some(sheet_name.to_string()), reference: reference.clone() })
+                }, 
+                _ => Ok(Array2::from_elem((1, 1), Value::Error(ExcelError::Ref)))
+            }; 
+        }
+        if let Expr::Reference {sheet, reference} = expr {
+            let (mut row, mut col, mut num_rows, mut num_cols) = Reference::from(reference).get_dimensions();
+            let sheet: &Sheet = match sheet {
+                Some(s) => match self.sheets.iter().find(|x| x.name == s) {
+                    Some(sheet) => sheet, 
+                    None => return Ok(Array2::from_elem((1, 1), Value::Error(ExcelError::Ref)))
+                }, 
+                None => self.get_sheet_by_idx(self.current_sheet)
+            };
+            // Whole rows and columns stop at the last stored cell
+            if num_rows == usize::MAX { 
+                num_rows = sheet.values.dim().0; 
+                row = 1; 
+            }
+            if num_cols == usize::MAX { 
+                num_cols = sheet.values.dim().1; 
+                col = 1; 
+            }
+            Ok(sheet.values.slice_map(row-1..row-1+num_rows, col-1..col-1+num_cols, |b| {
+                if b.is_calculated() {
+                    b.calculated.clone()
+                } else {
+                    b.value.clone()
+                }
+           }))
+        } else {
+            panic!("Can only resolve a reference expression.")
         }
     }
 
@@ -419,18 +1322,37 @@ impl Book {
             let cell_value = &sheet.values[[cell_id.row-1, cell_id.column-1]].value; 
             if let Value::Formula(formula_text) = cell_value.clone() {
                 self.current_sheet = cell_id.sheet; 
+                self.current_cell = (cell_id.row, cell_id.column); 
                 let mut chars = formula_text.chars(); // Remove = at beginning
                 chars.next();
                 let expr: Expr = parse_str(chars.as_str())?; 
                 let new_value_result = evaluate_expr_with_context(expr, self, debug);
                 match new_value_result {
                     Ok(new_value) => {
+                        // A union left over at the top has no single area to show, =A1:A3,B2 
+                        let new_value = match new_value {
+                            Value::Areas(areas) if areas.len() > 1 => Value::Error(ExcelError::Value), 
+                            new_value => new_value
+                        }; 
                         if debug {
                             println!("======= Calculated cell: {}.{} -> {}", cell_id.sheet, Reference::from((cell_id.row, cell_id.column)), ensure_non_range(new_value.clone())); 
                         }
+                        let spill_idx: Option<usize> = self.get_spill_idx(cell_id); 
                         let sheet: &mut Sheet = self.get_mut_sheet_by_idx(cell_id.sheet); 
-                        sheet.values[[cell_id.row-1, cell_id.column-1]].calculated = ensure_non_range(new_value).ensure_single(); 
-                        sheet.values[[cell_id.row-1, cell_id.column-1]].dirty = false; 
+                        if cell_id.num_row > 1 || cell_id.num_col > 1 {
+                            // Array formulas fill their whole block from one evaluation
+                            let new_value = ensure_non_range(new_value); 
+                            for (row, column) in Reference::get_cells_from_dim(cell_id.row, cell_id.column, cell_id.num_row, cell_id.num_col) {
+                                let element = new_value.broadcast_get(row - cell_id.row, column - cell_id.column).unwrap_or(Value::Error(ExcelError::NA)); 
+                                sheet.values[[row-1, column-1]].calculated = element; 
+                                sheet.values[[row-1, column-1]].dirty = false; 
+                            }
+                        } else if let Some(spill_idx) = spill_idx {
+                            return self.spill(spill_idx, ensure_non_range(new_value)); 
+                        } else {
+                            sheet.values[[cell_id.row-1, cell_id.column-1]].calculated = ensure_non_range(new_value).ensure_single(); 
+                            sheet.values[[cell_id.row-1, cell_id.column-1]].dirty = false; 
+                        }
                         return Ok(()); 
                     }, 
                     Err(e) => {
@@ -453,8 +1375,16 @@ impl Book {
     }
 
     pub fn calculate(&mut self, debug: bool, progress: bool) -> Result<(), Error> {
+        self.calculate_with(debug, progress, None)
+    }
+
+    // Cells that fail are collected into failures, when given, instead of stopping the calculation 
+    fn calculate_with(&mut self, debug: bool, progress: bool, mut failures: Option<&mut Vec<(CellId, Error)>>) -> Result<(), Error> {
         loop {
             let mut calculated = true; 
+            if let Some(f) = failures.as_mut() {
+                f.clear(); 
+            }
             let order: Vec<CellId> = self.dependencies.get_order(); 
             let pb = match progress {
                 true => ProgressBar::new(order.len() as u64), 
@@ -468,12 +1398,25 @@ impl Book {
                     }, 
                     Err(err) => { 
                         match err {
+                            Error::Spill(_) => {
+                                // Cells that depend on the spill range moved, so the tree is rebuilt 
+                                self.dependencies = DependencyTree::new(); 
+                                self.load_dependencies()?; 
+                                calculated = false; 
+                                break // Recalculate
+                            }, 
                             Error::Volatile(new_expr) => {
-                                self.dependencies.add_expression(*cell_id, *new_expr, &self.sheets)?; 
+                                let mut dependencies = std::mem::take(&mut self.dependencies); 
+                                let result = dependencies.add_expression(*cell_id, *new_expr, self); 
+                                self.dependencies = dependencies; 
+                                result?; 
                                 calculated = false; 
                                 break // Recalculate
                             }, 
-                            _ => return Err(Error::Calculation(*cell_id, Box::new(err))) 
+                            _ => match failures.as_mut() {
+                                Some(f) => f.push((*cell_id, err)), 
+                                None => return Err(Error::Calculation(*cell_id, Box::new(err)))
+                            }
                         } 
                     }
                 }
@@ -485,6 +1428,86 @@ impl Book {
         Ok(())
     }
 
+    // Recalculates the book and compares every formula cell with the result saved in the file, 
+    // numbers may differ by tolerance relative to the larger of 1 and the saved value 
+    pub fn verify(&mut self, tolerance: f64, progress: bool) -> Result<Verification, Error> {
+        let mut failures: Vec<(CellId, Error)> = vec![]; 
+        self.calculate_with(false, progress, Some(&mut failures))?; 
+        let failures: HashMap<(usize, usize, usize), String> = failures.into_iter()
+            .map(|(cell_id, e)| {
+                let message: String = match e {
+                    Error::Calculation(_, inner) => inner.to_string(), 
+                    e => e.to_string()
+                }; 
+                ((cell_id.sheet, cell_id.row, cell_id.column), message)
+            })
+            .collect(); 
+        let mut verification = Verification::default(); 
+        for sheet in self.sheets.iter() {
+            for ((row, column), sheet_value) in sheet.values.indexed_iter() {
+                let (Value::Formula(formula), Some(cached)) = (&sheet_value.value, &sheet_value.cached) else {
+                    continue
+                }; 
+                verification.checked += 1; 
+                let error: Option<String> = failures.get(&(sheet.idx, row + 1, column + 1)).cloned(); 
+                if error.is_none() && Book::matches_cached(cached, &sheet_value.calculated, tolerance, self.date_system) {
+                    continue
+                }
+                let mismatch = Mismatch {
+                    sheet: sheet.name.clone(), 
+                    cell: Cell::from((row + 1, column + 1)).to_string(), 
+                    formula: formula.clone(), 
+                    cached: cached.clone(), 
+                    calculated: sheet_value.calculated.clone(), 
+                    error
+                }; 
+                for name in Book::function_names(formula) {
+                    verification.mismatches.entry(name).or_default().push(mismatch.clone()); 
+                }
+            }
+        }
+        Ok(verification)
+    }
+
+    fn matches_cached(cached: &Value, calculated: &Value, tolerance: f64, date_system: DateSystem) -> bool {
+        match (cached, calculated) {
+            (Value::Num(_) | Value::Date(_) | Value::DateTime(_), Value::Num(_) | Value::Date(_) | Value::DateTime(_) | Value::Empty) => {
+                let (expected, actual) = (cached.as_serial(date_system), calculated.as_serial(date_system)); 
+                (expected - actual).abs() <= tolerance * expected.abs().max(1.0)
+            }, 
+            // A formula returning an empty cell is saved as an empty string 
+            (Value::Text(t), Value::Empty) => t.is_empty(), 
+            _ => cached == calculated
+        }
+    }
+
+    // Functions a formula calls, mismatches in formulas without one are grouped under (none) 
+    fn function_names(formula: &str) -> BTreeSet<String> {
+        fn collect(expr: &Expr, names: &mut BTreeSet<String>) {
+            match expr {
+                Expr::Func { name, args } => {
+                    names.insert(name.to_uppercase()); 
+                    args.iter().for_each(|a| collect(a, names)); 
+                }, 
+                Expr::Infix(_, a, b) => {
+                    collect(a, names); 
+                    collect(b, names); 
+                }, 
+                Expr::Prefix(_, a) | Expr::Postfix(_, a) => collect(a, names), 
+                Expr::Array(items) => items.iter().for_each(|a| collect(a, names)), 
+                _ => {}
+            }
+        }
+        let mut names: BTreeSet<String> = BTreeSet::new(); 
+        if let Ok(expr) = parse_str(formula.trim_start_matches('=')) {
+            collect(&expr, &mut names); 
+        }
+        if names.is_empty() {
+            names.insert(String::from("(none)")); 
+        }
+        names
+    }
+
     pub fn set_value(&mut self, range: &str, value: Value) {
         let expr: Expr = parse_str(range).unwrap(); 
         if let Expr::Reference { sheet, reference } = expr {
@@ -506,6 +1529,7 @@ pub struct SheetValue {
     pub value: Value, 
     pub calculated: Value, 
     pub dirty: bool, 
+    pub cached: Option<Value>, // Result saved with a formula cell by the application that wrote the file
 }
 
 impl From<Value> for SheetValue {
@@ -514,6 +1538,7 @@ impl From<Value> for SheetValue {
             value: v, 
             calculated: Value::Empty, 
             dirty: true,
+            cached: None
         }
     }
 }
@@ -521,13 +1546,13 @@ impl From<Value> for SheetValue {
 impl From<(Value, Value)> for SheetValue {
     fn from(v: (Value, Value)) -> SheetValue {
         let (value, calculated) = v; 
-        SheetValue { value, calculated, dirty: true}
+        SheetValue { value, calculated, dirty: true, cached: None }
     }
 }
 
 impl SheetValue {
     fn new() -> SheetValue {
-        SheetValue { value: Value::Empty, calculated: Value::Empty, dirty: true }
+        SheetValue { value: Value::Empty, calculated: Value::Empty, dirty: true, cached: None }
     }
 
     fn is_calculated(&self) -> bool {
@@ -541,7 +1566,9 @@ pub struct Sheet {
     pub idx: usize, 
     pub max_rows: usize, 
     pub max_columns: usize, 
-    pub values: Array2<SheetValue>
+    pub declared: Option<(usize, usize)>, // Bottom right of the <dimension> in the package, only a hint as it can be stale 
+    pub values: CellStore<SheetValue>, 
+    pub part: Option<String>, // Worksheet part inside the package
 }
 
 impl From<(&str, usize)> for Sheet {
@@ -557,7 +1584,9 @@ impl From<(String, usize)> for Sheet {
             idx: s.1, 
             max_rows: 0, 
             max_columns: 0, 
-            values: Array::from_elem((0, 0), SheetValue::new())
+            declared: None, 
+            values: CellStore::new(SheetValue::new()), 
+            part: None
         }
     }
 }
@@ -565,32 +1594,18 @@ impl From<(String, usize)> for Sheet {
 impl Sheet {
     pub fn set_value(&mut self, reference: Reference, value: Value) {
         let sheet_value = if value.is_formula() {
-            SheetValue {value, calculated: Value::Empty, dirty: true }
+            SheetValue {value, calculated: Value::Empty, dirty: true, cached: None }
         } else {
-            SheetValue {value: value.clone(), calculated: value, dirty: false}
+            SheetValue {value: value.clone(), calculated: value, dirty: false, cached: None }
         }; 
+        self.resize(reference.row(), reference.column()); 
         self.values[[reference.row()-1,reference.column()-1]] = sheet_value; 
     }
 
+    // Cells are stored sparsely, this only tracks the used range
     pub fn resize(&mut self, row: usize, column: usize) {
-        if self.values.dim().0 == 0 && self.values.dim().1 == 0 {
-            self.values = Array::from_elem((row, column), SheetValue::new()); 
-            self.max_columns = column; 
-            self.max_rows = row; 
-        } else {
-            if row > self.max_rows {
-                for _ in 0..(row - self.max_rows) {
-                    self.values.push(Axis(0), ArrayView::from(&Array::from_elem(self.max_columns, SheetValue::new()))).unwrap(); 
-                }
-                self.max_rows = row; 
-            }
-            if column > self.max_columns {
-                for _ in 0..(column - self.max_columns) {
-                    self.values.push(Axis(1), ArrayView::from(&Array::from_elem(self.max_rows, SheetValue::new()))).unwrap(); 
-                }
-                self.max_columns = column; 
-            }
-        }
+        self.max_rows = self.max_rows.max(row); 
+        self.max_columns = self.max_columns.max(column); 
     }
 }
 
@@ -603,63 +1618,989 @@ impl fmt::Display for Sheet {
 #[derive(Debug)]
 pub struct SharedString(String); 
 
-#[derive(Default, Debug)]
-pub struct Style {
-    pub number_format_id: usize, 
-    pub apply_number_format: bool 
+enum CellContent {
+    Value(String), 
+    InlineString(String), 
+    Empty 
 }
 
-impl Style {
-    pub fn new() -> Style {
-        Default::default()
-    }
+// Shared strings as written back to the package, existing entries keep their index 
+struct SharedStringTable {
+    existing: Vec<String>, 
+    indices: HashMap<String, usize>, 
+    added: Vec<String>, 
+    enabled: bool 
 }
 
-#[derive(Debug)]
-struct SheetFlags {
-    is_shared_formula: bool, 
-    is_date: bool, 
-    is_formula: bool, 
-    is_string: bool, 
-    is_value: bool, 
-    current_cell_reference: String, 
-    shared_formulas: Vec<(Cell, String)>, // Start Cell, Formula
-}
+impl SharedStringTable {
+    fn new(shared_strings: &[SharedString], enabled: bool) -> SharedStringTable {
+        let existing: Vec<String> = shared_strings.iter().map(|SharedString(s)| s.clone()).collect(); 
+        let mut indices: HashMap<String, usize> = HashMap::new(); 
+        for (idx, s) in existing.iter().enumerate() {
+            indices.entry(s.clone()).or_insert(idx); 
+        }
+        SharedStringTable { existing, indices, added: vec![], enabled }
+    }
 
-impl SheetFlags {
-    fn new() -> SheetFlags {
-        SheetFlags {
-            is_shared_formula: false, 
-            is_date: false, 
-            is_formula: false, 
-            is_string: false, 
-            is_value: false, 
-            current_cell_reference: String::new(), 
-            shared_formulas: vec![]
+    // Without a shared strings part text is written inline 
+    fn index(&mut self, text: &str, original: Option<usize>) -> Option<usize> {
+        if !self.enabled {
+            return None; 
+        }
+        if let Some(idx) = original.filter(|idx| self.existing.get(*idx).is_some_and(|s| s == text)) {
+            return Some(idx); 
         }
+        if let Some(idx) = self.indices.get(text) {
+            return Some(*idx); 
+        }
+        let idx = self.existing.len() + self.added.len(); 
+        self.added.push(text.to_string()); 
+        self.indices.insert(text.to_string(), idx); 
+        Some(idx)
     }
-    
-    fn reset(&mut self) {
-        self.is_shared_formula = false; 
-        self.is_date = false; 
-        self.is_formula = false;
-        self.is_string = false; 
-        self.is_value = false; 
-        self.current_cell_reference = String::new(); 
+
+    fn write_xml(&self, xml: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut reader = Reader::from_reader(xml); 
+        let mut writer = Writer::new(Vec::new()); 
+        let mut buf = Vec::new(); 
+        loop {
+            match reader.read_event_into(&mut buf)?.into_owned() {
+                Event::Start(e) if e.local_name().as_ref() == b"sst" => {
+                    writer.write_event(Event::Start(self.update_counts(&reader, &e)?))?; 
+                }, 
+                Event::Empty(e) if e.local_name().as_ref() == b"sst" => {
+                    writer.write_event(Event::Start(self.update_counts(&reader, &e)?))?; 
+                    self.write_added(&mut writer, &element_prefix(&e))?; 
+                    writer.write_event(Event::End(e.to_end().into_owned()))?; 
+                }, 
+                Event::End(e) if e.local_name().as_ref() == b"sst" => {
+                    self.write_added(&mut writer, &element_prefix_end(&e))?; 
+                    writer.write_event(Event::End(e))?; 
+                }, 
+                Event::Eof => break, 
+                event => { writer.write_event(event)?; }
+            }
+            buf.clear(); 
+        }
+        Ok(writer.into_inner())
+    }
+
+    fn update_counts(&self, reader: &Reader<&[u8]>, e: &BytesStart) -> Result<BytesStart<'static>, Error> {
+        let mut start = BytesStart::new(String::from_utf8_lossy(e.name().as_ref()).to_string()); 
+        for a in e.attributes() {
+            let a = a?; 
+            match a.key.local_name().as_ref() {
+                b"count" => {
+                    let count = a.decode_and_unescape_value(reader.decoder())?.parse::<usize>().unwrap_or(0) + self.added.len(); 
+                    start.push_attribute(("count", count.to_string().as_str())); 
+                }, 
+                b"uniqueCount" => {
+                    let count = self.existing.len() + self.added.len(); 
+                    start.push_attribute(("uniqueCount", count.to_string().as_str())); 
+                }, 
+                _ => { start.push_attribute(a); }
+            }
+        }
+        Ok(start)
+    }
+
+    fn write_added(&self, writer: &mut Writer<Vec<u8>>, prefix: &str) -> Result<(), Error> {
+        let (si_name, t_name) = (format!("{}si", prefix), format!("{}t", prefix)); 
+        for text in self.added.iter() {
+            writer.write_event(Event::Start(BytesStart::new(si_name.as_str())))?; 
+            writer.write_event(Event::Start(BytesStart::new(t_name.as_str()).with_attributes([("xml:space", "preserve")])))?; 
+            writer.write_event(Event::Text(BytesText::new(text)))?; 
+            writer.write_event(Event::End(BytesEnd::new(t_name.as_str())))?; 
+            writer.write_event(Event::End(BytesEnd::new(si_name.as_str())))?; 
+        }
+        Ok(())
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::workbook::{Sheet, Book};
-    use crate::evaluate::value::Value;
-    use crate::parser::parse_str; 
-    use crate::errors::Error; 
-    use ndarray::arr2; 
+// Rewrites the cells of a worksheet part from the values in the book 
+struct SheetWriter<'a> {
+    book: &'a Book, 
+    sheet_idx: usize, 
+    writer: Writer<Vec<u8>>, 
+    prefix: String, 
+    pending: BTreeMap<usize, BTreeSet<usize>>, // Cells that may not be in the part yet, by row then column 
+    shared_strings: &'a mut SharedStringTable 
+}
 
-    fn get_cell<'a>(book: &'a Book, sheet_name: &'a str, row: usize, column: usize) -> Value {
-        let sheet: &Sheet = book.get_sheet_by_name(sheet_name.to_string()); 
-        sheet.values[[row, column]].value.clone()
+impl<'a> SheetWriter<'a> {
+    fn new(book: &'a Book, sheet_idx: usize, shared_strings: &'a mut SharedStringTable) -> SheetWriter<'a> {
+        let mut pending: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new(); 
+        for ((row, column), sheet_value) in book.sheets[sheet_idx].values.indexed_iter() {
+            let value = SheetWriter::saved_value(sheet_value); 
+            if (!value.is_empty() && !value.is_formula()) || sheet_value.value.is_formula() {
+                pending.entry(row + 1).or_default().insert(column + 1); 
+            }
+        }
+        SheetWriter { book, sheet_idx, writer: Writer::new(Vec::new()), prefix: String::new(), pending, shared_strings }
+    }
+
+    // What a cell shows: formulas and spilled cells show their calculated value 
+    fn saved_value(sheet_value: &SheetValue) -> Value {
+        let value = if sheet_value.value.is_formula() || sheet_value.value.is_empty() {
+            &sheet_value.calculated 
+        } else {
+            &sheet_value.value 
+        }; 
+        value.broadcast_get(0, 0).unwrap_or(Value::Empty)
+    }
+
+    fn write_xml(mut self, xml: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut reader = Reader::from_reader(xml); 
+        let mut buf = Vec::new(); 
+        let mut current_row: usize = 0; 
+        loop {
+            match reader.read_event_into(&mut buf)?.into_owned() {
+                Event::Empty(e) if e.local_name().as_ref() == b"dimension" => {
+                    let dimension = self.dimension(&reader, &e)?; 
+                    self.writer.write_event(Event::Empty(dimension))?; 
+                }, 
+                Event::Empty(e) if e.local_name().as_ref() == b"sheetData" => {
+                    self.prefix = element_prefix(&e); 
+                    self.writer.write_event(Event::Start(e.clone()))?; 
+                    self.write_pending_rows(usize::MAX)?; 
+                    self.writer.write_event(Event::End(e.to_end().into_owned()))?; 
+                }, 
+                Event::Start(e) if e.local_name().as_ref() == b"sheetData" => {
+                    self.prefix = element_prefix(&e); 
+                    self.writer.write_event(Event::Start(e))?; 
+                }, 
+                Event::End(e) if e.local_name().as_ref() == b"sheetData" => {
+                    self.write_pending_rows(usize::MAX)?; 
+                    self.writer.write_event(Event::End(e))?; 
+                }, 
+                Event::Start(e) if e.local_name().as_ref() == b"row" => {
+                    current_row = SheetWriter::row_number(&reader, &e)?; 
+                    self.write_pending_rows(current_row)?; 
+                    self.writer.write_event(Event::Start(e))?; 
+                }, 
+                Event::Empty(e) if e.local_name().as_ref() == b"row" => {
+                    current_row = SheetWriter::row_number(&reader, &e)?; 
+                    self.write_pending_rows(current_row)?; 
+                    self.writer.write_event(Event::Start(e.clone()))?; 
+                    self.write_pending_cells(current_row, usize::MAX)?; 
+                    self.writer.write_event(Event::End(e.to_end().into_owned()))?; 
+                }, 
+                Event::End(e) if e.local_name().as_ref() == b"row" => {
+                    self.write_pending_cells(current_row, usize::MAX)?; 
+                    self.writer.write_event(Event::End(e))?; 
+                }, 
+                Event::Start(e) if e.local_name().as_ref() == b"c" => {
+                    let mut children: Vec<Event<'static>> = vec![]; 
+                    let mut child_buf = Vec::new(); 
+                    loop {
+                        match reader.read_event_into(&mut child_buf)?.into_owned() {
+                            Event::End(end) if end.local_name().as_ref() == b"c" => break, 
+                            Event::Eof => break, 
+                            child => children.push(child)
+                        }
+                        child_buf.clear(); 
+                    }
+                    self.write_existing_cell(&reader, e, children)?; 
+                }, 
+                Event::Empty(e) if e.local_name().as_ref() == b"c" => {
+                    self.write_existing_cell(&reader, e, vec![])?; 
+                }, 
+                Event::Eof => break, 
+                event => { self.writer.write_event(event)?; }
+            }
+            buf.clear(); 
+        }
+        Ok(self.writer.into_inner())
+    }
+
+    // Grows the used range to cover cells added to the sheet 
+    fn dimension(&self, reader: &Reader<&[u8]>, e: &BytesStart) -> Result<BytesStart<'static>, Error> {
+        let sheet = &self.book.sheets[self.sheet_idx]; 
+        let mut dimension = BytesStart::new(String::from_utf8_lossy(e.name().as_ref()).to_string()); 
+        for a in e.attributes() {
+            let a = a?; 
+            if a.key.local_name().as_ref() == b"ref" {
+                let reference = a.decode_and_unescape_value(reader.decoder())?.to_string(); 
+                let (row, column, num_rows, num_cols) = Reference::from(reference.clone()).get_dimensions(); 
+                let (end_row, end_column) = (row + num_rows - 1, column + num_cols - 1); 
+                if sheet.max_rows > end_row || sheet.max_columns > end_column {
+                    let start = Cell::from((row, column)); 
+                    let end = Cell::from((end_row.max(sheet.max_rows), end_column.max(sheet.max_columns))); 
+                    dimension.push_attribute(("ref", format!("{}:{}", start, end).as_str())); 
+                    continue; 
+                }
+            }
+            dimension.push_attribute(a); 
+        }
+        Ok(dimension)
+    }
+
+    fn row_number(reader: &Reader<&[u8]>, e: &BytesStart) -> Result<usize, Error> {
+        for a in e.attributes() {
+            let a = a?; 
+            if a.key.local_name().as_ref() == b"r" {
+                let value = a.decode_and_unescape_value(reader.decoder())?.to_string(); 
+                return value.parse::<usize>().map_err(|_| Error::InvalidAttribute(String::from("r"), value)); 
+            }
+        }
+        Ok(0)
+    }
+
+    // Rows before the given row that only exist in the book 
+    fn write_pending_rows(&mut self, before_row: usize) -> Result<(), Error> {
+        let rows: Vec<usize> = self.pending.range(..before_row).map(|(row, _)| *row).collect(); 
+        for row in rows {
+            let name = format!("{}row", self.prefix); 
+            self.writer.write_event(Event::Start(BytesStart::new(name.as_str()).with_attributes([("r", row.to_string().as_str())])))?; 
+            self.write_pending_cells(row, usize::MAX)?; 
+            self.writer.write_event(Event::End(BytesEnd::new(name.as_str())))?; 
+        }
+        Ok(())
+    }
+
+    // Cells of a row before the given column that only exist in the book 
+    fn write_pending_cells(&mut self, row: usize, before_column: usize) -> Result<(), Error> {
+        let columns: Vec<usize> = match self.pending.get_mut(&row) {
+            Some(columns) => {
+                let remaining = columns.split_off(&before_column); 
+                std::mem::replace(columns, remaining).into_iter().collect()
+            }, 
+            None => return Ok(())
+        }; 
+        if self.pending.get(&row).is_some_and(|x| x.is_empty()) {
+            self.pending.remove(&row); 
+        }
+        for column in columns {
+            let reference = Cell::from((row, column)).to_string(); 
+            let cell = BytesStart::new(format!("{}c", self.prefix)).with_attributes([("r", reference.as_str())]); 
+            self.write_cell(cell, (row, column), vec![], None)?; 
+        }
+        Ok(())
+    }
+
+    fn write_existing_cell(&mut self, reader: &Reader<&[u8]>, cell: BytesStart<'static>, children: Vec<Event<'static>>) -> Result<(), Error> {
+        let mut reference = String::new(); 
+        let mut is_shared_string = false; 
+        for a in cell.attributes() {
+            let a = a?; 
+            match a.key.local_name().as_ref() {
+                b"r" => { reference = a.decode_and_unescape_value(reader.decoder())?.to_string(); }, 
+                b"t" => { is_shared_string = a.value.as_ref() == b"s"; }, 
+                _ => {}
+            }
+        }
+        let (row, column) = Cell::from(reference).as_tuple(); 
+        self.write_pending_cells(row, column)?; 
+        if let Some(columns) = self.pending.get_mut(&row) {
+            columns.remove(&column); 
+        }
+        // The shared string the cell pointed to, reused while the text is unchanged 
+        let original_string: Option<usize> = match is_shared_string {
+            true => children.iter().find_map(|x| match x {
+                Event::Text(t) => t.unescape().ok().and_then(|x| x.trim().parse::<usize>().ok()), 
+                _ => None
+            }), 
+            false => None
+        }; 
+        self.write_cell(cell, (row, column), children, original_string)
+    }
+
+    // The formula the book holds for a cell, array formulas keep the <f> they were loaded with 
+    fn book_formula(&self, row: usize, column: usize) -> Option<String> {
+        match self.book.sheets[self.sheet_idx].values.get([row.wrapping_sub(1), column.wrapping_sub(1)]) {
+            Some(SheetValue { value: Value::Formula(formula), .. }) if self.book.get_array_formula(self.sheet_idx, row, column).is_none() => {
+                Some(formula.trim_start_matches('=').to_string())
+            }, 
+            _ => None
+        }
+    }
+
+    fn write_cell(&mut self, cell: BytesStart<'static>, (row, column): (usize, usize), children: Vec<Event<'static>>, original_string: Option<usize>) -> Result<(), Error> {
+        let value = self.book.sheets[self.sheet_idx].values.get([row.wrapping_sub(1), column.wrapping_sub(1)]).map(SheetWriter::saved_value); 
+        let book_formula: Option<String> = self.book_formula(row, column); 
+        let value = match value {
+            Some(v) if !v.is_formula() && !v.is_range() => v, 
+            // Cells the book knows nothing new about are written back as they were 
+            _ => {
+                self.writer.write_event(Event::Start(cell.clone()))?; 
+                for child in children {
+                    self.writer.write_event(child)?; 
+                }
+                self.writer.write_event(Event::End(cell.to_end().into_owned()))?; 
+                return Ok(()); 
+            }
+        }; 
+        // Formulas are kept, cached values and inline strings are replaced 
+        let mut formula: Vec<Event<'static>> = vec![]; 
+        let mut in_formula = false; 
+        for child in children {
+            match &child {
+                Event::Start(e) if e.local_name().as_ref() == b"f" => { in_formula = true; formula.push(child); }, 
+                Event::Empty(e) if e.local_name().as_ref() == b"f" => { formula.push(child); }, 
+                Event::End(e) if e.local_name().as_ref() == b"f" => { in_formula = false; formula.push(child); }, 
+                _ if in_formula => { formula.push(child); }, 
+                _ => {}
+            }
+        }
+        // Shared and array formulas are left to the <f> they were loaded from, other formulas are written 
+        // from the book unless they are unchanged, which keeps prefixes like _xlfn. 
+        let is_grouped = match formula.first() {
+            Some(Event::Start(e) | Event::Empty(e)) => e.attributes().flatten().any(|a| a.key.local_name().as_ref() == b"t" && a.value.as_ref() != b"normal"), 
+            _ => false
+        }; 
+        let original: String = formula.iter().filter_map(|x| match x {
+            Event::Text(t) => t.unescape().ok().map(|t| t.to_string()), 
+            _ => None
+        }).collect(); 
+        let is_unchanged = |text: &String| original.replace("_xlfn.", "").replace("_xlws.", "") == *text; 
+        if let Some(text) = book_formula.filter(|text| !is_grouped && !is_unchanged(text)) {
+            let name = format!("{}f", element_prefix(&cell)); 
+            formula = vec![
+                Event::Start(BytesStart::new(name.clone())), 
+                Event::Text(BytesText::new(&text).into_owned()), 
+                Event::End(BytesEnd::new(name))
+            ]; 
+        }
+        let (cell_type, content): (Option<&str>, CellContent) = match value {
+            Value::Num(n) if n.is_finite() => (None, CellContent::Value(n.to_string())), 
+            Value::Num(_) => (Some("e"), CellContent::Value(ExcelError::Num.to_string())), 
+            Value::Date(_) | Value::DateTime(_) => (None, CellContent::Value(value.as_serial(self.book.date_system).to_string())), 
+            Value::Bool(b) => (Some("b"), CellContent::Value(String::from(if b { "1" } else { "0" }))), 
+            Value::Error(err) => (Some("e"), CellContent::Value(err.to_string())), 
+            Value::Text(text) if !formula.is_empty() => (Some("str"), CellContent::Value(text)), 
+            Value::Text(text) => {
+                match self.shared_strings.index(&text, original_string) {
+                    Some(idx) => (Some("s"), CellContent::Value(idx.to_string())), 
+                    None => (Some("inlineStr"), CellContent::InlineString(text))
+                }
+            }, 
+            _ => (None, CellContent::Empty)
+        }; 
+        let prefix = element_prefix(&cell); 
+        let mut start = BytesStart::new(String::from_utf8_lossy(cell.name().as_ref()).to_string()); 
+        for a in cell.attributes() {
+            let a = a?; 
+            if a.key.local_name().as_ref() != b"t" {
+                start.push_attribute(a); 
+            }
+        }
+        if let Some(t) = cell_type {
+            start.push_attribute(("t", t)); 
+        }
+        let end = start.to_end().into_owned(); 
+        self.writer.write_event(Event::Start(start))?; 
+        for event in formula {
+            self.writer.write_event(event)?; 
+        }
+        match content {
+            CellContent::Value(v) => {
+                let name = format!("{}v", prefix); 
+                self.writer.write_event(Event::Start(BytesStart::new(name.as_str())))?; 
+                self.writer.write_event(Event::Text(BytesText::new(&v)))?; 
+                self.writer.write_event(Event::End(BytesEnd::new(name.as_str())))?; 
+            }, 
+            CellContent::InlineString(text) => {
+                let (is_name, t_name) = (format!("{}is", prefix), format!("{}t", prefix)); 
+                self.writer.write_event(Event::Start(BytesStart::new(is_name.as_str())))?; 
+                self.writer.write_event(Event::Start(BytesStart::new(t_name.as_str()).with_attributes([("xml:space", "preserve")])))?; 
+                self.writer.write_event(Event::Text(BytesText::new(&text)))?; 
+                self.writer.write_event(Event::End(BytesEnd::new(t_name.as_str())))?; 
+                self.writer.write_event(Event::End(BytesEnd::new(is_name.as_str())))?; 
+            }, 
+            CellContent::Empty => {}
+        }
+        self.writer.write_event(Event::End(end))?; 
+        Ok(())
+    }
+}
+
+// Namespace prefix of an element including the colon, empty for the default namespace 
+fn element_prefix(e: &BytesStart) -> String {
+    match e.name().prefix() {
+        Some(prefix) => format!("{}:", String::from_utf8_lossy(prefix.as_ref())), 
+        None => String::new()
+    }
+}
+
+fn element_prefix_end(e: &BytesEnd) -> String {
+    match e.name().prefix() {
+        Some(prefix) => format!("{}:", String::from_utf8_lossy(prefix.as_ref())), 
+        None => String::new()
+    }
+}
+
+// Another workbook referred to by formulas, cached holds the values saved with this book 
+pub struct ExternalLink {
+    pub target: String, 
+    pub cached: Book, 
+    pub live: Option<Book>, 
+}
+
+impl ExternalLink {
+    pub fn new(target: String) -> ExternalLink {
+        ExternalLink { target, cached: Book::new(), live: None }
+    }
+
+    pub fn book(&self) -> &Book {
+        self.live.as_ref().unwrap_or(&self.cached)
+    }
+
+    // [1]Rates -> (1, Rates), C:\Models\[Book.xlsx]Rates -> (Book.xlsx, Rates), None for sheets of this book 
+    pub fn split(sheet: &str) -> Option<(&str, &str)> {
+        let start = sheet.find('[')?; 
+        let (book, sheet_name) = sheet[start+1..].split_once(']')?; 
+        Some((book, sheet_name))
+    }
+
+    pub fn file_name(target: &str) -> &str {
+        target.rsplit(['/', '\\']).next().unwrap_or(target)
+    }
+
+    // Sheets in an expression from a linked book are prefixed with the link 
+    fn qualify(expr: Expr, book: &str, default_sheet: &str) -> Expr {
+        let sheet = |s: Option<String>| Some(format!("[{}]{}", book, s.unwrap_or_else(|| default_sheet.to_string()))); 
+        match expr {
+            Expr::Reference { sheet: s, reference } => Expr::Reference { sheet: sheet(s), reference }, 
+            Expr::MultiSheetReference { sheets: (first, last), reference } => {
+                Expr::MultiSheetReference { sheets: (format!("[{}]{}", book, first), last), reference }
+            }, 
+            Expr::Name { sheet: s, name } => Expr::Name { sheet: sheet(s), name }, 
+            Expr::Infix(op, a, b) => Expr::Infix(op, Box::new(ExternalLink::qualify(*a, book, default_sheet)), Box::new(ExternalLink::qualify(*b, book, default_sheet))), 
+            Expr::Prefix(op, a) => Expr::Prefix(op, Box::new(ExternalLink::qualify(*a, book, default_sheet))), 
+            Expr::Postfix(op, a) => Expr::Postfix(op, Box::new(ExternalLink::qualify(*a, book, default_sheet))), 
+            Expr::Func { name, args } => Expr::Func { name, args: args.into_iter().map(|a| ExternalLink::qualify(a, book, default_sheet)).collect() }, 
+            Expr::Array(items) => Expr::Array(items.into_iter().map(|a| ExternalLink::qualify(a, book, default_sheet)).collect()), 
+            e => e
+        }
+    }
+}
+
+// A formula cell whose calculated result differs from the one saved in the file 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    pub sheet: String, 
+    pub cell: String, 
+    pub formula: String, 
+    pub cached: Value, 
+    pub calculated: Value, 
+    pub error: Option<String>, // Set when the cell could not be calculated
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Verification {
+    pub checked: usize, // Formula cells with a saved result
+    pub mismatches: BTreeMap<String, Vec<Mismatch>>, // By function name, a cell is listed under each function it calls
+}
+
+impl Verification {
+    pub fn is_ok(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+
+    pub fn mismatched_cells(&self) -> usize {
+        self.mismatches.values().flatten().map(|m| (&m.sheet, &m.cell)).collect::<BTreeSet<_>>().len()
+    }
+}
+
+impl fmt::Display for Verification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} of {} formula cells differ from the saved results", self.mismatched_cells(), self.checked)?; 
+        for (name, mismatches) in self.mismatches.iter() {
+            writeln!(f, "{} ({})", name, mismatches.len())?; 
+            for m in mismatches.iter() {
+                match &m.error {
+                    Some(e) => writeln!(f, "    {}!{} {}: expected {}, failed with {}", m.sheet, m.cell, m.formula, m.cached, e)?, 
+                    None => writeln!(f, "    {}!{} {}: expected {}, calculated {}", m.sheet, m.cell, m.formula, m.cached, m.calculated)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefinedName {
+    pub name: String, 
+    pub sheet: Option<usize>, // None for workbook scope
+    pub formula: String, 
+}
+
+impl DefinedName {
+    pub fn expression(&self) -> Result<Expr, Error> {
+        parse_str(self.formula.trim_start_matches('='))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Spill {
+    pub anchor: CellId, // Dynamic array formula
+    pub num_rows: usize, 
+    pub num_cols: usize, 
+}
+
+impl Spill {
+    pub fn contains(&self, row: usize, column: usize) -> bool {
+        (self.anchor.row..self.anchor.row+self.num_rows).contains(&row) 
+            && (self.anchor.column..self.anchor.column+self.num_cols).contains(&column)
+    }
+
+    // Anchor first 
+    pub fn cells(&self) -> Vec<(usize, usize)> {
+        Reference::get_cells_from_dim(self.anchor.row, self.anchor.column, self.num_rows, self.num_cols)
+    }
+
+    pub fn reference(&self) -> Reference {
+        if self.num_rows == 1 && self.num_cols == 1 {
+            Reference::from((self.anchor.row, self.anchor.column))
+        } else {
+            Reference::from((self.anchor.row, self.anchor.column, self.anchor.row + self.num_rows - 1, self.anchor.column + self.num_cols - 1))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub name: String, 
+    pub sheet: usize, 
+    pub reference: String, // Range including header and totals rows
+    pub header_rows: usize, 
+    pub totals_rows: usize, 
+    pub columns: Vec<String>, 
+}
+
+impl Table {
+    pub fn contains(&self, row: usize, column: usize) -> bool {
+        let (start_row, start_col, num_rows, num_cols) = Reference::from(self.reference.clone()).get_dimensions(); 
+        (start_row..start_row+num_rows).contains(&row) && (start_col..start_col+num_cols).contains(&column)
+    }
+
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|x| x.eq_ignore_ascii_case(name))
+    }
+
+    pub fn resolve(&self, specifiers: &[TableSpecifier], columns: &Option<(String, String)>, current_row: usize) -> Result<Reference, ExcelError> {
+        let (top, left, num_rows, num_cols) = Reference::from(self.reference.clone()).get_dimensions(); 
+        let bottom = top + num_rows - 1; 
+        let data = (top + self.header_rows, bottom.saturating_sub(self.totals_rows)); 
+        let specifiers = if specifiers.is_empty() { &[TableSpecifier::Data][..] } else { specifiers }; 
+        let mut rows: Option<(usize, usize)> = None; 
+        for specifier in specifiers {
+            let (start, end) = match specifier {
+                TableSpecifier::All => (top, bottom), 
+                TableSpecifier::Data => data, 
+                TableSpecifier::Headers if self.header_rows > 0 => (top, top), 
+                TableSpecifier::Totals if self.totals_rows > 0 => (bottom, bottom), 
+                TableSpecifier::ThisRow if (data.0..=data.1).contains(&current_row) => (current_row, current_row), 
+                TableSpecifier::ThisRow => return Err(ExcelError::Value), 
+                _ => return Err(ExcelError::Ref)
+            }; 
+            rows = Some(rows.map_or((start, end), |(a, b)| (a.min(start), b.max(end)))); 
+        }
+        let (start_row, end_row) = rows.unwrap(); 
+        let (start_col, end_col) = match columns {
+            Some((first, last)) => {
+                let first = self.column_index(first).ok_or(ExcelError::Ref)?; 
+                let last = self.column_index(last).ok_or(ExcelError::Ref)?; 
+                (left + first.min(last), left + first.max(last))
+            }, 
+            None => (left, left + num_cols - 1)
+        }; 
+        if start_row > end_row {
+            Err(ExcelError::Ref)
+        } else if start_row == end_row && start_col == end_col {
+            Ok(Reference::from((start_row, start_col)))
+        } else {
+            Ok(Reference::from((start_row, start_col, end_row, end_col)))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relationship {
+    pub id: String, 
+    pub rel_type: String, 
+    pub target: String, // Part name relative to the package root, or as written for external targets
+}
+
+impl Relationship {
+    pub fn kind(&self) -> &str {
+        self.rel_type.rsplit('/').next().unwrap_or("")
+    }
+
+    // xl/workbook.xml -> xl/_rels/workbook.xml.rels
+    pub fn rels_path(part: &str) -> String {
+        match part.rsplit_once('/') {
+            Some((dir, file)) => format!("{}/_rels/{}.rels", dir, file), 
+            None => format!("_rels/{}.rels", part)
+        }
+    }
+
+    // Targets are relative to the directory of the source part unless they start with /
+    pub fn resolve_target(part: &str, target: &str) -> String {
+        let mut segments: Vec<&str> = match target.strip_prefix('/') {
+            Some(_) => vec![], 
+            None => part.rsplit_once('/').map(|(dir, _)| dir.split('/').collect()).unwrap_or_default()
+        }; 
+        for segment in target.trim_start_matches('/').split('/') {
+            match segment {
+                ".." => { segments.pop(); }, 
+                "." | "" => {}, 
+                s => segments.push(s)
+            }
+        }
+        segments.join("/")
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct Style {
+    pub number_format_id: usize, 
+    pub apply_number_format: bool, 
+    pub format_code: String 
+}
+
+impl Style {
+    pub fn new() -> Style {
+        Default::default()
+    }
+
+    pub fn kind(&self) -> FormatKind {
+        classify(&self.format_code)
+    }
+
+    pub fn format(&self, value: &Value, date_system: DateSystem) -> String {
+        format_value(value, &self.format_code, date_system)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CellType {
+    Number, 
+    SharedString, 
+    InlineString, 
+    FormulaString, 
+    Boolean, 
+    Error, 
+    Date 
+}
+
+impl From<&[u8]> for CellType {
+    fn from(t: &[u8]) -> CellType {
+        match t {
+            b"s" => CellType::SharedString, 
+            b"inlineStr" => CellType::InlineString, 
+            b"str" => CellType::FormulaString, 
+            b"b" => CellType::Boolean, 
+            b"e" => CellType::Error, 
+            b"d" => CellType::Date, 
+            _ => CellType::Number
+        }
+    }
+}
+
+// Values, formulas and blocks read from one worksheet part 
+#[derive(Debug, Default)]
+struct LoadedSheet {
+    formulas: Vec<(CellId, String)>, 
+    array_formulas: Vec<CellId>, 
+    spills: Vec<Spill>, 
+}
+
+impl LoadedSheet {
+    // Stores a value read from the package
+    fn insert(&mut self, sheet: &mut Sheet, row: usize, column: usize, value: Value, dimensions: (usize, usize)) {
+        if value.is_formula() {
+            let cell_id = CellId::from((sheet.idx, row, column, dimensions.0, dimensions.1, true)); 
+            self.formulas.push((cell_id, value.to_string())); 
+        }
+        sheet.resize(row, column); 
+        sheet.values[[row-1, column-1]] = SheetValue { value: value.clone(), calculated: value, dirty: false, cached: None }; 
+    }
+
+    fn expand_blocks(&self, sheet: &mut Sheet) {
+        // Spilled cells keep their cached values but are not inputs 
+        for spill in self.spills.iter() {
+            sheet.resize(spill.anchor.row + spill.num_rows - 1, spill.anchor.column + spill.num_cols - 1); 
+            for (row, column) in spill.cells().into_iter().skip(1) {
+                let cached: Value = sheet.values[[row-1, column-1]].value.clone(); 
+                sheet.values[[row-1, column-1]] = SheetValue { value: Value::Empty, calculated: cached.clone(), dirty: false, cached: Some(cached) }; 
+            }
+        }
+        // Every cell of an array formula block shows the anchor's formula
+        for block in self.array_formulas.iter() {
+            let formula: Value = sheet.values[[block.row-1, block.column-1]].value.clone(); 
+            sheet.resize(block.row + block.num_row - 1, block.column + block.num_col - 1); 
+            for (row, column) in Reference::get_cells_from_dim(block.row, block.column, block.num_row, block.num_col).into_iter().skip(1) {
+                // The other cells of the block were saved with their part of the result 
+                let cached: Option<Value> = Some(sheet.values[[row-1, column-1]].value.clone()).filter(|x| !x.is_empty()); 
+                sheet.values[[row-1, column-1]] = SheetValue { value: formula.clone(), calculated: formula.clone(), dirty: false, cached }; 
+            }
+        }
+    }
+}
+
+// Parses a worksheet part into its sheet, needing only read access to the rest of the book 
+struct SheetLoader<'a> {
+    sheet_idx: usize, 
+    sheet: &'a mut Sheet, 
+    shared_strings: &'a [SharedString], 
+    styles: &'a [Style], 
+    date_system: DateSystem, 
+    loaded: LoadedSheet, 
+}
+
+impl<'a> SheetLoader<'a> {
+    fn new(sheet_idx: usize, sheet: &'a mut Sheet, shared_strings: &'a [SharedString], styles: &'a [Style], date_system: DateSystem) -> SheetLoader<'a> {
+        SheetLoader { sheet_idx, sheet, shared_strings, styles, date_system, loaded: LoadedSheet::default() }
+    }
+
+    fn read(mut self, f: ZipFile, progress: bool) -> Result<LoadedSheet, Error> {
+        let mut buf = Vec::new();
+        // Progress is measured in bytes of the uncompressed part 
+        let pb = match progress {
+            true => ProgressBar::new(f.size()), 
+            false => ProgressBar::hidden()
+        }; 
+        let mut reader: NsReader<BufReader<ZipFile>> = NsReader::from_reader(BufReader::new(f)); 
+        let mut flags = SheetFlags::new(); 
+        loop {
+            match reader.read_event_into(&mut buf) {
+                Ok(Event::Empty(ref e)) if Book::is_element(&reader, e.name(), b"dimension") => {
+                    for a in e.attributes() {
+                        let a = a?; 
+                        if a.key.local_name().as_ref() == b"ref" {
+                            let declared = Book::decode_attribute_string(&reader, a)?; 
+                            self.sheet.declared = Book::parse_dimension(&declared); 
+                        }
+                    }
+                }, 
+                Ok(Event::End(ref e)) if Book::is_element(&reader, e.name(), b"row") => {
+                    pb.set_position(reader.buffer_position()); 
+                }, 
+                Ok(Event::Start(ref e)) if Book::is_element(&reader, e.name(), b"c") => {
+                    for a in e.attributes() {
+                        let a = a?; 
+                        match a.key.local_name().as_ref() {
+                            b"r" => {
+                                // Cell reference
+                                flags.reset(); 
+                                flags.current_cell_reference = Book::decode_attribute_string(&reader, a)?;
+                            }, 
+                            b"t" => {
+                                // Cell type
+                                flags.cell_type = CellType::from(a.value.as_ref()); 
+                            },
+                            b"cm" => {
+                                // Cell metadata marks dynamic array formulas 
+                                flags.is_dynamic = true; 
+                            },
+                            b"s" => {
+                                // Cell style / date
+                                let cell_style_idx: usize = Book::decode_attribute_usize(&reader, a)?; 
+                                if self.styles.get(cell_style_idx).is_some_and(|style| style.apply_number_format && style.kind().is_date()) {
+                                    flags.is_date = true;
+                                }
+                            }, 
+                            _ => {}
+                        }
+                    }
+                }, 
+                Ok(Event::Start(ref e)) if Book::is_element(&reader, e.name(), b"f") => {
+                    // Formula flag
+                    flags.is_formula = true;
+                    let mut formula_type: Vec<u8> = vec![]; 
+                    let mut formula_reference: Option<String> = None; 
+                    for a in e.attributes() {
+                        let a = a?;
+                        match a.key.local_name().as_ref() {
+                            b"t" => { formula_type = a.value.to_vec(); }, 
+                            b"ref" => { formula_reference = Some(Book::decode_attribute_string(&reader, a)?); }, 
+                            _ => {}
+                        }
+                    }
+                    match (formula_type.as_slice(), formula_reference) {
+                        (b"array", Some(r)) if flags.is_dynamic => { flags.spill_reference = Some(r); }, 
+                        (b"array", Some(r)) => { flags.array_reference = Some(r); }, 
+                        (b"shared", Some(_)) => { flags.is_shared_formula = true; }, 
+                        _ => {}
+                    }
+                }, 
+                Ok(Event::Empty(ref e)) if Book::is_element(&reader, e.name(), b"f") => {
+                    // Shared formula
+                    for a in e.attributes() {
+                        let a = a?;
+                        if a.key.local_name().as_ref() == b"si" {
+                            let formula_index: usize = Book::decode_attribute_usize(&reader, a)?; 
+                            let (start_cell, formula_text): &(Cell, String) = flags.shared_formulas.get(formula_index)
+                                .ok_or_else(|| Error::InvalidAttribute(String::from("si"), formula_index.to_string()))?; 
+                            let base_reference = Reference::from(start_cell.as_tuple()); 
+                            let current_cell = Cell::from(flags.current_cell_reference.clone()); 
+                            let current_reference = Reference::from(current_cell.as_tuple());
+                            let adjusted_formula: Value = Value::Formula(format!("={}", adjust_formula(base_reference, current_reference, formula_text.clone())?)); 
+                            let (row, column): (usize, usize) = current_cell.as_tuple(); 
+                            self.sheet.resize(row, column); 
+                            self.sheet.values[[row-1, column-1]].value = adjusted_formula.clone(); 
+                            let cell_id = CellId::from((self.sheet_idx, row, column, 1, 1, true)); 
+                            self.loaded.formulas.push((cell_id, adjusted_formula.to_string())); 
+                            flags.has_formula = true; 
+                        }
+                    }
+                }, 
+                Ok(Event::Start(ref e)) if Book::is_element(&reader, e.name(), b"v") => {
+                    // Value
+                    flags.is_value = true; 
+                }, 
+                Ok(Event::Start(ref e)) if Book::is_element(&reader, e.name(), b"is") => {
+                    // Inline string, possibly split into rich text runs
+                    flags.is_inline_string = true; 
+                }, 
+                Ok(Event::Start(ref e)) if Book::is_element(&reader, e.name(), b"rPh") => {
+                    flags.is_phonetic = true; 
+                }, 
+                Ok(Event::End(ref e)) if Book::is_element(&reader, e.name(), b"rPh") => {
+                    flags.is_phonetic = false; 
+                }, 
+                Ok(Event::End(ref e)) if Book::is_element(&reader, e.name(), b"is") && flags.is_inline_string && !flags.current_cell_reference.is_empty() => {
+                    let value = Value::from(decode_ooxml_escapes(&flags.inline_string)); 
+                    self.set_loaded_value(&flags.current_cell_reference, value, (1, 1)); 
+                    flags.reset(); 
+                }, 
+                Ok(Event::Text(ref e)) if flags.is_inline_string && !flags.is_phonetic => {
+                    flags.inline_string.push_str(&Book::decode_text_event(&reader, e)?); 
+                }, 
+                Ok(Event::Text(ref e)) if flags.is_value && flags.has_formula => {
+                    // Cached result of the formula, kept as written even when it cannot be decoded 
+                    let cell_text = Book::decode_text_event(&reader, e)?; 
+                    let cached: Option<Value> = Book::decode_cell_value(self.shared_strings, self.date_system, &flags, &cell_text).ok(); 
+                    let (row, column): (usize, usize) = Cell::from(flags.current_cell_reference.clone()).as_tuple(); 
+                    self.sheet.values[[row-1, column-1]].cached = cached; 
+                    flags.reset(); 
+                }, 
+                Ok(Event::Text(ref e)) if flags.is_value || flags.is_formula => {
+                    let cell_text = Book::decode_text_event(&reader, e)?; 
+                    if !cell_text.is_empty() && !flags.current_cell_reference.is_empty() {
+                        let value: Value = if flags.is_formula {
+                            if flags.is_shared_formula {
+                                flags.shared_formulas.push(
+                                    (Cell::from(flags.current_cell_reference.clone()), cell_text.clone())
+                                )
+                            }
+                            Value::Formula(format!("={}", &cell_text.replace("_xlfn.", "").replace("_xlws.", "").to_owned()))
+                        } else {
+                            Book::decode_cell_value(self.shared_strings, self.date_system, &flags, &cell_text)?
+                        }; 
+                        let dimensions: (usize, usize) = match &flags.array_reference {
+                            Some(r) if value.is_formula() => {
+                                let (row, column, num_rows, num_cols) = Reference::from(r.as_str()).get_dimensions(); 
+                                self.loaded.array_formulas.push(CellId::from((self.sheet_idx, row, column, num_rows, num_cols, true))); 
+                                (num_rows, num_cols)
+                            }, 
+                            _ => (1, 1)
+                        }; 
+                        if let (Some(r), true) = (&flags.spill_reference, value.is_formula()) {
+                            let (row, column, num_rows, num_cols) = Reference::from(r.as_str()).get_dimensions(); 
+                            let anchor = CellId::from((self.sheet_idx, row, column, 1, 1, true)); 
+                            self.loaded.spills.push(Spill { anchor, num_rows, num_cols }); 
+                        }
+                        let is_formula: bool = value.is_formula(); 
+                        self.set_loaded_value(&flags.current_cell_reference, value, dimensions); 
+                        if is_formula {
+                            flags.is_formula = false; 
+                            flags.has_formula = true; 
+                        } else {
+                            flags.reset(); 
+                        }
+                    }
+                }, 
+                Ok(Event::Eof) => break, 
+                Err(e) => return Err(Error::from(e)), 
+                _ => {
+                } 
+            }
+            buf.clear(); 
+        }
+        pb.finish(); 
+        self.loaded.expand_blocks(self.sheet); 
+        Ok(self.loaded)
+    }
+
+    fn set_loaded_value(&mut self, cell_reference: &str, value: Value, dimensions: (usize, usize)) {
+        let (row, column): (usize, usize) = Cell::from(cell_reference.to_string()).as_tuple(); 
+        self.loaded.insert(self.sheet, row, column, value, dimensions); 
+    }
+}
+
+#[derive(Debug)]
+struct SheetFlags {
+    is_shared_formula: bool, 
+    is_date: bool, 
+    is_formula: bool, 
+    has_formula: bool, // A following <v> is the cached result 
+    is_value: bool, 
+    is_inline_string: bool, 
+    is_phonetic: bool, 
+    cell_type: CellType, 
+    inline_string: String, 
+    array_reference: Option<String>, 
+    spill_reference: Option<String>, 
+    is_dynamic: bool, 
+    current_cell_reference: String, 
+    shared_formulas: Vec<(Cell, String)>, // Start Cell, Formula
+}
+
+impl SheetFlags {
+    fn new() -> SheetFlags {
+        SheetFlags {
+            is_shared_formula: false, 
+            is_date: false, 
+            is_formula: false, 
+            has_formula: false, 
+            is_value: false, 
+            is_inline_string: false, 
+            is_phonetic: false, 
+            cell_type: CellType::Number, 
+            inline_string: String::new(), 
+            array_reference: None, 
+            spill_reference: None, 
+            is_dynamic: false, 
+            current_cell_reference: String::new(), 
+            shared_formulas: vec![]
+        }
+    }
+    
+    fn reset(&mut self) {
+        self.is_shared_formula = false; 
+        self.is_date = false; 
+        self.is_formula = false;
+        self.has_formula = false; 
+        self.is_value = false; 
+        self.is_inline_string = false; 
+        self.is_phonetic = false; 
+        self.cell_type = CellType::Number; 
+        self.inline_string = String::new(); 
+        self.array_reference = None; 
+        self.spill_reference = None; 
+        self.is_dynamic = false; 
+        self.current_cell_reference = String::new(); 
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::workbook::{Sheet, Book, Relationship};
+    use crate::dependency::CellId; 
+    use crate::evaluate::value::Value;
+    use crate::parser::{parse_str, ast::Error as ExcelError}; 
+    use crate::errors::Error; 
+    use ndarray::arr2; 
+    use crate::utils::DateSystem; 
+    use crate::format::FormatKind; 
+    use std::io::Cursor; 
+    use chrono::NaiveDate; 
+
+    fn get_cell<'a>(book: &'a Book, sheet_name: &'a str, row: usize, column: usize) -> Value {
+        let sheet: &Sheet = book.get_sheet_by_name(sheet_name.to_string()); 
+        sheet.values[[row, column]].value.clone()
     }
 
     #[test]
@@ -671,6 +2612,576 @@ mod tests {
         assert_eq!(&book.sheets[2].name, "test 3");
     }
 
+    #[test]
+    fn test_sheet_relationships() {
+        let mut book = Book::from("assets/relationships.xlsx"); 
+        book.load(false).expect("Could not load workbook"); 
+        assert_eq!(book.sheets.iter().map(|s| s.name.as_str()).collect::<Vec<&str>>(), vec!["Inputs", "Outputs"]); 
+        assert_eq!(book.sheets[0].part, Some(String::from("xl/worksheets/inputs.xml"))); 
+        assert_eq!(get_cell(&book, "Inputs", 1, 0), Value::from(20.0)); 
+        assert_eq!(get_cell(&book, "Outputs", 0, 0), Value::Formula(String::from("=SUM(Inputs!A1:A2)"))); 
+    }
+
+    #[test]
+    fn test_resolve_relationship_target() {
+        assert_eq!(Relationship::rels_path("xl/workbook.xml"), "xl/_rels/workbook.xml.rels"); 
+        assert_eq!(Relationship::resolve_target("xl/workbook.xml", "worksheets/sheet1.xml"), "xl/worksheets/sheet1.xml"); 
+        assert_eq!(Relationship::resolve_target("xl/workbook.xml", "/xl/worksheets/sheet1.xml"), "xl/worksheets/sheet1.xml"); 
+        assert_eq!(Relationship::resolve_target("xl/worksheets/sheet1.xml", "../tables/table1.xml"), "xl/tables/table1.xml"); 
+    }
+
+    #[test]
+    fn test_defined_names() -> Result<(), Error> {
+        let mut book = Book::from("assets/names.xlsx"); 
+        book.load(false).expect("Could not load workbook"); 
+        assert_eq!(book.get_defined_name("revenue", 1).unwrap().formula, "Inputs!$B$1"); 
+        assert_eq!(book.get_defined_name("Local", 1).unwrap().sheet, Some(1)); 
+        assert_eq!(book.get_defined_name("Local", 0).unwrap().sheet, None); 
+        book.calculate(false, false)?; 
+        assert_eq!(book.resolve_str_ref("Outputs!A1:A5")?, arr2(&
+            [[Value::from(200.0)], 
+            [Value::from(75.0)], 
+            [Value::from(6.0)], 
+            [Value::from(101.0)], 
+            [Value::from(7.0)]]
+        )); 
+        assert!(book.is_circular_name("Total", Some(1))); 
+        assert!(book.is_circular_name("LoopB", Some(1))); 
+        assert!(!book.is_circular_name("Net", Some(1))); 
+        assert_eq!(book.resolve_str_ref("Outputs!A6:A7")?, arr2(&[[Value::Error(ExcelError::Ref)], [Value::Error(ExcelError::Ref)]])); 
+        // Scoped to a sheet that does not exist 
+        assert_eq!(book.resolve_str_ref("Outputs!A8")?, arr2(&[[Value::Error(ExcelError::Ref)]])); 
+        book.set_value("Inputs!B1", Value::from(10.0)); 
+        book.calculate(false, false)?; 
+        assert_eq!(book.resolve_str_ref("Outputs!A2")?, arr2(&[[Value::from(7.5)]])); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_tables() -> Result<(), Error> {
+        let mut book = Book::from("assets/tables.xlsx"); 
+        book.load(false).expect("Could not load workbook"); 
+        let table = book.get_table(&Some("sales".to_string()), 0, 1, 1).unwrap(); 
+        assert_eq!(table.columns, vec!["Item", "Qty", "Unit Price", "Amount"]); 
+        assert_eq!(table.totals_rows, 1); 
+        assert_eq!(book.get_table(&None, 0, 3, 2).unwrap().name, "Sales"); 
+        assert!(book.get_table(&None, 1, 3, 2).is_none()); 
+        book.calculate(false, false)?; 
+        assert_eq!(book.resolve_str_ref("Data!D2:D5")?, arr2(&
+            [[Value::from(3.0)], 
+            [Value::from(6.0)], 
+            [Value::from(2.0)], 
+            [Value::from(11.0)]]
+        )); 
+        assert_eq!(book.resolve_str_ref("Summary!A1:A6")?, arr2(&
+            [[Value::from(11.0)], 
+            [Value::from(9.0)], 
+            [Value::from(4.0)], 
+            [Value::from(13.0)], 
+            [Value::from(19.0)], 
+            [Value::Error(ExcelError::Value)]]
+        )); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_strings() {
+        let mut book = Book::from("assets/strings.xlsx"); 
+        book.load(false).unwrap(); 
+        let expected = ["Plain", "Bold and italic", "", "東京", "Tab\tHere", "Literal _x0041_", "Fish & Chips", "After"]; 
+        for (row, text) in expected.iter().enumerate() {
+            assert_eq!(get_cell(&book, "Strings", row, 0), Value::from(text.to_string())); 
+        }
+    }
+
+    #[test]
+    fn test_cell_types() -> Result<(), Error> {
+        let mut book = Book::from("assets/cell_types.xlsx"); 
+        book.load(false)?; 
+        assert_eq!(get_cell(&book, "Types", 0, 0), Value::from(42.5)); 
+        assert_eq!(get_cell(&book, "Types", 1, 0), Value::from(7.0)); 
+        assert_eq!(get_cell(&book, "Types", 2, 0), Value::from("Inline")); 
+        assert_eq!(get_cell(&book, "Types", 3, 0), Value::from("Rich text!")); 
+        assert_eq!(get_cell(&book, "Types", 4, 0), Value::Formula(String::from("=\"ab\"&\"cd\""))); 
+        assert_eq!(get_cell(&book, "Types", 5, 0), Value::Bool(true)); 
+        assert_eq!(get_cell(&book, "Types", 6, 0), Value::Bool(false)); 
+        assert_eq!(get_cell(&book, "Types", 7, 0), Value::Error(ExcelError::NA)); 
+        assert_eq!(get_cell(&book, "Types", 8, 0), Value::Error(ExcelError::Div)); 
+        assert_eq!(get_cell(&book, "Types", 9, 0), Value::from("Cached text")); 
+        assert_eq!(get_cell(&book, "Types", 11, 0), Value::Error(ExcelError::Calc)); 
+        book.calculate(false, false)?; 
+        assert_eq!(book.resolve_str_ref("Types!A5")?, arr2(&[[Value::from("abcd")]])); 
+        assert_eq!(book.resolve_str_ref("Types!A11")?, arr2(&[[Value::Bool(true)]])); 
+        assert_eq!(book.resolve_str_ref("Types!A13")?, arr2(&[[Value::Error(ExcelError::Calc)]])); 
+        assert_eq!(book.resolve_str_ref("Types!A14:A15")?, arr2(&[[Value::from(0.0)], [Value::from(0.01)]])); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_date1904() -> Result<(), Error> {
+        let mut book = Book::from("assets/date1904.xlsx"); 
+        book.load(false)?; 
+        assert_eq!(book.date_system, DateSystem::Excel1904); 
+        let date = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap(); 
+        assert_eq!(get_cell(&book, "Dates", 0, 0), Value::from(date)); 
+        assert_eq!(get_cell(&book, "Dates", 1, 0), Value::from(date.and_hms_opt(18, 0, 0).unwrap())); 
+        assert_eq!(get_cell(&book, "Dates", 4, 0), Value::from(43904.0)); 
+        book.calculate(false, false)?; 
+        assert_eq!(book.resolve_str_ref("Dates!A3")?, arr2(&[[Value::from(date.succ_opt().unwrap().and_hms_opt(0, 0, 0).unwrap())]])); 
+        assert_eq!(book.resolve_str_ref("Dates!A4")?, arr2(&[[Value::from(0.75)]])); 
+        assert_eq!(book.resolve_str_ref("Dates!A6")?, arr2(&[[Value::from(43904.0)]])); 
+        assert_eq!(book.resolve_str_ref("Dates!A7")?, arr2(&[[Value::from(date.succ_opt().unwrap())]])); 
+        assert_eq!(book.resolve_str_ref("Dates!A8")?, arr2(&[[Value::from(21952.0)]])); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_out_of_range_dates() -> Result<(), Error> {
+        let mut book = Book::from("assets/formats.xlsx"); 
+        book.load(false)?; 
+        assert_eq!(get_cell(&book, "Formats", 5, 0), Value::from(1e10)); 
+        assert_eq!(get_cell(&book, "Formats", 6, 0), Value::from(-1e300)); 
+        assert_eq!(book.styles[1].format(&get_cell(&book, "Formats", 5, 0), book.date_system), "####"); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_number_formats() -> Result<(), Error> {
+        let mut book = Book::from("assets/formats.xlsx"); 
+        book.load(false)?; 
+        let date = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap(); 
+        assert_eq!(get_cell(&book, "Formats", 0, 0), Value::from(date)); 
+        assert_eq!(get_cell(&book, "Formats", 1, 0), Value::from(date)); 
+        assert_eq!(get_cell(&book, "Formats", 2, 0), Value::from(NaiveDate::from_ymd_opt(1899, 12, 30).unwrap().and_hms_opt(18, 0, 0).unwrap())); 
+        assert_eq!(get_cell(&book, "Formats", 3, 0), Value::from(0.125)); 
+        let formatted: Vec<String> = (0..5).map(|row| book.styles[row + 1].format(&get_cell(&book, "Formats", row, 0), book.date_system)).collect(); 
+        assert_eq!(formatted, vec!["2024-03-15", "Mar-24", "18:00:00", "12.5%", "€1,234.50"]); 
+        assert_eq!(book.styles[4].kind(), FormatKind::Percent); 
+        assert_eq!(book.styles[5].kind(), FormatKind::Currency); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_reader() -> Result<(), Error> {
+        let bytes: Vec<u8> = std::fs::read("assets/cell_types.xlsx")?; 
+        let mut book = Book::from_reader(Cursor::new(bytes))?; 
+        book.load(false)?; 
+        assert_eq!(get_cell(&book, "Types", 0, 0), Value::from(42.5)); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_errors() {
+        assert!(matches!(Book::open("assets/missing.xlsx"), Err(Error::Io(_)))); 
+        assert!(matches!(Book::from_reader(Cursor::new(b"not a zip".to_vec())), Err(Error::Zip(_)))); 
+    }
+
+    #[test]
+    fn test_save() -> Result<(), Error> {
+        let mut book = Book::from("assets/save.xlsx"); 
+        book.load(false)?; 
+        book.set_value("Data!A1", Value::from(10.0)); 
+        book.set_value("Data!C5", Value::from("Added")); 
+        book.calculate(false, false)?; 
+        let path = std::env::temp_dir().join("formulae_test_save.xlsx"); 
+        book.save(path.to_str().unwrap())?; 
+
+        let mut saved = Book::open(path.to_str().unwrap())?; 
+        let read = |saved: &mut Book, part: &str| String::from_utf8(Book::read_part(&mut saved.zip, part).unwrap().unwrap()).unwrap(); 
+        let sheet_xml = read(&mut saved, "xl/worksheets/sheet1.xml"); 
+        assert!(sheet_xml.contains(r#"<c r="A1"><v>10</v></c>"#)); 
+        assert!(sheet_xml.contains(r#"<c r="A2" s="1"><v>45366</v></c>"#)); 
+        assert!(sheet_xml.contains(r#"<c r="B1" t="s"><v>0</v></c>"#)); 
+        assert!(sheet_xml.contains(r#"<c r="A3"><f>A1+1</f><v>11</v></c>"#)); 
+        assert!(sheet_xml.contains(r#"<row r="5"><c r="C5" t="s"><v>1</v></c></row>"#)); 
+        assert!(sheet_xml.contains("<pageMargins")); 
+        assert!(sheet_xml.contains(r#"<dimension ref="A1:C5"/>"#)); 
+        let shared_strings_xml = read(&mut saved, "xl/sharedStrings.xml"); 
+        assert!(shared_strings_xml.contains(r#"count="2" uniqueCount="2""#)); 
+        assert!(shared_strings_xml.contains("<b/>")); 
+        assert_eq!(read(&mut saved, "xl/theme/theme1.xml"), read(&mut book, "xl/theme/theme1.xml")); 
+
+        saved.load(false)?; 
+        assert_eq!(get_cell(&saved, "Data", 0, 0), Value::from(10.0)); 
+        assert_eq!(get_cell(&saved, "Data", 4, 2), Value::from("Added")); 
+        saved.calculate(false, false)?; 
+        assert_eq!(saved.resolve_str_ref("Data!B2")?, arr2(&[[Value::from("Hello world")]])); 
+        std::fs::remove_file(path)?; 
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_formulas() -> Result<(), Error> {
+        let mut book = Book::from("assets/save.xlsx"); 
+        book.load(false)?; 
+        book.set_value("Data!A1", Value::Formula(String::from("=5*2"))); 
+        book.set_value("Data!A3", Value::Formula(String::from("=A1*3"))); 
+        book.set_value("Data!D1", Value::Formula(String::from("=A1"))); 
+        let path = std::env::temp_dir().join("formulae_test_save_formulas.xlsx"); 
+        book.save(path.to_str().unwrap())?; 
+
+        let mut saved = Book::open(path.to_str().unwrap())?; 
+        let sheet_xml = String::from_utf8(Book::read_part(&mut saved.zip, "xl/worksheets/sheet1.xml")?.unwrap()).unwrap(); 
+        assert!(sheet_xml.contains(r#"<c r="A1"><f>5*2</f></c>"#)); 
+        assert!(sheet_xml.contains(r#"<c r="A3"><f>A1*3</f></c>"#)); 
+        assert!(sheet_xml.contains(r#"<c r="D1"><f>A1</f></c>"#)); 
+        assert!(sheet_xml.contains(r#"<c r="B2" t="str"><f>B1&amp;" world"</f><v>Hello world</v></c>"#)); 
+
+        saved.load(false)?; 
+        saved.calculate(false, false)?; 
+        assert_eq!(saved.resolve_str_ref("Data!A1")?, arr2(&[[Value::from(10.0)]])); 
+        assert_eq!(saved.resolve_str_ref("Data!A3")?, arr2(&[[Value::from(30.0)]])); 
+        assert_eq!(saved.resolve_str_ref("Data!D1")?, arr2(&[[Value::from(10.0)]])); 
+        std::fs::remove_file(path)?; 
+        Ok(())
+    }
+
+    #[test]
+    fn test_sparse_sheet() -> Result<(), Error> {
+        let mut book = Book::from("assets/sparse.xlsx"); 
+        book.load(false)?; 
+        let sheet = book.get_sheet_by_name(String::from("Sparse")); 
+        assert_eq!(sheet.values.len(), 3); 
+        assert_eq!((sheet.max_rows, sheet.max_columns), (1048576, 16384)); 
+        assert_eq!(get_cell(&book, "Sparse", 1048575, 16383), Value::from(2.0)); 
+        book.calculate(false, false)?; 
+        assert_eq!(book.resolve_str_ref("Sparse!A2")?, arr2(&[[Value::from(4.0)]])); 
+        assert_eq!(book.resolve_str_ref("Sparse!XFD1048575:XFD1048576")?, arr2(&[[Value::Empty], [Value::from(2.0)]])); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_declared_dimension() -> Result<(), Error> {
+        let mut book = Book::from("assets/dimension.xlsx"); 
+        book.load(false)?; 
+        let declared = book.get_sheet_by_name(String::from("Declared")); 
+        assert_eq!(declared.declared, Some((5000, 26))); 
+        assert_eq!((declared.max_rows, declared.max_columns), (2, 2)); 
+        assert_eq!(declared.values.len(), 3); 
+        let stale = book.get_sheet_by_name(String::from("Stale")); 
+        assert_eq!(stale.declared, Some((1, 1))); 
+        assert_eq!((stale.max_rows, stale.max_columns), (3, 3)); 
+        let broken = book.get_sheet_by_name(String::from("Broken")); 
+        assert_eq!(broken.declared, None); 
+        assert_eq!((broken.max_rows, broken.max_columns), (2, 2)); 
+        // SUM(A:A) depends on the two stored cells of column A rather than every declared row 
+        assert_eq!(book.dependencies.get_order().len(), 4); 
+        book.calculate(false, false)?; 
+        assert_eq!(book.resolve_str_ref("Declared!B1")?, arr2(&[[Value::from(3.0)]])); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_parallel() -> Result<(), Error> {
+        for path in ["assets/basic.xlsx", "assets/data_types.xlsx", "assets/names.xlsx", "assets/spill.xlsx", "assets/dimension.xlsx"] {
+            let mut sequential = Book::from(path); 
+            sequential.load(false)?; 
+            let mut parallel = Book::from(path); 
+            parallel.load_parallel(false)?; 
+            assert_eq!(parallel.sheets, sequential.sheets); 
+            assert_eq!(parallel.formulas, sequential.formulas); 
+            assert_eq!(parallel.array_formulas, sequential.array_formulas); 
+            assert_eq!(parallel.spills, sequential.spills); 
+        }
+        // Without a path to reopen the sheets are read in sequence 
+        let mut book = Book::from_reader(Cursor::new(std::fs::read("assets/tables.xlsx")?))?; 
+        book.load_parallel(false)?; 
+        let mut expected = Book::from("assets/tables.xlsx"); 
+        expected.load(false)?; 
+        assert_eq!(book.sheets, expected.sheets); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_ods() -> Result<(), Error> {
+        let mut book = Book::from("assets/basic.ods"); 
+        book.load(false)?; 
+        assert_eq!(book.sheets.iter().map(|x| x.name.as_str()).collect::<Vec<&str>>(), vec!["Data", "Other Sheet"]); 
+        assert_eq!(book.get_sheet_by_name(String::from("Data")).max_rows, 7); 
+        assert_eq!(get_cell(&book, "Data", 0, 1), Value::from("a  b\nc")); 
+        assert_eq!(get_cell(&book, "Data", 2, 1), Value::from(2.0)); 
+        assert_eq!(get_cell(&book, "Data", 3, 0), Value::from(chrono::NaiveDate::from_ymd_opt(2024, 2, 29).unwrap())); 
+        assert_eq!(get_cell(&book, "Data", 3, 1), Value::from(0.5)); 
+        assert_eq!(get_cell(&book, "Data", 3, 2), Value::from(true)); 
+        assert_eq!(get_cell(&book, "Data", 4, 1), Value::Formula(String::from("='Other Sheet'!A1*2"))); 
+        assert_eq!(get_cell(&book, "Data", 6, 1), Value::Formula(String::from("=A2:B3*2"))); 
+        assert_eq!(book.array_formulas, vec![CellId::from((0, 6, 1, 2, 2, true))]); 
+        book.calculate(false, false)?; 
+        assert_eq!(book.resolve_str_ref("Data!A5:D5")?, arr2(&[[Value::from(5.0), Value::from(42.0), Value::from("y;es"), Value::from(22.0)]])); 
+        assert_eq!(book.resolve_str_ref("Data!A6:B7")?, arr2(&
+            [[Value::from(4.0), Value::from(4.0)], 
+            [Value::from(4.0), Value::from(4.0)]]
+        )); 
+        assert!(matches!(book.save_to_writer(Cursor::new(Vec::new())), Err(Error::UnsupportedSave(_)))); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_xlsb() -> Result<(), Error> {
+        let mut book = Book::from("assets/basic.xlsb"); 
+        book.load(false)?; 
+        assert_eq!(book.sheets.iter().map(|x| x.name.as_str()).collect::<Vec<&str>>(), vec!["Inputs", "Data Sheet"]); 
+        assert_eq!((book.sheets[0].max_rows, book.sheets[0].max_columns), (6, 5)); 
+        assert_eq!(get_cell(&book, "Inputs", 2, 0), Value::from("hello")); 
+        assert_eq!(get_cell(&book, "Inputs", 3, 1), Value::from("inline")); 
+        assert_eq!(get_cell(&book, "Inputs", 4, 0), Value::from(chrono::NaiveDate::from_ymd_opt(2023, 3, 15).unwrap())); 
+        assert_eq!(get_cell(&book, "Inputs", 4, 1), Value::Error(ExcelError::Div)); 
+        assert_eq!(get_cell(&book, "Inputs", 5, 4), Value::from(1.25)); 
+        assert_eq!(get_cell(&book, "Inputs", 1, 2), Value::Formula(String::from("=(B2+1)"))); 
+        assert_eq!(get_cell(&book, "Data Sheet", 1, 0), Value::Formula(String::from("=CONCAT(\"a\", \"b\")"))); 
+        assert_eq!(book.array_formulas, vec![CellId::from((0, 1, 4, 2, 1, true))]); 
+        assert_eq!(book.sheets[0].values[[0, 1]].cached, Some(Value::from(20.0))); 
+        assert_eq!(book.sheets[0].values[[1, 3]].cached, Some(Value::from(2.5))); 
+        assert_eq!(book.defined_names[0].formula, "Inputs!$A$1:$A$2"); 
+        // Local is scoped to Data Sheet, the third sheet counting the chartsheet that is not loaded 
+        assert_eq!((book.defined_names[1].name.as_str(), book.defined_names[1].sheet), ("Local", Some(1))); 
+        book.calculate(false, false)?; 
+        assert_eq!(book.resolve_str_ref("Inputs!B1:D2")?, arr2(&
+            [[Value::from(20.0), Value::from(21.0), Value::from(10.0)], 
+            [Value::from(12.5), Value::from(13.5), Value::from(2.5)]]
+        )); 
+        assert_eq!(book.resolve_str_ref("Inputs!B3")?, arr2(&[[Value::from("hello!")]])); 
+        assert_eq!(book.resolve_str_ref("'Data Sheet'!A1:A2")?, arr2(&[[Value::from(22.5)], [Value::from("ab")]])); 
+        assert!(matches!(book.save_to_writer(Cursor::new(Vec::new())), Err(Error::UnsupportedSave(_)))); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_xlsb_undecodable_formula() -> Result<(), Error> {
+        use std::io::{Read, Write}; 
+        use zip::{read::ZipArchive, write::{FileOptions, ZipWriter}}; 
+        // Point the CONCAT call on 'Data Sheet'!A2 at a function index that does not exist 
+        let mut source = ZipArchive::new(std::fs::File::open("assets/basic.xlsb")?)?; 
+        let mut output = ZipWriter::new(Cursor::new(Vec::new())); 
+        for idx in 0..source.len() {
+            let mut file = source.by_index(idx)?; 
+            let mut bytes: Vec<u8> = vec![]; 
+            file.read_to_end(&mut bytes)?; 
+            if file.name() == "xl/worksheets/data.bin" {
+                let at = bytes.windows(4).position(|w| w == [0x22, 3, 0xFF, 0]).unwrap(); 
+                bytes[at + 2..at + 4].copy_from_slice(&[0x7F, 0x7F]); 
+            }
+            output.start_file(file.name(), FileOptions::default())?; 
+            output.write_all(&bytes)?; 
+        }
+        let mut book = Book::from_reader(Cursor::new(output.finish()?.into_inner()))?; 
+        book.load(false)?; 
+        assert_eq!(get_cell(&book, "Data Sheet", 1, 0), Value::from("ab")); 
+        assert_eq!(get_cell(&book, "Inputs", 1, 2), Value::Formula(String::from("=(B2+1)"))); 
+        book.calculate(false, false)?; 
+        assert_eq!(book.resolve_str_ref("'Data Sheet'!A2")?, arr2(&[[Value::from("ab")]])); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify() -> Result<(), Error> {
+        let mut book = Book::from("assets/verify.xlsx"); 
+        book.load(false)?; 
+        let sheet = book.get_sheet_by_name(String::from("Sheet1")); 
+        assert_eq!(sheet.values[[0, 1]].cached, Some(Value::from(6.0))); 
+        assert_eq!(sheet.values[[3, 1]].cached, Some(Value::from("2x"))); 
+        assert_eq!(sheet.values[[5, 1]].cached, Some(Value::from(false))); 
+        assert_eq!(sheet.values[[8, 1]].cached, Some(Value::from(3.0))); 
+        assert_eq!(sheet.values[[1, 2]].cached, Some(Value::from(6.0))); 
+        assert_eq!(sheet.values[[0, 0]].cached, None); 
+        let verification = book.verify(1e-9, false)?; 
+        assert_eq!(verification.checked, 11); 
+        assert_eq!(verification.mismatches.keys().collect::<Vec<&String>>(), vec!["(none)", "AVERAGE", "FOOBAR"]); 
+        assert_eq!(verification.mismatches["AVERAGE"][0].cell, "B3"); 
+        assert_eq!(verification.mismatches["AVERAGE"][0].calculated, Value::from(2.5)); 
+        assert!(verification.mismatches["FOOBAR"][0].error.is_some()); 
+        assert_eq!(verification.mismatched_cells(), 3); 
+        assert_eq!(book.verify(0.5, false)?.mismatched_cells(), 1); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_external_links() -> Result<(), Error> {
+        let mut book = Book::from("assets/external.xlsx"); 
+        book.load(false)?; 
+        assert_eq!(book.external_links.len(), 1); 
+        assert_eq!(book.external_links[0].target, "Assumptions.xlsx"); 
+        assert_eq!(book.external_links[0].cached.sheets[0].name, "Rates"); 
+        book.calculate(false, false)?; 
+        assert_eq!(book.resolve_str_ref("Model!A1:A4")?, arr2(&
+            [[Value::from(5.0)], [Value::from(3.0)], [Value::from(0.1)], [Value::Error(ExcelError::Ref)]]
+        )); 
+        assert_eq!(book.resolve_str_ref("'[Assumptions.xlsx]Rates'!B4")?, arr2(&[[Value::from(0.05)]])); 
+        assert_eq!(book.resolve_str_ref("'[Assumptions.xlsx]Rates'!B6")?, arr2(&[[Value::Error(ExcelError::Busy)]])); 
+        assert!(book.get_external_name("[1]", "Growth").is_some()); 
+        assert!(book.get_external_name("[1]", "Spiral").is_none()); 
+
+        let mut assumptions = Book::from("assets/assumptions.xlsx"); 
+        assumptions.load(false)?; 
+        assert_eq!(book.link_book("models/assumptions.XLSX", assumptions), 0); 
+        book.calculate(false, false)?; 
+        assert_eq!(book.resolve_str_ref("Model!A1:A3")?, arr2(&[[Value::from(25.0)], [Value::from(3.0)], [Value::from(0.2)]])); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_reference_operators() -> Result<(), Error> {
+        let mut book = Book::from("assets/refops.xlsx"); 
+        book.load(false)?; 
+        book.calculate(false, false)?; 
+        assert_eq!(book.resolve_str_ref("Sheet1!C1:C5")?, arr2(&
+            [[Value::from(66.0)], [Value::from(9.0)], [Value::from(30.0)], [Value::from(93.0)], [Value::Error(ExcelError::Null)]]
+        )); 
+        assert_eq!(book.resolve_str_ref("Sheet1!D1:D2")?, arr2(&[[Value::from(21.0)], [Value::from(12.0)]])); 
+        assert_eq!(book.resolve_str_ref("Sheet1!B:B Sheet1!3:3")?, arr2(&[[Value::from(30.0)]])); 
+        assert_eq!(book.resolve_str_ref("Sheet1!A1:A2,Sheet1!B5")?, arr2(&[[Value::from(1.0), Value::from(2.0), Value::from(50.0)]])); 
+        assert_eq!(Book::reference_areas(&parse_str("Sheet1!A1:A3 Sheet2!A2")?), None); 
+        // Operands on sheets that do not exist are #REF!, a union has no single value for its cell 
+        assert_eq!(book.resolve_str_ref("Sheet1!G1:G3")?, arr2(&
+            [[Value::Error(ExcelError::Ref)], [Value::Error(ExcelError::Value)], [Value::Error(ExcelError::Ref)]]
+        )); 
+        // Unions keep their areas for AREAS and INDEX 
+        assert_eq!(book.resolve_str_ref("Sheet1!F1:F5")?, arr2(&
+            [[Value::from(3.0)], [Value::from(50.0)], [Value::Error(ExcelError::Ref)], [Value::from(2.0)], [Value::from(1.0)]]
+        )); 
+        assert_eq!(book.resolve_str_ref("Sheet1!A1:INDEX(Sheet1!B:B, 2)")?, arr2(&[[Value::Error(ExcelError::Value)]])); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_r1c1_formulas() -> Result<(), Error> {
+        let mut book = Book::from("assets/r1c1.xlsx"); 
+        book.load(false)?; 
+        assert_eq!(get_cell(&book, "Sheet1", 0, 1), Value::Formula(String::from("=(A1*2)"))); 
+        assert_eq!(get_cell(&book, "Sheet1", 1, 2), Value::Formula(String::from("=(C1+1)"))); 
+        book.calculate(false, false)?; 
+        assert_eq!(book.resolve_str_ref("Sheet1!B1:C3")?, arr2(&[
+            [Value::from(2.0), Value::from(12.0)], 
+            [Value::from(4.0), Value::from(13.0)], 
+            [Value::from(6.0), Value::Empty]
+        ])); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_multisheet_references() -> Result<(), Error> {
+        let mut book = Book::from("assets/multisheet.xlsx"); 
+        book.load(false)?; 
+        book.calculate(false, false)?; 
+        assert_eq!(book.resolve_str_ref("Summary!A1:B6")?, arr2(&[
+            [Value::from(60.0), Value::from(30.0)], 
+            [Value::from(6.0), Value::from(66.0)], 
+            [Value::from(10.0), Value::from(30.0)], 
+            [Value::from(20.0), Value::from(10.0)], 
+            [Value::Error(ExcelError::Ref), Value::from(1055.0)], 
+            [Value::from(22.0), Value::Empty]
+        ])); 
+        assert_eq!(book.get_sheet_span("Mar Final", "Jan"), Some(vec!["Jan".to_string(), "Feb".to_string(), "Mar Final".to_string()])); 
+        assert_eq!(book.resolve_str_ref("Jan:Feb!B1:B2")?, arr2(&[[Value::from(1.0), Value::from(10.0), Value::from(2.0), Value::from(20.0)]])); 
+        assert_eq!(book.resolve_str_ref("Jan:Feb!B:B")?, arr2(&[[Value::from(1.0), Value::from(10.0), Value::from(2.0), Value::from(20.0)]])); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_strict_and_prefixed() -> Result<(), Error> {
+        for path in ["assets/strict.xlsx", "assets/prefixed.xlsx"] {
+            let mut book = Book::from(path); 
+            book.load(false)?; 
+            assert_eq!(book.sheets.iter().map(|x| x.name.as_str()).collect::<Vec<&str>>(), vec!["Inputs", "Calc"], "{}", path); 
+            assert_eq!(book.defined_names[0].formula, "Inputs!$B$1"); 
+            assert_eq!(get_cell(&book, "Inputs", 1, 0), Value::from("shared text")); 
+            assert_eq!(get_cell(&book, "Inputs", 1, 1), Value::from("inline")); 
+            assert_eq!(get_cell(&book, "Inputs", 2, 0), Value::from(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()), "{}", path); 
+            // The data validation formula in the extension list is not a cell formula
+            assert_eq!(book.formulas.len(), 3); 
+            assert_eq!(book.sheets[1].values[[2, 0]].cached, Some(Value::from(420.0))); 
+            book.calculate(false, false)?; 
+            assert_eq!(book.resolve_str_ref("Calc!A1:A3")?, arr2(&[[Value::from(105.0)], [Value::from(210.0)], [Value::from(420.0)]])); 
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_csv() -> Result<(), Error> {
+        let book = Book::from_csv("assets/inputs.csv")?; 
+        assert_eq!(book.sheets[0].name, "inputs"); 
+        assert_eq!((book.sheets[0].max_rows, book.sheets[0].max_columns), (4, 3)); 
+        assert_eq!(get_cell(&book, "inputs", 2, 1), Value::from("a, b")); 
+        assert_eq!(get_cell(&book, "inputs", 3, 1), Value::from(true)); 
+        assert_eq!(get_cell(&book, "inputs", 1, 2), Value::from(chrono::NaiveDate::from_ymd_opt(2024, 1, 31).unwrap())); 
+        assert_eq!(get_cell(&book, "inputs", 2, 2), Value::Empty); 
+
+        let mut book = Book::from("assets/csv_model.xlsx"); 
+        book.load(false)?; 
+        assert_eq!(book.import_csv("assets/inputs.csv", "Inputs")?, 0); 
+        book.calculate(false, false)?; 
+        assert_eq!(book.resolve_str_ref("Model!A1:C1")?, arr2(&[[Value::from(60.0), Value::from("label!"), Value::from(3.0)]])); 
+        assert_eq!(book.import_delimited("5\tx\n", '\t', "Extra")?, 2); 
+        assert_eq!(book.resolve_str_ref("Extra!A1:B1")?, arr2(&[[Value::from(5.0), Value::from("x")]])); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_value() {
+        let mut book = Book::from("assets/malformed.xlsx"); 
+        match book.load(false) {
+            Err(Error::InvalidValue(cell, value)) => {
+                assert_eq!(cell, "B1"); 
+                assert_eq!(value, "abc"); 
+            }, 
+            _ => panic!("Expected an invalid value error")
+        }
+    }
+
+    #[test]
+    fn test_array_formulas() -> Result<(), Error> {
+        let mut book = Book::from("assets/arrays.xlsx"); 
+        book.load(false)?; 
+        let block = CellId::from((0, 1, 3, 3, 1, true)); 
+        assert_eq!(book.get_array_formula(0, 2, 3), Some(block)); 
+        assert_eq!(book.get_array_formula(0, 3, 3), Some(block)); 
+        assert_eq!(book.get_array_formula(0, 4, 3), None); 
+        assert_eq!(get_cell(&book, "Arrays", 2, 2), Value::Formula(String::from("=A1:A3*B1:B3"))); 
+        assert!(book.dependencies.cell_exists(&block)); 
+        assert!(!book.dependencies.cell_exists(&CellId::from((0, 2, 3, 1, 1, true)))); 
+        book.calculate(false, false)?; 
+        assert_eq!(book.resolve_str_ref("Arrays!C1:D3")?, arr2(&
+            [[Value::from(4.0), Value::from(32.0)], 
+            [Value::from(10.0), Value::from(11.0)], 
+            [Value::from(18.0), Value::from(32.0)]]
+        )); 
+        assert_eq!(book.resolve_str_ref("Arrays!E1:G1")?, arr2(&[[Value::from(1.0), Value::from(2.0), Value::from(3.0)]])); 
+        assert_eq!(book.resolve_str_ref("Arrays!F2:F4")?, arr2(&
+            [[Value::from(1.0)], 
+            [Value::from(2.0)], 
+            [Value::Error(ExcelError::NA)]]
+        )); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_dynamic_arrays() -> Result<(), Error> {
+        let mut book = Book::from("assets/spill.xlsx"); 
+        book.load(false)?; 
+        assert_eq!(book.spills.len(), 3); 
+        assert_eq!(get_cell(&book, "Spill", 1, 1), Value::Empty); 
+        book.calculate(false, false)?; 
+        assert_eq!(book.resolve_str_ref("Spill!B1:C3")?, arr2(&
+            [[Value::from(2.0), Value::from(12.0)], 
+            [Value::from(4.0), Value::from(7.0)], 
+            [Value::from(6.0), Value::Empty]]
+        )); 
+        assert_eq!(book.resolve_str_ref("Spill!D1")?, arr2(&[[Value::Error(ExcelError::Spill)]])); 
+        assert_eq!(book.resolve_str_ref("Spill!E2:F3")?, arr2(&
+            [[Value::from(2.0), Value::Empty], 
+            [Value::from(30.0), Value::Empty]]
+        )); 
+        assert_eq!(book.resolve_str_ref("Spill!F1")?, arr2(&[[Value::from(12.0)]])); 
+        // G1 grows past the range saved in the file
+        assert_eq!(book.get_spill(0, 2, 7).unwrap().reference().to_string(), "G1:G2"); 
+        assert_eq!(book.resolve_str_ref("Spill!G1:H2")?, arr2(&
+            [[Value::from(1.0), Value::from(3.0)], 
+            [Value::from(2.0), Value::from(20.0)]]
+        )); 
+        assert_eq!(book.resolve_str_ref("Spill!A4")?, arr2(&[[Value::Error(ExcelError::Ref)]])); 
+        Ok(())
+    }
+
     #[test]
     fn test_cells() {
         let mut book = Book::from("assets/data_types.xlsx"); 
@@ -716,5 +3227,22 @@ mod tests {
         assert!(book.resolve_str_ref("Sheet1!H7").unwrap()[[0, 0]].as_num() - 19.947 < 0.01); 
         Ok(())
     }
-}
 
+    #[test]
+    fn test_load_fixtures() -> Result<(), Error> {
+        let mut paths: Vec<String> = vec![]; 
+        for entry in std::fs::read_dir("assets")? {
+            paths.push(entry?.path().to_string_lossy().to_string()); 
+        }
+        paths.sort(); 
+        // malformed.xlsx is rejected and verify.xlsx calls an unknown function on purpose 
+        for path in paths.iter().filter(|p| [".xlsx", ".xlsb", ".ods"].iter().any(|x| p.ends_with(x)) && !p.ends_with("malformed.xlsx")) {
+            let mut book = Book::from(path.as_str()); 
+            book.load(false).unwrap_or_else(|e| panic!("{} did not load: {}", path, e)); 
+            if !path.ends_with("verify.xlsx") {
+                book.calculate(false, false).unwrap_or_else(|e| panic!("{} did not calculate: {}", path, e)); 
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,867 @@
+use std::collections::HashMap; 
+use std::io::Read; 
+
+use crate::cell::Cell; 
+use crate::errors::Error; 
+use crate::evaluate::value::Value; 
+use crate::parser::ast::{Error as ExcelError, Expr, Infix, Literal, Postfix, Prefix, TableSpecifier}; 
+use crate::utils::DateSystem; 
+
+// BIFF12 record types
+pub const ROW_HEADER: u16 = 0; 
+pub const CELL_BLANK: u16 = 1; 
+pub const CELL_RK: u16 = 2; 
+pub const CELL_ERROR: u16 = 3; 
+pub const CELL_BOOL: u16 = 4; 
+pub const CELL_REAL: u16 = 5; 
+pub const CELL_STRING: u16 = 6; 
+pub const CELL_SHARED_STRING: u16 = 7; 
+pub const FORMULA_STRING: u16 = 8; 
+pub const FORMULA_NUMBER: u16 = 9; 
+pub const FORMULA_BOOL: u16 = 10; 
+pub const FORMULA_ERROR: u16 = 11; 
+pub const SHARED_STRING_ITEM: u16 = 19; 
+pub const NAME: u16 = 39; 
+pub const FORMAT: u16 = 44; 
+pub const XF: u16 = 47; 
+pub const SHEET_DIMENSION: u16 = 148; 
+pub const WORKBOOK_PROPERTIES: u16 = 153; 
+pub const BUNDLE_SHEET: u16 = 156; 
+pub const BEGIN_LIST: u16 = 343; 
+pub const BEGIN_LIST_COLUMN: u16 = 347; 
+pub const SUPPORTING_BOOK: u16 = 359; 
+pub const SUPPORTING_SELF: u16 = 360; 
+pub const SUPPORTING_SAME: u16 = 361; 
+pub const EXTERN_SHEET: u16 = 362; 
+pub const SHARED_FORMULA: u16 = 426; 
+pub const ARRAY_FORMULA: u16 = 427; 
+pub const BEGIN_CELL_XFS: u16 = 617; 
+pub const END_CELL_XFS: u16 = 618; 
+pub const SUPPORTING_ADDIN: u16 = 666; 
+
+const MAX_ROW: u32 = 1048575; 
+const MAX_COLUMN: u16 = 16383; 
+
+// Reads the variable length type and size that prefix every record
+pub struct RecordReader<R: Read> {
+    source: R, 
+}
+
+impl<R: Read> RecordReader<R> {
+    pub fn new(source: R) -> RecordReader<R> {
+        RecordReader { source }
+    }
+
+    pub fn next_record(&mut self) -> Result<Option<(u16, Vec<u8>)>, Error> {
+        let mut byte = [0u8; 1]; 
+        if self.source.read(&mut byte)? == 0 {
+            return Ok(None); 
+        }
+        let mut record_type: u16 = (byte[0] & 0x7F) as u16; 
+        if byte[0] & 0x80 != 0 {
+            self.source.read_exact(&mut byte)?; 
+            record_type |= ((byte[0] & 0x7F) as u16) << 7; 
+        }
+        let mut size: usize = 0; 
+        for i in 0..4 {
+            self.source.read_exact(&mut byte)?; 
+            size |= ((byte[0] & 0x7F) as usize) << (7 * i); 
+            if byte[0] & 0x80 == 0 {
+                break; 
+            }
+        }
+        let mut data = vec![0u8; size]; 
+        self.source.read_exact(&mut data)?; 
+        Ok(Some((record_type, data)))
+    }
+}
+
+// Little endian fields of a record body
+pub struct ByteReader<'a> {
+    data: &'a [u8], 
+    position: usize, 
+    record_type: u16, 
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(record_type: u16, data: &'a [u8]) -> ByteReader<'a> {
+        ByteReader { data, position: 0, record_type }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.position >= self.data.len()
+    }
+
+    pub fn bytes(&mut self, length: usize) -> Result<&'a [u8], Error> {
+        let end = self.position.checked_add(length).filter(|x| *x <= self.data.len()).ok_or(Error::InvalidRecord(self.record_type))?; 
+        let output = &self.data[self.position..end]; 
+        self.position = end; 
+        Ok(output)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, Error> {
+        let b = self.bytes(2)?; 
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, Error> {
+        let b = self.bytes(4)?; 
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub fn i32(&mut self) -> Result<i32, Error> {
+        Ok(self.u32()? as i32)
+    }
+
+    pub fn f64(&mut self) -> Result<f64, Error> {
+        let mut b = [0u8; 8]; 
+        b.copy_from_slice(self.bytes(8)?); 
+        Ok(f64::from_le_bytes(b))
+    }
+
+    // UTF-16 characters with a 32 bit count
+    pub fn wide_string(&mut self) -> Result<String, Error> {
+        let length = self.u32()? as usize; 
+        self.utf16(length)
+    }
+
+    // A count of 0xFFFFFFFF marks a missing string
+    pub fn nullable_wide_string(&mut self) -> Result<Option<String>, Error> {
+        match self.u32()? {
+            0xFFFFFFFF => Ok(None), 
+            length => Ok(Some(self.utf16(length as usize)?))
+        }
+    }
+
+    pub fn utf16(&mut self, length: usize) -> Result<String, Error> {
+        let bytes = self.bytes(length.checked_mul(2).ok_or(Error::InvalidRecord(self.record_type))?)?; 
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|x| u16::from_le_bytes([x[0], x[1]])).collect(); 
+        Ok(String::from_utf16_lossy(&units))
+    }
+
+    // Token stream and extra data of a parsed formula
+    pub fn formula(&mut self) -> Result<(&'a [u8], &'a [u8]), Error> {
+        let cce = self.u32()? as usize; 
+        let rgce = self.bytes(cce)?; 
+        let cb = self.u32()? as usize; 
+        let rgcb = self.bytes(cb)?; 
+        Ok((rgce, rgcb))
+    }
+
+    // Rows then columns of a range, zero based
+    pub fn range(&mut self) -> Result<(u32, u32, u32, u32), Error> {
+        Ok((self.u32()?, self.u32()?, self.u32()?, self.u32()?))
+    }
+}
+
+pub fn error_from_code(code: u8) -> Option<ExcelError> {
+    match code {
+        0x00 => Some(ExcelError::Null), 
+        0x07 => Some(ExcelError::Div), 
+        0x0F => Some(ExcelError::Value), 
+        0x17 => Some(ExcelError::Ref), 
+        0x1D => Some(ExcelError::Name), 
+        0x24 => Some(ExcelError::Num), 
+        0x2A => Some(ExcelError::NA), 
+        0x2B => Some(ExcelError::GettingData), 
+        _ => None
+    }
+}
+
+// Integers and truncated doubles, either of them optionally multiplied by 100
+pub fn rk_number(rk: u32) -> f64 {
+    let value = match rk & 0x02 != 0 {
+        true => ((rk as i32) >> 2) as f64, 
+        false => f64::from_bits(((rk & 0xFFFFFFFC) as u64) << 32)
+    }; 
+    match rk & 0x01 != 0 {
+        true => value / 100.0, 
+        false => value
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct XlsbName {
+    pub name: String, 
+    pub sheet: Option<usize>, 
+    pub rgce: Vec<u8>, 
+    pub rgcb: Vec<u8>, 
+}
+
+// Parts of workbook.bin needed to load the sheets and decode their formulas
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct XlsbWorkbook {
+    pub sheets: Vec<(String, String)>, // Name, relationship id
+    pub date_system: DateSystem, 
+    pub extern_sheets: Vec<Option<(String, String)>>, // First and last sheet name, None outside this workbook
+    pub names: Vec<XlsbName>, 
+}
+
+impl XlsbWorkbook {
+    pub fn context<'a>(&'a self, tables: &'a [XlsbTable]) -> FormulaContext<'a> {
+        FormulaContext { extern_sheets: &self.extern_sheets, names: &self.names, tables }
+    }
+}
+
+pub fn read_workbook<R: Read>(source: R) -> Result<XlsbWorkbook, Error> {
+    let mut records = RecordReader::new(source); 
+    let mut workbook = XlsbWorkbook::default(); 
+    let mut supporting_books: Vec<bool> = vec![]; // True for links back to this workbook
+    let mut xti: Vec<(u32, i32, i32)> = vec![]; 
+    while let Some((record_type, data)) = records.next_record()? {
+        let mut r = ByteReader::new(record_type, &data); 
+        match record_type {
+            WORKBOOK_PROPERTIES if r.u32()? & 0x01 != 0 => workbook.date_system = DateSystem::Excel1904, 
+            BUNDLE_SHEET => {
+                r.bytes(8)?; // Visibility and tab id
+                let rel_id = r.nullable_wide_string()?.unwrap_or_default(); 
+                let name = r.wide_string()?; 
+                workbook.sheets.push((name, rel_id)); 
+            }, 
+            SUPPORTING_SELF | SUPPORTING_SAME => supporting_books.push(true), 
+            SUPPORTING_BOOK | SUPPORTING_ADDIN => supporting_books.push(false), 
+            EXTERN_SHEET => {
+                for _ in 0..r.u32()? {
+                    xti.push((r.u32()?, r.i32()?, r.i32()?)); 
+                }
+            }, 
+            NAME => {
+                r.u32()?; // Flags
+                r.u8()?; // Keyboard shortcut
+                let sheet = match r.u32()? {
+                    0xFFFFFFFF => None, 
+                    itab => Some(itab as usize)
+                }; 
+                let name = r.wide_string()?; 
+                let (rgce, rgcb) = r.formula()?; 
+                workbook.names.push(XlsbName { name, sheet, rgce: rgce.to_vec(), rgcb: rgcb.to_vec() }); 
+            }, 
+            _ => {}
+        }
+    }
+    let sheet_name = |itab: i32| workbook.sheets.get(usize::try_from(itab).ok()?).map(|x| x.0.clone()); 
+    workbook.extern_sheets = xti.into_iter().map(|(book, first, last)| {
+        match supporting_books.get(book as usize).copied().unwrap_or(true) {
+            true => Some((sheet_name(first)?, sheet_name(last)?)), 
+            false => None
+        }
+    }).collect(); 
+    Ok(workbook)
+}
+
+pub fn read_shared_strings<R: Read>(source: R) -> Result<Vec<String>, Error> {
+    let mut records = RecordReader::new(source); 
+    let mut strings: Vec<String> = vec![]; 
+    while let Some((record_type, data)) = records.next_record()? {
+        if record_type == SHARED_STRING_ITEM {
+            let mut r = ByteReader::new(record_type, &data); 
+            r.u8()?; // Rich text and phonetic flags, the runs follow the text
+            strings.push(r.wide_string()?); 
+        }
+    }
+    Ok(strings)
+}
+
+// Number format id of each cell format and the custom format codes
+pub fn read_styles<R: Read>(source: R) -> Result<(Vec<usize>, HashMap<usize, String>), Error> {
+    let mut records = RecordReader::new(source); 
+    let mut cell_formats: Vec<usize> = vec![]; 
+    let mut codes: HashMap<usize, String> = HashMap::new(); 
+    let mut in_cell_xfs = false; 
+    while let Some((record_type, data)) = records.next_record()? {
+        let mut r = ByteReader::new(record_type, &data); 
+        match record_type {
+            FORMAT => {
+                let id = r.u16()? as usize; 
+                codes.insert(id, r.wide_string()?); 
+            }, 
+            BEGIN_CELL_XFS => in_cell_xfs = true, 
+            END_CELL_XFS => in_cell_xfs = false, 
+            XF if in_cell_xfs => {
+                r.u16()?; // Parent style
+                cell_formats.push(r.u16()? as usize); 
+            }, 
+            _ => {}
+        }
+    }
+    Ok((cell_formats, codes))
+}
+
+// Names, sheets, extern sheets and tables that tokens refer to by index
+pub struct FormulaContext<'a> {
+    pub extern_sheets: &'a [Option<(String, String)>], 
+    pub names: &'a [XlsbName], 
+    pub tables: &'a [XlsbTable], 
+}
+
+// Table part of a sheet, rows and columns are 1-based
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct XlsbTable {
+    pub id: u32, 
+    pub name: String, 
+    pub first_row: usize, 
+    pub last_row: usize, 
+    pub first_column: usize, 
+    pub last_column: usize, 
+    pub header_rows: usize, 
+    pub totals_rows: usize, 
+    pub columns: Vec<String>, 
+}
+
+pub fn read_table<R: Read>(source: R) -> Result<XlsbTable, Error> {
+    let mut records = RecordReader::new(source); 
+    let mut table = XlsbTable::default(); 
+    while let Some((record_type, data)) = records.next_record()? {
+        let mut r = ByteReader::new(record_type, &data); 
+        match record_type {
+            BEGIN_LIST => {
+                let (first_row, last_row, first_column, last_column) = r.range()?; 
+                (table.first_row, table.last_row) = (first_row as usize + 1, last_row as usize + 1); 
+                (table.first_column, table.last_column) = (first_column as usize + 1, last_column as usize + 1); 
+                r.u32()?; // Table type
+                table.id = r.u32()?; 
+                table.header_rows = r.u32()? as usize; 
+                table.totals_rows = r.u32()? as usize; 
+                r.bytes(32)?; // Flags, differential formats and connection
+                r.nullable_wide_string()?; // Internal name
+                table.name = r.wide_string()?; 
+            }, 
+            BEGIN_LIST_COLUMN => {
+                r.bytes(24)?; // Field id, totals function and formats
+                let name = r.nullable_wide_string()?; 
+                let caption = r.nullable_wide_string()?; 
+                table.columns.push(caption.or(name).unwrap_or_default()); 
+            }, 
+            _ => {}
+        }
+    }
+    Ok(table)
+}
+
+// Decodes the reverse polish token stream of a formula, relative references are taken from the cell at row, column (1-based)
+pub fn decode_formula(rgce: &[u8], rgcb: &[u8], row: usize, column: usize, context: &FormulaContext) -> Result<Expr, Error> {
+    let mut tokens = ByteReader::new(0, rgce); 
+    let mut extra = ByteReader::new(0, rgcb); 
+    let mut stack: Vec<Expr> = vec![]; 
+    let unsupported = |ptg: u8| Error::UnableToParse(format!("formula token 0x{:02X}", ptg)); 
+    while !tokens.is_empty() {
+        let ptg = tokens.u8()?; 
+        // Operand classes share a base token
+        let base = match ptg {
+            0x20..=0x7F => (ptg & 0x1F) | 0x20, 
+            _ => ptg
+        }; 
+        match base {
+            0x03..=0x11 => {
+                let b = stack.pop().ok_or_else(|| unsupported(ptg))?; 
+                let a = stack.pop().ok_or_else(|| unsupported(ptg))?; 
+                let op = match base {
+                    0x03 => Infix::Plus, 
+                    0x04 => Infix::Minus, 
+                    0x05 => Infix::Multiply, 
+                    0x06 => Infix::Divide, 
+                    0x07 => Infix::Exponent, 
+                    0x08 => Infix::Ampersand, 
+                    0x09 => Infix::LessThan, 
+                    0x0A => Infix::LessThanEqual, 
+                    0x0B => Infix::Equal, 
+                    0x0C => Infix::GreaterThanEqual, 
+                    0x0D => Infix::GreaterThan, 
+                    0x0E => Infix::NotEqual, 
+                    0x0F => Infix::Intersect, 
+                    0x10 => Infix::Union, 
+                    _ => Infix::Range
+                }; 
+                stack.push(Expr::Infix(op, Box::new(a), Box::new(b))); 
+            }, 
+            0x12 | 0x13 => {
+                let a = stack.pop().ok_or_else(|| unsupported(ptg))?; 
+                let prefix = if base == 0x12 { Prefix::Plus } else { Prefix::Minus }; 
+                stack.push(Expr::Prefix(prefix, Box::new(a))); 
+            }, 
+            0x14 => {
+                let a = stack.pop().ok_or_else(|| unsupported(ptg))?; 
+                stack.push(Expr::Postfix(Postfix::Percent, Box::new(a))); 
+            }, 
+            0x15 => {}, // Parentheses are implied by the token order
+            0x17 => {
+                let length = tokens.u16()? as usize; 
+                stack.push(Expr::from(tokens.utf16(length)?)); 
+            }, 
+            0x19 => {
+                let kind = tokens.u8()?; 
+                let data = tokens.u16()?; 
+                match kind {
+                    0x04 => { tokens.bytes((data as usize + 1) * 2)?; }, // Choose jump table
+                    0x10 => {
+                        let a = stack.pop().ok_or_else(|| unsupported(ptg))?; 
+                        stack.push(Expr::Func { name: String::from("SUM"), args: vec![a] }); 
+                    }, 
+                    _ => {}
+                }
+            }, 
+            0x16 => stack.push(Expr::Literal(Literal::Empty)), 
+            0x18 => {
+                let eptg = tokens.u8()?; 
+                match eptg {
+                    0x19 => stack.push(table_reference(&mut tokens, context).ok_or_else(|| unsupported(ptg))?), 
+                    _ => return Err(Error::UnableToParse(format!("formula token 0x{:02X} 0x{:02X}", ptg, eptg)))
+                }
+            }, 
+            0x1C => {
+                let code = tokens.u8()?; 
+                stack.push(Expr::Error(error_from_code(code).ok_or_else(|| Error::UnableToParse(format!("error code 0x{:02X}", code)))?)); 
+            }, 
+            0x1D => stack.push(Expr::from(tokens.u8()? != 0)), 
+            0x1E => stack.push(Expr::from(tokens.u16()? as f64)), 
+            0x1F => stack.push(Expr::from(tokens.f64()?)), 
+            0x20 => {
+                tokens.bytes(14)?; 
+                let rows = extra.u32()? as usize; 
+                let columns = extra.u32()? as usize; 
+                // Rows follow each other like {1,2;3,4} reads into the same expression
+                let mut items: Vec<Expr> = vec![]; 
+                for _ in 0..rows.checked_mul(columns).ok_or_else(|| unsupported(ptg))? {
+                    items.push(match extra.u8()? {
+                        0x00 => Expr::from(extra.f64()?), 
+                        0x01 => {
+                            let length = extra.u16()? as usize; 
+                            Expr::from(extra.utf16(length)?)
+                        }, 
+                        0x02 => Expr::from(extra.u8()? != 0), 
+                        0x04 => {
+                            let code = extra.u8()?; 
+                            extra.bytes(3)?; 
+                            Expr::Error(error_from_code(code).ok_or_else(|| unsupported(ptg))?)
+                        }, 
+                        _ => return Err(unsupported(ptg))
+                    }); 
+                }
+                stack.push(Expr::Array(items)); 
+            }, 
+            0x21 | 0x22 => {
+                let (argc, index) = match base {
+                    0x21 => {
+                        let index = tokens.u16()?; 
+                        let argc = function(index).and_then(|(_, argc)| argc).ok_or_else(|| unsupported(ptg))?; 
+                        (argc, index)
+                    }, 
+                    _ => ((tokens.u8()? & 0x7F) as usize, tokens.u16()? & 0x7FFF)
+                }; 
+                if stack.len() < argc {
+                    return Err(unsupported(ptg)); 
+                }
+                let mut args: Vec<Expr> = stack.split_off(stack.len() - argc); 
+                let name: String = match function(index) {
+                    Some((name, _)) => name.to_string(), 
+                    // User defined and newer functions are named by their first argument
+                    None if index == 255 && !args.is_empty() => match args.remove(0) {
+                        Expr::Name { name, .. } => name.replace("_xlfn.", "").replace("_xlws.", ""), 
+                        _ => return Err(unsupported(ptg))
+                    }, 
+                    None => return Err(Error::FunctionNotSupport(format!("#{}", index)))
+                }; 
+                stack.push(Expr::Func { name, args }); 
+            }, 
+            0x23 => {
+                let index = tokens.u32()? as usize; 
+                let name = index.checked_sub(1).and_then(|i| context.names.get(i)).ok_or_else(|| unsupported(ptg))?; 
+                stack.push(Expr::Name { sheet: None, name: name.name.clone() }); 
+            }, 
+            0x24 | 0x2C => {
+                let relative = base == 0x2C; 
+                let cell = location(tokens.u32()?, tokens.u16()?, relative, row, column); 
+                stack.push(Expr::Reference { sheet: None, reference: cell }); 
+            }, 
+            0x25 | 0x2D => {
+                let relative = base == 0x2D; 
+                stack.push(Expr::Reference { sheet: None, reference: area(&mut tokens, relative, row, column)? }); 
+            }, 
+            0x26..=0x28 => { tokens.bytes(6)?; }, // The sub-expression follows
+            0x29 => { tokens.bytes(2)?; }, 
+            0x2A => { tokens.bytes(6)?; stack.push(Expr::Error(ExcelError::Ref)); }, 
+            0x2B => { tokens.bytes(12)?; stack.push(Expr::Error(ExcelError::Ref)); }, 
+            0x3A | 0x3B => {
+                let sheets = extern_sheet(context, tokens.u16()?); 
+                let reference = match base {
+                    0x3A => location(tokens.u32()?, tokens.u16()?, false, row, column), 
+                    _ => area(&mut tokens, false, row, column)?
+                }; 
+                stack.push(match sheets {
+                    Some((first, last)) if first == last => Expr::Reference { sheet: Some(first.clone()), reference }, 
+                    Some((first, last)) => Expr::MultiSheetReference { sheets: (first.clone(), last.clone()), reference }, 
+                    None => Expr::Error(ExcelError::Ref)
+                }); 
+            }, 
+            0x3C => { tokens.bytes(8)?; stack.push(Expr::Error(ExcelError::Ref)); }, 
+            0x3D => { tokens.bytes(14)?; stack.push(Expr::Error(ExcelError::Ref)); }, 
+            _ => return Err(unsupported(ptg))
+        }
+    }
+    match (stack.pop(), stack.is_empty()) {
+        (Some(expr), true) => Ok(expr), 
+        _ => Err(Error::UnableToParse(String::from("formula tokens")))
+    }
+}
+
+// Table[[#Data],[Amount]] by table id and column indices
+fn table_reference(tokens: &mut ByteReader, context: &FormulaContext) -> Option<Expr> {
+    tokens.u16().ok()?; // Extern sheet
+    let flags = tokens.u16().ok()?; 
+    let id = tokens.u32().ok()?; 
+    let (first, last) = (tokens.u16().ok()? as usize, tokens.u16().ok()? as usize); 
+    // Invalid and nonresident lists have no table to name
+    if flags & 0x3000 != 0 {
+        return None; 
+    }
+    let table = context.tables.iter().find(|t| t.id == id)?; 
+    let specifiers = match (flags >> 2) & 0x1F {
+        0x00 => vec![], 
+        0x01 => vec![TableSpecifier::All], 
+        0x02 => vec![TableSpecifier::Headers], 
+        0x04 => vec![TableSpecifier::Data], 
+        0x06 => vec![TableSpecifier::Headers, TableSpecifier::Data], 
+        0x08 => vec![TableSpecifier::Totals], 
+        0x0C => vec![TableSpecifier::Data, TableSpecifier::Totals], 
+        0x10 => vec![TableSpecifier::ThisRow], 
+        _ => return None
+    }; 
+    let columns = match flags & 0x03 {
+        0 => None, 
+        1 => Some((table.columns.get(first)?.clone(), table.columns.get(first)?.clone())), 
+        _ => Some((table.columns.get(first)?.clone(), table.columns.get(last)?.clone()))
+    }; 
+    Some(Expr::StructuredReference { table: Some(table.name.clone()), specifiers, columns })
+}
+
+fn extern_sheet<'a>(context: &FormulaContext<'a>, ixti: u16) -> Option<&'a (String, String)> {
+    context.extern_sheets.get(ixti as usize)?.as_ref()
+}
+
+// Column bits carry the relative flags, relative tokens hold offsets from the current cell
+fn resolve(row: u32, column: u16, relative: bool, base_row: usize, base_column: usize) -> (usize, bool, usize, bool) {
+    let row_relative = column & 0x8000 != 0; 
+    let column_relative = column & 0x4000 != 0; 
+    let mut r = row as usize; 
+    let mut c = (column & 0x3FFF) as usize; 
+    if relative && row_relative {
+        r = (base_row - 1 + (row as i32 as i64).rem_euclid(MAX_ROW as i64 + 1) as usize) % (MAX_ROW as usize + 1); 
+    }
+    if relative && column_relative {
+        let offset = (((column & 0x3FFF) << 2) as i16 >> 2) as i64; 
+        c = (base_column - 1 + offset.rem_euclid(MAX_COLUMN as i64 + 1) as usize) % (MAX_COLUMN as usize + 1); 
+    }
+    (r + 1, !row_relative, c + 1, !column_relative)
+}
+
+fn location(row: u32, column: u16, relative: bool, base_row: usize, base_column: usize) -> String {
+    let (r, row_anchor, c, column_anchor) = resolve(row, column, relative, base_row, base_column); 
+    format!("{}{}{}{}", if column_anchor { "$" } else { "" }, Cell::column_number_to_letter(c), if row_anchor { "$" } else { "" }, r)
+}
+
+fn area(tokens: &mut ByteReader, relative: bool, base_row: usize, base_column: usize) -> Result<String, Error> {
+    let (first_row, last_row) = (tokens.u32()?, tokens.u32()?); 
+    let (first_column, last_column) = (tokens.u16()?, tokens.u16()?); 
+    let (r1, r1_anchor, c1, c1_anchor) = resolve(first_row, first_column, relative, base_row, base_column); 
+    let (r2, r2_anchor, c2, c2_anchor) = resolve(last_row, last_column, relative, base_row, base_column); 
+    let dollar = |anchor: bool| if anchor { "$" } else { "" }; 
+    let column = |c: usize, anchor: bool| format!("{}{}", dollar(anchor), Cell::column_number_to_letter(c)); 
+    let row = |r: usize, anchor: bool| format!("{}{}", dollar(anchor), r); 
+    Ok(if r1 == 1 && r2 == MAX_ROW as usize + 1 {
+        format!("{}:{}", column(c1, c1_anchor), column(c2, c2_anchor))
+    } else if c1 == 1 && c2 == MAX_COLUMN as usize + 1 {
+        format!("{}:{}", row(r1, r1_anchor), row(r2, r2_anchor))
+    } else {
+        format!("{}{}:{}{}", column(c1, c1_anchor), row(r1, r1_anchor), column(c2, c2_anchor), row(r2, r2_anchor))
+    })
+}
+
+pub fn formula_value(rgce: &[u8], rgcb: &[u8], row: usize, column: usize, context: &FormulaContext) -> Result<Value, Error> {
+    let expr = decode_formula(rgce, rgcb, row, column, context)?; 
+    Ok(Value::Formula(format!("={}", expr)))
+}
+
+// Built-in function names and fixed argument counts by function table index, None for variable arguments
+fn function(index: u16) -> Option<(&'static str, Option<usize>)> {
+    let f = match index {
+        0 => ("COUNT", None), 1 => ("IF", None), 2 => ("ISNA", Some(1)), 3 => ("ISERROR", Some(1)), 
+        4 => ("SUM", None), 5 => ("AVERAGE", None), 6 => ("MIN", None), 7 => ("MAX", None), 
+        8 => ("ROW", None), 9 => ("COLUMN", None), 10 => ("NA", Some(0)), 11 => ("NPV", None), 
+        12 => ("STDEV", None), 13 => ("DOLLAR", None), 14 => ("FIXED", None), 15 => ("SIN", Some(1)), 
+        16 => ("COS", Some(1)), 17 => ("TAN", Some(1)), 18 => ("ATAN", Some(1)), 19 => ("PI", Some(0)), 
+        20 => ("SQRT", Some(1)), 21 => ("EXP", Some(1)), 22 => ("LN", Some(1)), 23 => ("LOG10", Some(1)), 
+        24 => ("ABS", Some(1)), 25 => ("INT", Some(1)), 26 => ("SIGN", Some(1)), 27 => ("ROUND", Some(2)), 
+        28 => ("LOOKUP", None), 29 => ("INDEX", None), 30 => ("REPT", Some(2)), 31 => ("MID", Some(3)), 
+        32 => ("LEN", Some(1)), 33 => ("VALUE", Some(1)), 34 => ("TRUE", Some(0)), 35 => ("FALSE", Some(0)), 
+        36 => ("AND", None), 37 => ("OR", None), 38 => ("NOT", Some(1)), 39 => ("MOD", Some(2)), 
+        46 => ("VAR", None), 48 => ("TEXT", Some(2)), 56 => ("PV", None), 57 => ("FV", None), 
+        58 => ("NPER", None), 59 => ("PMT", None), 60 => ("RATE", None), 61 => ("MIRR", Some(3)), 
+        62 => ("IRR", None), 63 => ("RAND", Some(0)), 64 => ("MATCH", None), 65 => ("DATE", Some(3)), 
+        66 => ("TIME", Some(3)), 67 => ("DAY", Some(1)), 68 => ("MONTH", Some(1)), 69 => ("YEAR", Some(1)), 
+        70 => ("WEEKDAY", None), 71 => ("HOUR", Some(1)), 72 => ("MINUTE", Some(1)), 73 => ("SECOND", Some(1)), 
+        74 => ("NOW", Some(0)), 75 => ("AREAS", Some(1)), 76 => ("ROWS", Some(1)), 77 => ("COLUMNS", Some(1)), 
+        78 => ("OFFSET", None), 82 => ("SEARCH", None), 83 => ("TRANSPOSE", Some(1)), 86 => ("TYPE", Some(1)), 
+        97 => ("ATAN2", Some(2)), 98 => ("ASIN", Some(1)), 99 => ("ACOS", Some(1)), 100 => ("CHOOSE", None), 
+        101 => ("HLOOKUP", None), 102 => ("VLOOKUP", None), 105 => ("ISREF", Some(1)), 109 => ("LOG", None), 
+        111 => ("CHAR", Some(1)), 112 => ("LOWER", Some(1)), 113 => ("UPPER", Some(1)), 114 => ("PROPER", Some(1)), 
+        115 => ("LEFT", None), 116 => ("RIGHT", None), 117 => ("EXACT", Some(2)), 118 => ("TRIM", Some(1)), 
+        119 => ("REPLACE", Some(4)), 120 => ("SUBSTITUTE", None), 121 => ("CODE", Some(1)), 124 => ("FIND", None), 
+        125 => ("CELL", None), 126 => ("ISERR", Some(1)), 127 => ("ISTEXT", Some(1)), 128 => ("ISNUMBER", Some(1)), 
+        129 => ("ISBLANK", Some(1)), 130 => ("T", Some(1)), 131 => ("N", Some(1)), 140 => ("DATEVALUE", Some(1)), 
+        141 => ("TIMEVALUE", Some(1)), 142 => ("SLN", Some(3)), 143 => ("SYD", Some(4)), 144 => ("DDB", None), 
+        148 => ("INDIRECT", None), 162 => ("CLEAN", Some(1)), 163 => ("MDETERM", Some(1)), 164 => ("MINVERSE", Some(1)), 
+        165 => ("MMULT", Some(2)), 167 => ("IPMT", None), 168 => ("PPMT", None), 169 => ("COUNTA", None), 
+        183 => ("PRODUCT", None), 184 => ("FACT", Some(1)), 190 => ("ISNONTEXT", Some(1)), 193 => ("STDEVP", None), 
+        194 => ("VARP", None), 197 => ("TRUNC", None), 198 => ("ISLOGICAL", Some(1)), 212 => ("ROUNDUP", Some(2)), 
+        213 => ("ROUNDDOWN", Some(2)), 216 => ("RANK", None), 219 => ("ADDRESS", None), 220 => ("DAYS360", None), 
+        221 => ("TODAY", Some(0)), 222 => ("VDB", None), 227 => ("MEDIAN", None), 228 => ("SUMPRODUCT", None), 
+        229 => ("SINH", Some(1)), 230 => ("COSH", Some(1)), 231 => ("TANH", Some(1)), 247 => ("DB", None), 
+        252 => ("FREQUENCY", Some(2)), 261 => ("ERROR.TYPE", Some(1)), 269 => ("AVEDEV", None), 276 => ("COMBIN", Some(2)), 
+        279 => ("EVEN", Some(1)), 285 => ("FLOOR", Some(2)), 288 => ("CEILING", Some(2)), 298 => ("ODD", Some(1)), 
+        299 => ("PERMUT", Some(2)), 318 => ("DEVSQ", None), 319 => ("GEOMEAN", None), 320 => ("HARMEAN", None), 
+        321 => ("SUMSQ", None), 325 => ("LARGE", Some(2)), 326 => ("SMALL", Some(2)), 327 => ("QUARTILE", Some(2)), 
+        328 => ("PERCENTILE", Some(2)), 330 => ("MODE", None), 336 => ("CONCATENATE", None), 337 => ("POWER", Some(2)), 
+        342 => ("RADIANS", Some(1)), 343 => ("DEGREES", Some(1)), 344 => ("SUBTOTAL", None), 345 => ("SUMIF", None), 
+        346 => ("COUNTIF", Some(2)), 347 => ("COUNTBLANK", Some(1)), 359 => ("HYPERLINK", None), 361 => ("AVERAGEA", None), 
+        362 => ("MAXA", None), 363 => ("MINA", None), 480 => ("IFERROR", Some(2)), 481 => ("COUNTIFS", None), 
+        482 => ("SUMIFS", None), 483 => ("AVERAGEIF", None), 484 => ("AVERAGEIFS", None), 
+        _ => return None
+    }; 
+    Some(f)
+}
+
+// Cell value and formula of one cell record, the formula tokens are decoded once the sheet is read
+#[derive(Debug, Clone, PartialEq)]
+pub struct XlsbCell {
+    pub row: usize, 
+    pub column: usize, 
+    pub style: usize, 
+    pub value: CellValue, 
+    pub formula: Option<(Vec<u8>, Vec<u8>)>, 
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CellValue {
+    Empty, 
+    Number(f64), 
+    Bool(bool), 
+    Error(ExcelError), 
+    Text(String), 
+    SharedString(usize), 
+}
+
+// Shared and array formulas cover a range, their member cells only point back with PtgExp
+#[derive(Debug, Clone, PartialEq)]
+pub struct XlsbBlock {
+    pub first_row: usize, 
+    pub last_row: usize, 
+    pub first_column: usize, 
+    pub last_column: usize, 
+    pub rgce: Vec<u8>, 
+    pub rgcb: Vec<u8>, 
+}
+
+impl XlsbBlock {
+    pub fn contains(&self, row: usize, column: usize) -> bool {
+        (self.first_row..=self.last_row).contains(&row) && (self.first_column..=self.last_column).contains(&column)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct XlsbSheet {
+    pub dimension: Option<(usize, usize)>, // Last row and column
+    pub cells: Vec<XlsbCell>, 
+    pub shared_formulas: Vec<XlsbBlock>, 
+    pub array_formulas: Vec<XlsbBlock>, 
+}
+
+// Rows and columns are 1-based like the rest of the book
+pub fn read_sheet<R: Read>(source: R) -> Result<XlsbSheet, Error> {
+    let mut records = RecordReader::new(source); 
+    let mut sheet = XlsbSheet::default(); 
+    let mut row: usize = 1; 
+    let block = |r: &mut ByteReader| -> Result<XlsbBlock, Error> {
+        let (first_row, last_row, first_column, last_column) = r.range()?; 
+        if r.record_type == ARRAY_FORMULA {
+            r.u8()?; // Flags
+        }
+        let (rgce, rgcb) = r.formula()?; 
+        Ok(XlsbBlock {
+            first_row: first_row as usize + 1, last_row: last_row as usize + 1, 
+            first_column: first_column as usize + 1, last_column: last_column as usize + 1, 
+            rgce: rgce.to_vec(), rgcb: rgcb.to_vec()
+        })
+    }; 
+    while let Some((record_type, data)) = records.next_record()? {
+        let mut r = ByteReader::new(record_type, &data); 
+        match record_type {
+            ROW_HEADER => row = r.u32()? as usize + 1, 
+            SHEET_DIMENSION => {
+                let (_, last_row, _, last_column) = r.range()?; 
+                if last_row <= MAX_ROW && last_column <= MAX_COLUMN as u32 {
+                    sheet.dimension = Some((last_row as usize + 1, last_column as usize + 1)); 
+                }
+            }, 
+            CELL_BLANK..=FORMULA_ERROR => {
+                let column = r.u32()? as usize + 1; 
+                let style = (r.u32()? & 0xFFFFFF) as usize; 
+                let value = match record_type {
+                    CELL_RK => CellValue::Number(rk_number(r.u32()?)), 
+                    CELL_ERROR | FORMULA_ERROR => {
+                        let code = r.u8()?; 
+                        let invalid = || Error::InvalidValue(Cell::from((row, column)).to_string(), format!("error code 0x{:02X}", code)); 
+                        CellValue::Error(error_from_code(code).ok_or_else(invalid)?)
+                    }, 
+                    CELL_BOOL | FORMULA_BOOL => CellValue::Bool(r.u8()? != 0), 
+                    CELL_REAL | FORMULA_NUMBER => CellValue::Number(r.f64()?), 
+                    CELL_STRING | FORMULA_STRING => CellValue::Text(r.wide_string()?), 
+                    CELL_SHARED_STRING => CellValue::SharedString(r.u32()? as usize), 
+                    _ => CellValue::Empty
+                }; 
+                let formula = match record_type {
+                    FORMULA_STRING..=FORMULA_ERROR => {
+                        r.u16()?; // Recalculation flags
+                        let (rgce, rgcb) = r.formula()?; 
+                        Some((rgce.to_vec(), rgcb.to_vec()))
+                    }, 
+                    _ => None
+                }; 
+                if value != CellValue::Empty || formula.is_some() {
+                    sheet.cells.push(XlsbCell { row, column, style, value, formula }); 
+                }
+            }, 
+            SHARED_FORMULA => sheet.shared_formulas.push(block(&mut r)?), 
+            ARRAY_FORMULA => sheet.array_formulas.push(block(&mut r)?), 
+            _ => {}
+        }
+    }
+    Ok(sheet)
+}
+
+// Formula cells that only hold PtgExp take their tokens from the block covering them
+pub fn is_block_member(rgce: &[u8]) -> bool {
+    rgce.first() == Some(&0x01)
+}
+
+// Shared or array formulas by the row of their first cell, which PtgExp points back to
+pub struct BlockIndex<'a> {
+    rows: HashMap<usize, Vec<&'a XlsbBlock>>, 
+}
+
+impl<'a> BlockIndex<'a> {
+    pub fn new(blocks: &'a [XlsbBlock]) -> BlockIndex<'a> {
+        let mut rows: HashMap<usize, Vec<&'a XlsbBlock>> = HashMap::new(); 
+        for block in blocks {
+            rows.entry(block.first_row).or_default().push(block); 
+        }
+        BlockIndex { rows }
+    }
+
+    pub fn find(&self, rgce: &[u8], row: usize, column: usize) -> Option<&'a XlsbBlock> {
+        let first_row = ByteReader::new(0, rgce.get(1..)?).u32().ok()? as usize + 1; 
+        self.rows.get(&first_row)?.iter().find(|b| b.contains(row, column)).copied()
+    }
+}
+
+pub fn cell_value(value: &CellValue, shared_strings: &[String], is_date: bool, date_system: DateSystem) -> Value {
+    match value {
+        CellValue::Empty => Value::Empty, 
+        CellValue::Number(n) if is_date => Value::from_serial(*n, date_system), 
+        CellValue::Number(n) => Value::Num(*n), 
+        CellValue::Bool(b) => Value::Bool(*b), 
+        CellValue::Error(e) => Value::Error(e.clone()), 
+        CellValue::Text(s) => Value::Text(s.clone()), 
+        CellValue::SharedString(i) => shared_strings.get(*i).map_or(Value::Empty, |s| Value::Text(s.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::xlsb::{decode_formula, error_from_code, rk_number, FormulaContext, XlsbName, XlsbTable}; 
+    use crate::parser::{parse_str, ast::Expr}; 
+    use crate::errors::Error; 
+
+    fn decode(rgce: &[u8], row: usize, column: usize) -> Result<Expr, Error> {
+        decode_with(rgce, &[], row, column)
+    }
+
+    fn decode_with(rgce: &[u8], rgcb: &[u8], row: usize, column: usize) -> Result<Expr, Error> {
+        let extern_sheets = vec![
+            Some((String::from("Data Sheet"), String::from("Data Sheet"))), 
+            Some((String::from("Jan"), String::from("Dec")))
+        ]; 
+        let names = vec![XlsbName { name: String::from("_xlfn.CONCAT"), sheet: None, rgce: vec![], rgcb: vec![] }]; 
+        let tables = vec![XlsbTable {
+            id: 3, name: String::from("Sales"), first_row: 1, last_row: 4, first_column: 1, last_column: 2, 
+            header_rows: 1, totals_rows: 0, columns: vec![String::from("Item"), String::from("Amount")]
+        }]; 
+        decode_formula(rgce, rgcb, row, column, &FormulaContext { extern_sheets: &extern_sheets, names: &names, tables: &tables })
+    }
+
+    #[test]
+    fn test_rk_number() {
+        assert_eq!(rk_number((25 << 2) | 0x02), 25.0); 
+        assert_eq!(rk_number((125 << 2) | 0x03), 1.25); 
+        assert_eq!(rk_number(((1.5f64).to_bits() >> 32) as u32), 1.5); 
+    }
+
+    #[test]
+    fn test_decode_formula() -> Result<(), Error> {
+        // SUM(A1:B2)*2
+        let rgce = [0x25, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0xC0, 1, 0xC0, 0x22, 1, 4, 0, 0x1E, 2, 0, 0x05]; 
+        let expr = decode(&rgce, 1, 1)?; 
+        assert_eq!(expr.to_string(), "(SUM(A1:B2)*2)"); 
+        assert_eq!(parse_str(&expr.to_string())?, parse_str("(SUM(A1:B2)*2)")?); 
+        // 'Data Sheet'!$C$3&"x"
+        let rgce = [0x3A, 0, 0, 2, 0, 0, 0, 2, 0, 0x17, 1, 0, b'x', 0, 0x08]; 
+        assert_eq!(decode(&rgce, 1, 1)?.to_string(), "('Data Sheet'!$C$3&\"x\")"); 
+        // SUM(Jan:Dec!$B$2) across the sheets of the second XTI entry
+        let rgce = [0x3A, 1, 0, 1, 0, 0, 0, 1, 0, 0x22, 1, 4, 0]; 
+        assert_eq!(decode(&rgce, 1, 1)?.to_string(), "SUM(Jan:Dec!$B$2)"); 
+        // Relative reference one row up and one column left of C3
+        let rgce = [0x2C, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]; 
+        assert_eq!(decode(&rgce, 3, 3)?.to_string(), "B2"); 
+        // CONCAT("a", A:A) stored as a user defined function
+        let rgce = [0x23, 1, 0, 0, 0, 0x17, 1, 0, b'a', 0, 0x25, 0, 0, 0, 0, 0xFF, 0xFF, 0x0F, 0, 0, 0xC0, 0, 0xC0, 0x22, 3, 0xFF, 0]; 
+        assert_eq!(decode(&rgce, 1, 1)?.to_string(), "CONCAT(\"a\", A:A)"); 
+        // -(15%), the percent applied before the negation
+        let rgce = [0x1E, 15, 0, 0x14, 0x13]; 
+        assert_eq!(decode(&rgce, 1, 1)?.to_string(), "-(15%)"); 
+        assert!(decode(&[0x10], 1, 1).is_err()); 
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_newer_tokens() -> Result<(), Error> {
+        // IF(A1,,2) with the second argument left out
+        let rgce = [0x24, 0, 0, 0, 0, 0, 0xC0, 0x16, 0x1E, 2, 0, 0x22, 3, 1, 0]; 
+        let expr = decode(&rgce, 1, 1)?; 
+        assert_eq!(expr.to_string(), "IF(A1, , 2)"); 
+        assert_eq!(parse_str(&expr.to_string())?, expr); 
+        // IFERROR(A1, 0) by its fixed argument index
+        let rgce = [0x24, 0, 0, 0, 0, 0, 0xC0, 0x1E, 0, 0, 0x21, 0xE0, 0x01]; 
+        assert_eq!(decode(&rgce, 1, 1)?.to_string(), "IFERROR(A1, 0)"); 
+        // The data rows of the Amount column of table 3
+        let rgce = [0x18, 0x19, 0, 0, 0x11, 0, 3, 0, 0, 0, 1, 0, 1, 0]; 
+        assert_eq!(decode(&rgce, 1, 1)?.to_string(), "Sales[[#Data],[Amount]]"); 
+        assert!(decode(&[0x18, 0x19, 0, 0, 0x11, 0, 9, 0, 0, 0, 1, 0, 1, 0], 1, 1).is_err()); 
+        // {1,2;3,4} with two rows
+        let mut rgce = vec![0x20]; 
+        rgce.extend([0; 14]); 
+        let mut rgcb: Vec<u8> = vec![2, 0, 0, 0, 2, 0, 0, 0]; 
+        for x in [1.0f64, 2.0, 3.0, 4.0] {
+            rgcb.push(0x00); 
+            rgcb.extend(x.to_le_bytes()); 
+        }
+        assert_eq!(decode_with(&rgce, &rgcb, 1, 1)?, parse_str("{1,2;3,4}")?); 
+        // Error codes Excel does not write are rejected rather than read as #N/A
+        assert_eq!(error_from_code(0x2A).map(|e| e.to_string()), Some(String::from("#N/A"))); 
+        assert!(error_from_code(0x99).is_none()); 
+        assert!(decode(&[0x1C, 0x99], 1, 1).is_err()); 
+        Ok(())
+    }
+}